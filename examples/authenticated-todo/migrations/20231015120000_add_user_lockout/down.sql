@@ -0,0 +1,2 @@
+ALTER TABLE users DROP COLUMN lockout_end;
+ALTER TABLE users DROP COLUMN access_failed_count;
@@ -0,0 +1,2 @@
+ALTER TABLE users ADD COLUMN access_failed_count INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN lockout_end BIGINT;
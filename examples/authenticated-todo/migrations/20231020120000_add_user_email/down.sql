@@ -0,0 +1,3 @@
+ALTER TABLE users DROP COLUMN security_stamp;
+ALTER TABLE users DROP COLUMN email_confirmed;
+ALTER TABLE users DROP COLUMN email;
\ No newline at end of file
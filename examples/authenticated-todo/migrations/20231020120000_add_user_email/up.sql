@@ -0,0 +1,3 @@
+ALTER TABLE users ADD COLUMN email VARCHAR;
+ALTER TABLE users ADD COLUMN email_confirmed BOOLEAN NOT NULL DEFAULT 0;
+ALTER TABLE users ADD COLUMN security_stamp VARCHAR NOT NULL DEFAULT '';
\ No newline at end of file
@@ -0,0 +1,8 @@
+CREATE TABLE user_logins (
+    provider VARCHAR NOT NULL,
+    provider_key VARCHAR NOT NULL,
+    user_id INTEGER NOT NULL REFERENCES users (id) ON DELETE CASCADE,
+    PRIMARY KEY (provider, provider_key)
+);
+
+CREATE INDEX user_logins_user_id ON user_logins (user_id);
\ No newline at end of file
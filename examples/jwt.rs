@@ -1,4 +1,3 @@
-use jsonwebtoken::{DecodingKey, EncodingKey};
 use rocket::{
     fairing::AdHoc,
     response::status::Unauthorized,
@@ -6,7 +5,7 @@ use rocket::{
     Orbit, Rocket,
 };
 use rocket_identity::{
-    schemes::jwt::{JwtBearer, JwtConfig, JwtToken, JwtTokenProvider},
+    schemes::jwt::{JwtToken, JwtTokenProvider},
     stores::memory::MemoryStore,
     {Identity, Services, User, UserRepository},
 };
@@ -73,19 +72,14 @@ fn rocket() -> _ {
     // that actually persists users
     let user_store = MemoryStore::new();
 
-    // This should be read from configuration
-    let secret = b"My Secret";
-    let jwt_config = JwtConfig {
-        encoding_key: EncodingKey::from_secret(secret),
-        deconding_key: DecodingKey::from_secret(secret),
-    };
+    // The JWT scheme is configured from the `identity.jwt` settings. Set the
+    // secret in Rocket.toml or with e.g. ROCKET_IDENTITY='{jwt={secret="..."}}',
+    // the value below is only a fallback for trying out the example
+    let figment = rocket::Config::figment().join(("identity.jwt.secret", "My Secret"));
 
-    let config = Identity::config()
-        .with_user_store(user_store)
-        .add_scheme(JwtBearer::new(jwt_config))
-        .build();
+    let config = Identity::config().with_user_store(user_store).build();
 
-    rocket::build()
+    rocket::custom(figment)
         .mount("/", routes![login, index, admin])
         .attach(Identity::fairing(config))
         .attach(AdHoc::on_liftoff("User setup", |r| {
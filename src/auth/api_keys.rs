@@ -0,0 +1,147 @@
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use rand::{rngs::OsRng, RngCore};
+use rocket::time::OffsetDateTime;
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    stores::{ApiKeyStoreError, StoredApiKey, UserApiKeyStoreScope, UserStoreScope},
+    ClaimValue, Roles, User, UserRepository,
+};
+
+/// Identifies API keys of this crate, e.g. for secret scanning.
+const API_KEY_PREFIX: &str = "rik_";
+
+impl UserRepository {
+    /// All API keys issued to the user.
+    pub async fn api_keys(&self, user: &User) -> Result<Vec<StoredApiKey>, ApiKeyError> {
+        let mut user_store = self.user_store.write().await;
+        let api_keys = Self::api_key_store(user_store.as_mut())?;
+
+        Ok(api_keys.user_api_keys(user).await?)
+    }
+
+    /// Issue a new API key to the user. Returns the key, which has to be handed to the
+    /// client now since only its hash is stored, and the stored key.
+    pub async fn create_api_key(
+        &self,
+        user: &User,
+        name: &str,
+        scopes: &[&str],
+        expires: Option<OffsetDateTime>,
+    ) -> Result<(String, StoredApiKey), ApiKeyError> {
+        let mut id = [0; 8];
+        OsRng.fill_bytes(&mut id);
+        let id = HEXLOWER.encode(&id);
+
+        let mut secret = [0; 32];
+        OsRng.fill_bytes(&mut secret);
+        let secret = BASE64URL_NOPAD.encode(&secret);
+
+        let api_key = StoredApiKey {
+            id: id.clone(),
+            name: name.to_owned(),
+            secret_hash: Sha256::digest(secret.as_bytes()).to_vec(),
+            scopes: scopes.iter().map(|s| (*s).to_owned()).collect(),
+            created: OffsetDateTime::now_utc(),
+            expires,
+            last_used: None,
+        };
+
+        let mut user_store = self.user_store.write().await;
+        let api_keys = Self::api_key_store(user_store.as_mut())?;
+        api_keys.add_api_key(user, &api_key).await?;
+
+        Ok((format!("{}{}_{}", API_KEY_PREFIX, id, secret), api_key))
+    }
+
+    /// Revoke an API key of the user.
+    pub async fn revoke_api_key(&self, user: &User, id: &str) -> Result<(), ApiKeyError> {
+        let mut user_store = self.user_store.write().await;
+        let api_keys = Self::api_key_store(user_store.as_mut())?;
+
+        Ok(api_keys.remove_api_key(user, id).await?)
+    }
+
+    /// Find the user an API key was issued to. The roles of the user are replaced by the
+    /// scopes of the key, and the id of the key is added as the `api_key` claim.
+    pub async fn authenticate_api_key(&self, key: &str) -> Result<User, ApiKeyError> {
+        let Some((id, secret)) = key
+            .trim()
+            .strip_prefix(API_KEY_PREFIX)
+            .and_then(|key| key.split_once('_'))
+        else {
+            return Err(ApiKeyError::InvalidKey);
+        };
+
+        let mut user_store = self.user_store.write().await;
+        let api_keys = Self::api_key_store(user_store.as_mut())?;
+
+        let Some((mut user, mut api_key)) = api_keys.find_api_key(id).await? else {
+            return Err(ApiKeyError::InvalidKey);
+        };
+
+        let secret_hash = Sha256::digest(secret.as_bytes());
+        if !bool::from(secret_hash.as_slice().ct_eq(&api_key.secret_hash)) {
+            return Err(ApiKeyError::InvalidKey);
+        }
+
+        let now = OffsetDateTime::now_utc();
+        if api_key.expires.is_some_and(|expires| expires <= now) {
+            return Err(ApiKeyError::Expired);
+        }
+
+        api_key.last_used = Some(now);
+        api_keys.update_api_key(&user, &api_key).await?;
+
+        user.roles = Roles::from(api_key.scopes.clone());
+        user.claims.add("api_key", ClaimValue::String(api_key.id));
+
+        Ok(user)
+    }
+
+    fn api_key_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserApiKeyStoreScope, ApiKeyError> {
+        user_store.api_keys().ok_or_else(|| {
+            log::error!("The configured UserStore does not support API keys");
+            ApiKeyError::NotSupported
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeyError {
+    #[error("the user store does not support API keys")]
+    NotSupported,
+
+    #[error("the API key is invalid")]
+    InvalidKey,
+
+    #[error("the API key has expired")]
+    Expired,
+
+    #[error("no API key with this id exists")]
+    UnknownKey,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("API key operation failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<ApiKeyStoreError> for ApiKeyError {
+    fn from(e: ApiKeyStoreError) -> Self {
+        log::error!("Failed to access API keys: {}", e);
+
+        match e {
+            ApiKeyStoreError::UserNotFound => Self::UserNotFound,
+            ApiKeyStoreError::ApiKeyNotFound => Self::UnknownKey,
+            ApiKeyStoreError::ApiKeyExists => {
+                Self::Other("generated API key id is not unique".into())
+            }
+            ApiKeyStoreError::Other(e) => Self::Other(e),
+        }
+    }
+}
@@ -1,5 +1,7 @@
 use std::collections::HashMap;
 
+use rocket::serde::json::Value;
+
 /// A collection of claims about a User
 #[derive(Debug, Clone, Default)]
 pub struct Claims {
@@ -34,6 +36,13 @@ impl Claims {
     pub fn contains(&self, name: &str) -> bool {
         self.claims.contains_key(name)
     }
+
+    /// Iterate over the names and values of all claims
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &ClaimValue)> {
+        self.claims
+            .iter()
+            .map(|(name, value)| (name.as_str(), value))
+    }
 }
 
 /// A representation of valid claim values.
@@ -63,6 +72,24 @@ impl ClaimValue {
             _ => None,
         }
     }
+
+    /// Convert a JSON value, e.g. a claim of a token. Returns None for null.
+    pub(crate) fn from_json(value: Value) -> Option<Self> {
+        match value {
+            Value::Null => None,
+            Value::Bool(b) => Some(Self::Bool(b)),
+            Value::Number(n) => n.as_i64().map(Self::Int).or(n.as_f64().map(Self::Float)),
+            Value::String(s) => Some(Self::String(s)),
+            Value::Array(a) => Some(Self::Array(
+                a.into_iter().filter_map(Self::from_json).collect(),
+            )),
+            Value::Object(o) => Some(Self::Object(
+                o.into_iter()
+                    .filter_map(|(k, v)| Self::from_json(v).map(|v| (k, v)))
+                    .collect(),
+            )),
+        }
+    }
 }
 
 impl TryFrom<ClaimValue> for String {
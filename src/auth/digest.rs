@@ -0,0 +1,165 @@
+use rocket::time::OffsetDateTime;
+
+use crate::{
+    stores::{DigestCredentials, DigestStoreError, UserDigestStoreScope, UserStoreScope},
+    util::BoxableError,
+    LoginError, User, UserRepository,
+};
+
+impl UserRepository {
+    /// Allow the user to sign in with HTTP Digest authentication in the given realm.
+    /// Digest authentication cannot verify password hashes, so the credentials for the
+    /// realm are derived from the password and stored separately. They are updated
+    /// whenever the password changes.
+    pub async fn set_digest_password(
+        &self,
+        user: &User,
+        realm: &str,
+        password: &str,
+    ) -> Result<(), DigestError> {
+        let mut user_store = self.user_store.write().await;
+        let digest = Self::digest_store(user_store.as_mut())?;
+
+        let credentials = DigestCredentials::new(user, realm, password);
+
+        Ok(digest.set_digest_credentials(user, &credentials).await?)
+    }
+
+    /// Stop the user from signing in with HTTP Digest authentication in the given realm.
+    pub async fn remove_digest_password(
+        &self,
+        user: &User,
+        realm: &str,
+    ) -> Result<(), DigestError> {
+        let mut user_store = self.user_store.write().await;
+        let digest = Self::digest_store(user_store.as_mut())?;
+
+        Ok(digest.remove_digest_credentials(user, realm).await?)
+    }
+
+    /// Authenticate a user with the digest credentials for a realm. `verify` checks the
+    /// response of the client against the stored credentials. Rate limiting, lockout and
+    /// the checks after signing in apply as with [`authenticate`].
+    ///
+    /// [`authenticate`]: UserRepository::authenticate
+    pub(crate) async fn authenticate_digest(
+        &self,
+        username: &str,
+        realm: &str,
+        verify: impl FnOnce(&DigestCredentials) -> bool + Send,
+    ) -> Result<User, LoginError> {
+        self.check_rate_limit(&self.username_policy.normalize(username))
+            .await?;
+
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let user = self.find_user(user_store, username).await.map_err(|e| {
+            log::error!("Failed to find user: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        let Some(user) = user else {
+            return Err(LoginError::UserNotFound);
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let lockout = self.lockout_state(user_store, &user).await.map_err(|e| {
+            log::error!("Failed to retrieve lockout state: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+            return Err(LoginError::LockedOut { until });
+        }
+
+        let Some(digest) = user_store.digest() else {
+            log::error!("The configured UserStore does not support digest credentials");
+            return Err(LoginError::Other(DigestError::NotSupported.boxed()));
+        };
+
+        let credentials = digest.user_digest_credentials(&user).await.map_err(|e| {
+            log::error!("Failed to retrieve digest credentials: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        let Some(credentials) = credentials.iter().find(|c| c.realm == realm) else {
+            return Err(LoginError::MissingPassword);
+        };
+
+        if !verify(credentials) {
+            let locked_out_until = self
+                .record_failed_attempt(user_store, &user, lockout, now)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update lockout state: {}", e);
+                    LoginError::Other(e.boxed())
+                })?;
+
+            return match locked_out_until {
+                Some(until) => Err(LoginError::LockedOut { until }),
+                None => Err(LoginError::IncorrectPassword),
+            };
+        }
+
+        self.reset_failed_attempts(user_store, &user, lockout)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to reset lockout state: {}", e);
+                LoginError::Other(e.boxed())
+            })?;
+
+        self.finish_login(user_store, user).await
+    }
+
+    /// Derive the digest credentials of all realms the user has credentials for from a
+    /// new password, so they do not keep accepting the previous password.
+    pub(crate) async fn update_digest_credentials(
+        user_store: &mut dyn UserStoreScope,
+        user: &User,
+        password: &str,
+    ) -> Result<(), DigestStoreError> {
+        let Some(digest) = user_store.digest() else {
+            return Ok(());
+        };
+
+        for credentials in digest.user_digest_credentials(user).await? {
+            let credentials = DigestCredentials::new(user, &credentials.realm, password);
+            digest.set_digest_credentials(user, &credentials).await?;
+        }
+
+        Ok(())
+    }
+
+    fn digest_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserDigestStoreScope, DigestError> {
+        user_store.digest().ok_or_else(|| {
+            log::error!("The configured UserStore does not support digest credentials");
+            DigestError::NotSupported
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DigestError {
+    #[error("the user store does not support digest credentials")]
+    NotSupported,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("digest credentials operation failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<DigestStoreError> for DigestError {
+    fn from(e: DigestStoreError) -> Self {
+        log::error!("Failed to access digest credentials: {}", e);
+
+        match e {
+            DigestStoreError::UserNotFound => Self::UserNotFound,
+            DigestStoreError::Other(e) => Self::Other(e),
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::{
+    notifiers::{Notification, NotifierError},
+    stores::{EmailError, SecurityStampError, UserStoreScope},
+    tokens::TokenError,
+    util::BoxableError,
+    User, UserRepository,
+};
+
+const EMAIL_CONFIRMATION: &str = "EmailConfirmation";
+
+impl UserRepository {
+    /// Set the email address of the user. A new address has to be confirmed again.
+    pub async fn set_email(
+        &self,
+        user: &User,
+        email: Option<&str>,
+    ) -> Result<(), EmailChangeError> {
+        let email = email.map(str::trim).filter(|e| !e.is_empty());
+
+        if email.is_some_and(|e| !e.contains('@')) {
+            return Err(EmailChangeError::InvalidEmail);
+        }
+
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        user_store.set_email(user, email, false).await?;
+
+        // Tokens sent to the previous address must not confirm the new one
+        self.update_security_stamp(user_store, user).await?;
+
+        Ok(())
+    }
+
+    /// Generate a token confirming the current email address of the user, to be sent
+    /// to that address, e.g. as part of a link.
+    pub async fn generate_email_confirmation_token(
+        &self,
+        user: &User,
+    ) -> Result<String, EmailConfirmationError> {
+        let user_store = self.user_store.read().await;
+
+        let user = Self::current_user(user_store.as_ref(), user).await?;
+        let Some(email) = &user.email else {
+            return Err(EmailConfirmationError::MissingEmail);
+        };
+
+        let security_stamp = user_store.security_stamp(&user).await?;
+
+        Ok(self.token_provider.generate(
+            EMAIL_CONFIRMATION,
+            &user,
+            &security_stamp,
+            email,
+            self.token_provider.email_confirmation_lifetime,
+        ))
+    }
+
+    /// Generate an email confirmation token and send it to the user through the
+    /// configured notifier.
+    pub async fn send_email_confirmation(&self, user: &User) -> Result<(), EmailConfirmationError> {
+        let token = self.generate_email_confirmation_token(user).await?;
+
+        let user = {
+            let user_store = self.user_store.read().await;
+            Self::current_user(user_store.as_ref(), user).await?
+        };
+        let Some(email) = user.email.clone() else {
+            return Err(EmailConfirmationError::MissingEmail);
+        };
+
+        self.notify(&Notification::EmailConfirmation { user, email, token })
+            .await?;
+
+        Ok(())
+    }
+
+    /// Confirm the email address of the user with a token generated by
+    /// [`generate_email_confirmation_token`].
+    ///
+    /// [`generate_email_confirmation_token`]: UserRepository::generate_email_confirmation_token
+    pub async fn confirm_email(
+        &self,
+        user: &User,
+        token: &str,
+    ) -> Result<(), EmailConfirmationError> {
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let user = Self::current_user(user_store, user).await?;
+        let Some(email) = &user.email else {
+            return Err(EmailConfirmationError::MissingEmail);
+        };
+
+        let security_stamp = user_store.security_stamp(&user).await?;
+
+        self.token_provider
+            .verify(token, EMAIL_CONFIRMATION, &user, &security_stamp, email)?;
+
+        user_store.set_email(&user, Some(email), true).await?;
+
+        Ok(())
+    }
+
+    /// The user as currently stored, since the email address may have changed since
+    /// the given user was loaded.
+    async fn current_user(
+        user_store: &dyn UserStoreScope,
+        user: &User,
+    ) -> Result<User, EmailConfirmationError> {
+        user_store
+            .find_user_by_username(&user.username)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to find user: {}", e);
+                EmailConfirmationError::Other(e.boxed())
+            })?
+            .ok_or(EmailConfirmationError::UserNotFound)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailChangeError {
+    #[error("the user store does not support email confirmation")]
+    NotSupported,
+
+    #[error("the email address is invalid")]
+    InvalidEmail,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("email address could not be changed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailConfirmationError {
+    #[error("the user store does not support email confirmation")]
+    NotSupported,
+
+    #[error("the user has no email address")]
+    MissingEmail,
+
+    #[error("the confirmation token is invalid")]
+    InvalidToken(#[from] TokenError),
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("the confirmation token could not be sent")]
+    Notifier(#[from] NotifierError),
+
+    #[error("email address could not be confirmed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<EmailError> for EmailChangeError {
+    fn from(e: EmailError) -> Self {
+        log::error!("Failed to set email: {}", e);
+
+        match e {
+            EmailError::NotSupported => Self::NotSupported,
+            EmailError::UserNotFound => Self::UserNotFound,
+            EmailError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+impl From<SecurityStampError> for EmailChangeError {
+    fn from(e: SecurityStampError) -> Self {
+        log::error!("Failed to update security stamp: {}", e);
+
+        match e {
+            SecurityStampError::NotSupported => Self::NotSupported,
+            SecurityStampError::UserNotFound => Self::UserNotFound,
+            SecurityStampError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+impl From<EmailError> for EmailConfirmationError {
+    fn from(e: EmailError) -> Self {
+        log::error!("Failed to set email: {}", e);
+
+        match e {
+            EmailError::NotSupported => Self::NotSupported,
+            EmailError::UserNotFound => Self::UserNotFound,
+            EmailError::Other(e) => Self::Other(e),
+        }
+    }
+}
+
+impl From<SecurityStampError> for EmailConfirmationError {
+    fn from(e: SecurityStampError) -> Self {
+        log::error!("Failed to retrieve security stamp: {}", e);
+
+        match e {
+            SecurityStampError::NotSupported => Self::NotSupported,
+            SecurityStampError::UserNotFound => Self::UserNotFound,
+            SecurityStampError::Other(e) => Self::Other(e),
+        }
+    }
+}
@@ -0,0 +1,333 @@
+use rocket::time::OffsetDateTime;
+
+use crate::{
+    external::{ExternalIdentity, OidcError, PendingAuthorization},
+    stores::{ExternalLogin, ExternalLoginStoreError, UserExternalLoginStoreScope, UserStoreScope},
+    util::BoxableError,
+    AddUserError, LoginError, User, UserRepository,
+};
+
+impl UserRepository {
+    /// Start signing in with an external provider. Returns the URL to redirect the user
+    /// to and the state that has to be remembered until the user returns.
+    pub fn start_external_login(
+        &self,
+        provider: &str,
+    ) -> Result<(String, PendingAuthorization), ExternalLoginError> {
+        let Some(provider) = self.external_providers.get(provider) else {
+            return Err(ExternalLoginError::UnknownProvider);
+        };
+
+        Ok(provider.authorization_request())
+    }
+
+    /// Complete signing in with an external provider once the user returned with an
+    /// authorization code, and sign in the local user linked to the external identity.
+    /// See [`authenticate_external`].
+    ///
+    /// [`authenticate_external`]: UserRepository::authenticate_external
+    pub async fn finish_external_login(
+        &self,
+        pending: &PendingAuthorization,
+        state: &str,
+        code: &str,
+    ) -> Result<User, ExternalLoginError> {
+        let identity = self.exchange_external_code(pending, state, code).await?;
+
+        self.authenticate_external(&identity).await
+    }
+
+    /// Complete an authorization request started with [`start_external_login`] by
+    /// linking the external identity to an existing user instead of signing in, e.g.
+    /// from the account settings of a signed in user.
+    ///
+    /// [`start_external_login`]: UserRepository::start_external_login
+    pub async fn finish_external_link(
+        &self,
+        user: &User,
+        pending: &PendingAuthorization,
+        state: &str,
+        code: &str,
+    ) -> Result<ExternalLogin, ExternalLoginError> {
+        let identity = self.exchange_external_code(pending, state, code).await?;
+
+        let login = ExternalLogin {
+            provider: identity.provider,
+            provider_key: identity.subject,
+        };
+        self.add_external_login(user, &login).await?;
+
+        Ok(login)
+    }
+
+    /// All external identities linked to the user.
+    pub async fn external_logins(
+        &self,
+        user: &User,
+    ) -> Result<Vec<ExternalLogin>, ExternalLoginError> {
+        let mut user_store = self.user_store.write().await;
+        let logins = Self::external_login_store(user_store.as_mut())?;
+
+        Ok(logins.user_logins(user).await?)
+    }
+
+    /// Link an external identity to the user. Fails if the identity is already linked to
+    /// any user.
+    pub async fn add_external_login(
+        &self,
+        user: &User,
+        login: &ExternalLogin,
+    ) -> Result<(), ExternalLoginError> {
+        let mut user_store = self.user_store.write().await;
+        let logins = Self::external_login_store(user_store.as_mut())?;
+
+        Ok(logins.add_login(user, login).await?)
+    }
+
+    /// Unlink an external identity from the user. Fails if the user could not log in
+    /// anymore afterwards, i.e. if the user has neither a password, a passkey nor
+    /// another linked identity.
+    pub async fn remove_external_login(
+        &self,
+        user: &User,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<(), ExternalLoginError> {
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let has_password = user_store
+            .password_hash(user)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to retrieve password hash: {}", e);
+                ExternalLoginError::Other(e.boxed())
+            })?
+            .is_some();
+
+        let has_passkeys = match user_store.passkeys() {
+            Some(passkeys) => !passkeys
+                .user_passkeys(user)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to retrieve passkeys: {}", e);
+                    ExternalLoginError::Other(e.boxed())
+                })?
+                .is_empty(),
+            None => false,
+        };
+
+        let logins = Self::external_login_store(user_store)?;
+        let has_other_logins = logins
+            .user_logins(user)
+            .await?
+            .iter()
+            .any(|l| l.provider != provider || l.provider_key != provider_key);
+
+        if !has_password && !has_passkeys && !has_other_logins {
+            return Err(ExternalLoginError::LastLogin);
+        }
+
+        Ok(logins.remove_login(user, provider, provider_key).await?)
+    }
+
+    async fn exchange_external_code(
+        &self,
+        pending: &PendingAuthorization,
+        state: &str,
+        code: &str,
+    ) -> Result<ExternalIdentity, ExternalLoginError> {
+        let Some(provider) = self.external_providers.get(&pending.provider) else {
+            return Err(ExternalLoginError::UnknownProvider);
+        };
+
+        let Some(http_client) = self.external_providers.http_client() else {
+            log::error!("Cannot sign in with external providers without an HttpClient");
+            return Err(ExternalLoginError::NoHttpClient);
+        };
+
+        Ok(provider
+            .exchange_code(http_client, pending, state, code)
+            .await
+            .map_err(|e| {
+                log::warn!("Sign-in with {} failed: {}", pending.provider, e);
+                e
+            })?)
+    }
+
+    /// Sign in the user linked to an external identity. If no user is linked yet, a new
+    /// user is created; existing users are never linked automatically, since the
+    /// provider cannot prove the identity belongs to them. Lockout, confirmed email and
+    /// two-factor requirements apply as for [`authenticate`].
+    ///
+    /// The claims of the external identity are added to the returned user.
+    ///
+    /// [`authenticate`]: UserRepository::authenticate
+    pub async fn authenticate_external(
+        &self,
+        identity: &ExternalIdentity,
+    ) -> Result<User, ExternalLoginError> {
+        let user = self.find_or_create_external_user(identity).await?;
+
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let lockout = self.lockout_state(user_store, &user).await.map_err(|e| {
+            log::error!("Failed to retrieve lockout state: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        let now = OffsetDateTime::now_utc();
+        if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+            return Err(LoginError::LockedOut { until }.into());
+        }
+
+        let mut user = self.finish_login(user_store, user).await?;
+        for (name, value) in identity.claims.iter() {
+            user.claims.add(name, value.clone());
+        }
+
+        Ok(user)
+    }
+
+    async fn find_or_create_external_user(
+        &self,
+        identity: &ExternalIdentity,
+    ) -> Result<User, ExternalLoginError> {
+        let mut user_store = self.user_store.write().await;
+        let logins = Self::external_login_store(user_store.as_mut())?;
+
+        if let Some(user) = logins
+            .find_user_by_login(&identity.provider, &identity.subject)
+            .await?
+        {
+            return Ok(user);
+        }
+
+        drop(user_store);
+
+        let user = self.create_external_user(identity).await?;
+
+        let mut user_store = self.user_store.write().await;
+        let logins = Self::external_login_store(user_store.as_mut())?;
+
+        logins
+            .add_login(
+                &user,
+                &ExternalLogin {
+                    provider: identity.provider.clone(),
+                    provider_key: identity.subject.clone(),
+                },
+            )
+            .await?;
+
+        log::info!(
+            "Created user {} for {} identity {}",
+            user.username,
+            identity.provider,
+            identity.subject
+        );
+
+        Ok(user)
+    }
+
+    /// Add a user without a password for an external identity. The username is taken
+    /// from the identity if possible, falling back to one derived from the provider.
+    async fn create_external_user(
+        &self,
+        identity: &ExternalIdentity,
+    ) -> Result<User, ExternalLoginError> {
+        let candidates = [
+            identity.preferred_username.clone(),
+            identity.email.clone(),
+            Some(format!("{}-{}", identity.provider, identity.subject)),
+        ];
+
+        for candidate in candidates.into_iter().flatten() {
+            let Ok(username) = self.username_policy.validate(&candidate) else {
+                continue;
+            };
+
+            if self
+                .find_by_username(&username)
+                .await
+                .map_err(|e| ExternalLoginError::Other(e.boxed()))?
+                .is_some()
+            {
+                continue;
+            }
+
+            let user = User {
+                email: identity.email.clone(),
+                email_confirmed: identity.email_verified,
+                ..User::with_username(username)
+            };
+
+            match self.add_user(&user, None).await {
+                Ok(()) => return Ok(user),
+                Err(AddUserError::UsernameExists | AddUserError::InvalidUsername(_)) => continue,
+                Err(e) => return Err(ExternalLoginError::Other(e.boxed())),
+            }
+        }
+
+        Err(ExternalLoginError::NoUsername)
+    }
+
+    fn external_login_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserExternalLoginStoreScope, ExternalLoginError> {
+        user_store.external_logins().ok_or_else(|| {
+            log::error!("The configured UserStore does not support external logins");
+            ExternalLoginError::NotSupported
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExternalLoginError {
+    #[error("the user store does not support external logins")]
+    NotSupported,
+
+    #[error("no external provider with this name is configured")]
+    UnknownProvider,
+
+    #[error("no HttpClient is configured")]
+    NoHttpClient,
+
+    #[error("the external provider did not confirm the identity")]
+    Provider(#[from] OidcError),
+
+    #[error("no valid username is available for the external identity")]
+    NoUsername,
+
+    #[error("the external identity is already linked to a user")]
+    AlreadyLinked,
+
+    #[error("the external identity is not linked to the user")]
+    UnknownLogin,
+
+    #[error("the user would not be able to log in without the external login")]
+    LastLogin,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("user could not be logged in")]
+    Login(#[from] LoginError),
+
+    #[error("external login failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<ExternalLoginStoreError> for ExternalLoginError {
+    fn from(e: ExternalLoginStoreError) -> Self {
+        log::error!("Failed to access external logins: {}", e);
+
+        match e {
+            ExternalLoginStoreError::UserNotFound => Self::UserNotFound,
+            ExternalLoginStoreError::LoginExists => Self::AlreadyLinked,
+            ExternalLoginStoreError::LoginNotFound => Self::UnknownLogin,
+            ExternalLoginStoreError::Other(e) => Self::Other(e),
+        }
+    }
+}
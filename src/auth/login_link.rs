@@ -0,0 +1,138 @@
+use rocket::time::OffsetDateTime;
+
+use crate::{
+    notifiers::{Notification, NotifierError},
+    stores::SecurityStampError,
+    util::BoxableError,
+    LoginError, User, UserRepository,
+};
+
+const LOGIN_LINK: &str = "LoginLink";
+
+impl UserRepository {
+    /// Generate a token allowing the user to log in without a password using
+    /// [`authenticate_with_login_token`]. The token expires and can only be used once.
+    ///
+    /// [`authenticate_with_login_token`]: UserRepository::authenticate_with_login_token
+    pub async fn generate_login_token(&self, user: &User) -> Result<String, LoginLinkError> {
+        let user_store = self.user_store.read().await;
+        let security_stamp = user_store.security_stamp(user).await?;
+
+        Ok(self.token_provider.generate(
+            LOGIN_LINK,
+            user,
+            &security_stamp,
+            "",
+            self.token_provider.login_link_lifetime,
+        ))
+    }
+
+    /// Generate a login token and send it to the user through the configured notifier.
+    pub async fn send_login_link(&self, user: &User) -> Result<(), LoginLinkError> {
+        let token = self.generate_login_token(user).await?;
+
+        self.notify(&Notification::LoginLink {
+            user: user.clone(),
+            token,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Authenticate a user with a token generated by [`generate_login_token`] instead of
+    /// a password. Lockout, confirmed email and two-factor requirements apply as for
+    /// [`authenticate`].
+    ///
+    /// Using the token replaces the security stamp of the user, so other tokens issued
+    /// before become invalid as well.
+    ///
+    /// [`generate_login_token`]: UserRepository::generate_login_token
+    /// [`authenticate`]: UserRepository::authenticate
+    pub async fn authenticate_with_login_token(
+        &self,
+        username: &str,
+        token: &str,
+    ) -> Result<User, LoginError> {
+        self.check_rate_limit(&self.username_policy.normalize(username))
+            .await?;
+
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let Some(user) = self.find_user(user_store, username).await.map_err(|e| {
+            log::error!("Failed to find user: {}", e);
+            LoginError::Other(e.boxed())
+        })?
+        else {
+            return Err(LoginError::UserNotFound);
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let lockout = self.lockout_state(user_store, &user).await.map_err(|e| {
+            log::error!("Failed to retrieve lockout state: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+            return Err(LoginError::LockedOut { until });
+        }
+
+        let security_stamp = user_store.security_stamp(&user).await.map_err(|e| {
+            log::error!("Failed to retrieve security stamp: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        if let Err(e) = self
+            .token_provider
+            .verify(token, LOGIN_LINK, &user, &security_stamp, "")
+        {
+            log::warn!("Rejected login token for {}: {}", user.username, e);
+            return Err(LoginError::InvalidLoginToken);
+        }
+
+        // Consume the token
+        self.update_security_stamp(user_store, &user)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update security stamp: {}", e);
+                LoginError::Other(e.boxed())
+            })?;
+
+        self.reset_failed_attempts(user_store, &user, lockout)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to reset lockout state: {}", e);
+                LoginError::Other(e.boxed())
+            })?;
+
+        self.finish_login(user_store, user).await
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LoginLinkError {
+    #[error("the user store does not support security stamps")]
+    NotSupported,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("the login link could not be sent")]
+    Notifier(#[from] NotifierError),
+
+    #[error("login link could not be created")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<SecurityStampError> for LoginLinkError {
+    fn from(e: SecurityStampError) -> Self {
+        log::error!("Failed to retrieve security stamp: {}", e);
+
+        match e {
+            SecurityStampError::NotSupported => Self::NotSupported,
+            SecurityStampError::UserNotFound => Self::UserNotFound,
+            SecurityStampError::Other(e) => Self::Other(e),
+        }
+    }
+}
@@ -1,9 +1,27 @@
+mod api_keys;
 mod claims;
+mod digest;
+mod email;
+mod external_logins;
+mod login_link;
+mod passkeys;
+mod password_reset;
 mod repository;
 mod roles;
+mod signing_keys;
+mod two_factor;
 mod user;
 
+pub use api_keys::*;
 pub use claims::*;
+pub use digest::*;
+pub use email::*;
+pub use external_logins::*;
+pub use login_link::*;
+pub use passkeys::*;
+pub use password_reset::*;
 pub use repository::*;
 pub use roles::*;
+pub use signing_keys::*;
+pub use two_factor::*;
 pub use user::*;
@@ -0,0 +1,169 @@
+use rocket::time::OffsetDateTime;
+
+use crate::{
+    stores::{Passkey, PasskeyStoreError, UserPasskeyStoreScope, UserStoreScope},
+    util::BoxableError,
+    webauthn::{
+        AssertionCredential, CreationOptions, PasskeyChallenge, RegistrationCredential,
+        RequestOptions, WebAuthnError,
+    },
+    LoginError, User, UserRepository,
+};
+
+impl UserRepository {
+    /// All passkeys registered for the user.
+    pub async fn passkeys(&self, user: &User) -> Result<Vec<Passkey>, PasskeyError> {
+        let mut user_store = self.user_store.write().await;
+        let passkeys = Self::passkey_store(user_store.as_mut())?;
+
+        Ok(passkeys.user_passkeys(user).await?)
+    }
+
+    /// Start registering a new passkey for the user. The options are passed to the
+    /// browser, the challenge has to be kept until [`finish_passkey_registration`].
+    ///
+    /// [`finish_passkey_registration`]: UserRepository::finish_passkey_registration
+    pub async fn start_passkey_registration(
+        &self,
+        user: &User,
+    ) -> Result<(CreationOptions, PasskeyChallenge), PasskeyError> {
+        let existing = self.passkeys(user).await?;
+
+        Ok(self.webauthn.start_registration(&user.username, &existing))
+    }
+
+    /// Verify the response of the authenticator and store the new passkey.
+    pub async fn finish_passkey_registration(
+        &self,
+        user: &User,
+        challenge: &PasskeyChallenge,
+        credential: &RegistrationCredential,
+    ) -> Result<Passkey, PasskeyError> {
+        if challenge.username.as_ref() != Some(&user.username) {
+            return Err(WebAuthnError::UserMismatch.into());
+        }
+
+        let passkey = self.webauthn.finish_registration(challenge, credential)?;
+
+        let mut user_store = self.user_store.write().await;
+        let passkeys = Self::passkey_store(user_store.as_mut())?;
+        passkeys.add_passkey(user, &passkey).await?;
+
+        Ok(passkey)
+    }
+
+    /// Start signing in with a passkey. Without a user, any discoverable credential
+    /// registered with this application is accepted.
+    pub async fn start_passkey_authentication(
+        &self,
+        user: Option<&User>,
+    ) -> Result<(RequestOptions, PasskeyChallenge), PasskeyError> {
+        let allowed = match user {
+            Some(user) => self.passkeys(user).await?,
+            None => Vec::new(),
+        };
+
+        Ok(self
+            .webauthn
+            .start_authentication(user.map(|u| u.username.as_str()), &allowed))
+    }
+
+    /// Verify the response of the authenticator and return the user the passkey
+    /// belongs to. Lockout, confirmed email and two-factor requirements apply as for
+    /// [`authenticate`].
+    ///
+    /// [`authenticate`]: UserRepository::authenticate
+    pub async fn finish_passkey_authentication(
+        &self,
+        challenge: &PasskeyChallenge,
+        credential: &AssertionCredential,
+    ) -> Result<User, PasskeyError> {
+        let credential_id = credential.credential_id()?;
+
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let passkeys = Self::passkey_store(user_store)?;
+        let Some((user, mut passkey)) = passkeys.find_passkey(&credential_id).await? else {
+            return Err(PasskeyError::UnknownCredential);
+        };
+
+        // Refuse locked out users before looking at the assertion
+        let now = OffsetDateTime::now_utc();
+        let lockout = self.lockout_state(user_store, &user).await.map_err(|e| {
+            log::error!("Failed to retrieve lockout state: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+            return Err(LoginError::LockedOut { until }.into());
+        }
+
+        passkey.sign_count =
+            self.webauthn
+                .verify_assertion(challenge, &user.username, &passkey, credential)?;
+
+        let passkeys = Self::passkey_store(user_store)?;
+        passkeys.update_passkey(&user, &passkey).await?;
+
+        Ok(self.finish_login(user_store, user).await?)
+    }
+
+    /// Remove a passkey of the user.
+    pub async fn remove_passkey(
+        &self,
+        user: &User,
+        credential_id: &[u8],
+    ) -> Result<(), PasskeyError> {
+        let mut user_store = self.user_store.write().await;
+        let passkeys = Self::passkey_store(user_store.as_mut())?;
+
+        Ok(passkeys.remove_passkey(user, credential_id).await?)
+    }
+
+    fn passkey_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserPasskeyStoreScope, PasskeyError> {
+        user_store.passkeys().ok_or_else(|| {
+            log::error!("The configured UserStore does not support passkeys");
+            PasskeyError::NotSupported
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeyError {
+    #[error("the user store does not support passkeys")]
+    NotSupported,
+
+    #[error("passkey could not be verified")]
+    WebAuthn(#[from] WebAuthnError),
+
+    #[error("no passkey with this credential id is registered")]
+    UnknownCredential,
+
+    #[error("the passkey is already registered")]
+    CredentialExists,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("user could not be signed in")]
+    Login(#[from] LoginError),
+
+    #[error("passkey operation failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<PasskeyStoreError> for PasskeyError {
+    fn from(e: PasskeyStoreError) -> Self {
+        log::error!("Failed to access passkeys: {}", e);
+
+        match e {
+            PasskeyStoreError::UserNotFound => Self::UserNotFound,
+            PasskeyStoreError::PasskeyNotFound => Self::UnknownCredential,
+            PasskeyStoreError::CredentialExists => Self::CredentialExists,
+            PasskeyStoreError::Other(e) => Self::Other(e),
+        }
+    }
+}
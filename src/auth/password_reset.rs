@@ -0,0 +1,131 @@
+use crate::{
+    notifiers::{Notification, NotifierError},
+    stores::SecurityStampError,
+    tokens::TokenError,
+    util::BoxableError,
+    validators::PasswordViolation,
+    User, UserRepository,
+};
+
+const PASSWORD_RESET: &str = "PasswordReset";
+
+impl UserRepository {
+    /// Generate a token allowing to reset the password of the user with
+    /// [`reset_password`]. The token expires, and becomes invalid once the password
+    /// changes, so it can only be used once.
+    ///
+    /// [`reset_password`]: UserRepository::reset_password
+    pub async fn generate_password_reset_token(
+        &self,
+        user: &User,
+    ) -> Result<String, PasswordResetError> {
+        let user_store = self.user_store.read().await;
+        let security_stamp = user_store.security_stamp(user).await?;
+
+        Ok(self.token_provider.generate(
+            PASSWORD_RESET,
+            user,
+            &security_stamp,
+            "",
+            self.token_provider.password_reset_lifetime,
+        ))
+    }
+
+    /// Generate a password reset token and send it to the user through the configured
+    /// notifier.
+    pub async fn send_password_reset(&self, user: &User) -> Result<(), PasswordResetError> {
+        let token = self.generate_password_reset_token(user).await?;
+
+        self.notify(&Notification::PasswordReset {
+            user: user.clone(),
+            token,
+        })
+        .await?;
+
+        Ok(())
+    }
+
+    /// Set a new password for the user with a token generated by
+    /// [`generate_password_reset_token`].
+    ///
+    /// [`generate_password_reset_token`]: UserRepository::generate_password_reset_token
+    pub async fn reset_password(
+        &self,
+        user: &User,
+        token: &str,
+        new_password: &str,
+    ) -> Result<(), PasswordResetError> {
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let security_stamp = user_store.security_stamp(user).await?;
+
+        self.token_provider
+            .verify(token, PASSWORD_RESET, user, &security_stamp, "")?;
+
+        self.password_validator
+            .validate(user, new_password)
+            .map_err(PasswordResetError::InvalidPassword)?;
+
+        let password_hash = self
+            .password_hasher
+            .hash_password(user, new_password)
+            .map_err(|e| {
+                log::error!("Failed to hash password: {}", e);
+                PasswordResetError::Other(e)
+            })?;
+
+        user_store
+            .set_password_hash(user, &password_hash)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to set password hash: {}", e);
+                PasswordResetError::Other(e.boxed())
+            })?;
+
+        Self::update_digest_credentials(user_store, user, new_password)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update digest credentials: {}", e);
+                PasswordResetError::Other(e.boxed())
+            })?;
+
+        // Invalidates the token that was just used
+        self.update_security_stamp(user_store, user).await?;
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasswordResetError {
+    #[error("the user store does not support security stamps")]
+    NotSupported,
+
+    #[error("the reset token is invalid")]
+    InvalidToken(#[from] TokenError),
+
+    #[error("password does not satisfy the password policy")]
+    InvalidPassword(Vec<PasswordViolation>),
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("the reset token could not be sent")]
+    Notifier(#[from] NotifierError),
+
+    #[error("password could not be reset")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<SecurityStampError> for PasswordResetError {
+    fn from(e: SecurityStampError) -> Self {
+        log::error!("Failed to access security stamp: {}", e);
+
+        match e {
+            SecurityStampError::NotSupported => Self::NotSupported,
+            SecurityStampError::UserNotFound => Self::UserNotFound,
+            SecurityStampError::Other(e) => Self::Other(e),
+        }
+    }
+}
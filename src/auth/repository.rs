@@ -1,21 +1,47 @@
-use std::sync::Arc;
+use std::{net::IpAddr, sync::Arc, time::Duration};
 
 use rocket::{
     request::{FromRequest, Outcome},
+    time::OffsetDateTime,
     Request, Sentinel,
 };
 use tokio::sync::RwLock;
 
 use crate::{
+    config::{LockoutOptions, SignInOptions},
+    external::ExternalProviders,
     hashers::PasswordHasher,
-    stores::{UserStore, UserStoreScope},
+    notifiers::{Notification, Notifier, NotifierError},
+    rate_limit::{memory::MemoryRateLimitStore, RateLimitOptions, RateLimitStore},
+    stores::{
+        LockoutState, LockoutStateError, RenameUserError, SecurityStampError,
+        UserLockoutStoreScope, UserStore, UserStoreScope,
+    },
+    tokens::TokenProvider,
+    two_factor::Totp,
     util::BoxableError,
+    validators::{
+        PasswordPolicy, PasswordValidator, PasswordViolation, UsernamePolicy, UsernameViolation,
+    },
+    webauthn::WebAuthn,
     Services, User,
 };
 
 pub struct UserRepository {
     pub user_store: RwLock<Box<dyn UserStoreScope>>,
     pub password_hasher: Arc<dyn PasswordHasher>,
+    pub password_validator: Arc<dyn PasswordValidator>,
+    pub username_policy: Arc<UsernamePolicy>,
+    pub lockout_options: LockoutOptions,
+    pub sign_in_options: SignInOptions,
+    pub rate_limit_store: Arc<dyn RateLimitStore>,
+    pub rate_limit_options: RateLimitOptions,
+    pub client_ip: Option<IpAddr>,
+    pub totp: Arc<Totp>,
+    pub webauthn: Arc<WebAuthn>,
+    pub token_provider: Arc<TokenProvider>,
+    pub notifier: Option<Arc<dyn Notifier>>,
+    pub external_providers: Arc<ExternalProviders>,
 }
 
 impl UserRepository {
@@ -26,14 +52,90 @@ impl UserRepository {
         Self {
             user_store: RwLock::new(user_store),
             password_hasher,
+            password_validator: Arc::new(PasswordPolicy::default()),
+            username_policy: Arc::new(UsernamePolicy::default()),
+            lockout_options: LockoutOptions::default(),
+            sign_in_options: SignInOptions::default(),
+            rate_limit_store: Arc::new(MemoryRateLimitStore::new()),
+            rate_limit_options: RateLimitOptions::disabled(),
+            client_ip: None,
+            totp: Arc::new(Totp::default()),
+            webauthn: Arc::new(WebAuthn::default()),
+            token_provider: Arc::new(TokenProvider::default()),
+            notifier: None,
+            external_providers: Arc::new(ExternalProviders::new()),
         }
     }
 
+    pub fn with_password_validator(
+        mut self,
+        password_validator: Arc<dyn PasswordValidator>,
+    ) -> Self {
+        self.password_validator = password_validator;
+        self
+    }
+
+    pub fn with_username_policy(mut self, username_policy: Arc<UsernamePolicy>) -> Self {
+        self.username_policy = username_policy;
+        self
+    }
+
+    pub fn with_lockout_options(mut self, lockout_options: LockoutOptions) -> Self {
+        self.lockout_options = lockout_options;
+        self
+    }
+
+    pub fn with_sign_in_options(mut self, sign_in_options: SignInOptions) -> Self {
+        self.sign_in_options = sign_in_options;
+        self
+    }
+
+    pub fn with_rate_limiting(
+        mut self,
+        rate_limit_store: Arc<dyn RateLimitStore>,
+        rate_limit_options: RateLimitOptions,
+    ) -> Self {
+        self.rate_limit_store = rate_limit_store;
+        self.rate_limit_options = rate_limit_options;
+        self
+    }
+
+    /// Set the IP address of the client on whose behalf this repository is used.
+    /// Login attempts are rate limited per client IP if it is known.
+    pub fn with_client_ip(mut self, client_ip: Option<IpAddr>) -> Self {
+        self.client_ip = client_ip;
+        self
+    }
+
+    pub fn with_totp(mut self, totp: Arc<Totp>) -> Self {
+        self.totp = totp;
+        self
+    }
+
+    pub fn with_webauthn(mut self, webauthn: Arc<WebAuthn>) -> Self {
+        self.webauthn = webauthn;
+        self
+    }
+
+    pub fn with_token_provider(mut self, token_provider: Arc<TokenProvider>) -> Self {
+        self.token_provider = token_provider;
+        self
+    }
+
+    pub fn with_notifier(mut self, notifier: Option<Arc<dyn Notifier>>) -> Self {
+        self.notifier = notifier;
+        self
+    }
+
+    pub fn with_external_providers(mut self, external_providers: Arc<ExternalProviders>) -> Self {
+        self.external_providers = external_providers;
+        self
+    }
+
     pub async fn find_by_username(&self, username: &str) -> Result<Option<User>, FindUserError> {
         let user_store = self.user_store.read().await;
 
-        user_store
-            .find_user_by_username(username)
+        self.find_user(user_store.as_ref(), username)
             .await
             .map_err(|e| {
                 log::error!("Failed to find user by username: {}", e);
@@ -42,27 +144,39 @@ impl UserRepository {
     }
 
     pub async fn authenticate(&self, username: &str, password: &str) -> Result<User, LoginError> {
-        let user_store = self.user_store.read().await;
+        self.check_rate_limit(&self.username_policy.normalize(username))
+            .await?;
 
-        let user = user_store
-            .find_user_by_username(username)
-            .await
-            .map_err(|e| {
-                log::error!("Failed to find user: {}", e);
-                LoginError::Other(e.boxed())
-            })?;
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let user = self.find_user(user_store, username).await.map_err(|e| {
+            log::error!("Failed to find user: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
 
         let Some(user) = user else {
-            return Err(LoginError::UserNotFound);
+            return Err(self.fail_without_hash(password, LoginError::UserNotFound));
         };
 
+        // Refuse locked out users before looking at the password
+        let now = OffsetDateTime::now_utc();
+        let lockout = self.lockout_state(user_store, &user).await.map_err(|e| {
+            log::error!("Failed to retrieve lockout state: {}", e);
+            LoginError::Other(e.boxed())
+        })?;
+
+        if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+            return Err(self.fail_without_hash(password, LoginError::LockedOut { until }));
+        }
+
         let password_hash = user_store.password_hash(&user).await.map_err(|e| {
             log::error!("Failed to retrieve password hash: {}", e);
             LoginError::Other(e.boxed())
         })?;
 
         let Some(password_hash) = password_hash else {
-            return Err(LoginError::MissingPassword);
+            return Err(self.fail_without_hash(password, LoginError::MissingPassword));
         };
 
         if !self
@@ -73,13 +187,205 @@ impl UserRepository {
                 LoginError::Other(e)
             })?
         {
-            return Err(LoginError::IncorrectPassword);
+            let locked_out_until = self
+                .record_failed_attempt(user_store, &user, lockout, now)
+                .await
+                .map_err(|e| {
+                    log::error!("Failed to update lockout state: {}", e);
+                    LoginError::Other(e.boxed())
+                })?;
+
+            return match locked_out_until {
+                Some(until) => Err(LoginError::LockedOut { until }),
+                None => Err(LoginError::IncorrectPassword),
+            };
+        }
+
+        // Reset failed attempts after a successful login
+        self.reset_failed_attempts(user_store, &user, lockout)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to reset lockout state: {}", e);
+                LoginError::Other(e.boxed())
+            })?;
+
+        self.finish_login(user_store, user).await
+    }
+
+    /// Checks that apply after the credentials of the user were verified, regardless of
+    /// how the user logged in.
+    pub(crate) async fn finish_login(
+        &self,
+        user_store: &mut dyn UserStoreScope,
+        user: User,
+    ) -> Result<User, LoginError> {
+        if self.sign_in_options.require_confirmed_email && !user.email_confirmed {
+            return Err(LoginError::EmailNotConfirmed);
+        }
+
+        // Users with two-factor authentication are not signed in by their first factor alone
+        if let Some(two_factor) = user_store.two_factor() {
+            let state = two_factor.two_factor_state(&user).await.map_err(|e| {
+                log::error!("Failed to retrieve two-factor state: {}", e);
+                LoginError::Other(e.boxed())
+            })?;
+
+            if state.enabled {
+                return Err(LoginError::TwoFactorRequired {
+                    user: Box::new(user),
+                });
+            }
         }
 
         Ok(user)
     }
 
+    /// Find a user by the normalized username.
+    pub(crate) async fn find_user(
+        &self,
+        user_store: &dyn UserStoreScope,
+        username: &str,
+    ) -> Result<Option<User>, crate::stores::FindUserError> {
+        let username = self.username_policy.normalize(username);
+
+        user_store.find_user_by_username(&username).await
+    }
+
+    pub(crate) async fn lockout_state(
+        &self,
+        user_store: &mut dyn UserStoreScope,
+        user: &User,
+    ) -> Result<LockoutState, LockoutStateError> {
+        if !self.lockout_options.enabled {
+            return Ok(LockoutState::default());
+        }
+
+        let lockout_store = Self::lockout_store(user_store)?;
+
+        lockout_store.lockout_state(user).await
+    }
+
+    /// Count a failed attempt towards the lockout of a user. Returns the end of the
+    /// lockout if the user is now locked out.
+    pub(crate) async fn record_failed_attempt(
+        &self,
+        user_store: &mut dyn UserStoreScope,
+        user: &User,
+        lockout: LockoutState,
+        now: OffsetDateTime,
+    ) -> Result<Option<OffsetDateTime>, LockoutStateError> {
+        if !self.lockout_options.enabled {
+            return Ok(None);
+        }
+
+        let mut lockout = LockoutState {
+            failed_attempts: lockout.failed_attempts + 1,
+            locked_out_until: None,
+        };
+
+        if lockout.failed_attempts >= self.lockout_options.max_failed_attempts {
+            lockout.failed_attempts = 0;
+            lockout.locked_out_until = Some(now + self.lockout_options.lockout_duration);
+            log::warn!("Locking out user {}", user.username);
+        }
+
+        let lockout_store = Self::lockout_store(user_store)?;
+        lockout_store.set_lockout_state(user, &lockout).await?;
+
+        Ok(lockout.locked_out_until)
+    }
+
+    pub(crate) async fn reset_failed_attempts(
+        &self,
+        user_store: &mut dyn UserStoreScope,
+        user: &User,
+        lockout: LockoutState,
+    ) -> Result<(), LockoutStateError> {
+        if lockout == LockoutState::default() {
+            return Ok(());
+        }
+
+        let lockout_store = Self::lockout_store(user_store)?;
+
+        lockout_store
+            .set_lockout_state(user, &LockoutState::default())
+            .await
+    }
+
+    fn lockout_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserLockoutStoreScope, LockoutStateError> {
+        user_store.lockout().ok_or_else(|| {
+            log::error!("The configured UserStore does not support lockouts");
+            LockoutStateError::NotSupported
+        })
+    }
+
+    /// Verify the password against the hasher's dummy hash before failing, so that
+    /// failing without a password hash takes as long as failing with one. Otherwise
+    /// usernames could be enumerated by timing the response.
+    fn fail_without_hash(&self, password: &str, err: LoginError) -> LoginError {
+        let user = User::with_username("");
+
+        let verified = self
+            .password_hasher
+            .dummy_hash()
+            .and_then(|hash| self.password_hasher.verify_password(&user, &hash, password));
+
+        if let Err(e) = verified {
+            log::error!("Failed to verify dummy password: {}", e);
+        }
+
+        err
+    }
+
+    pub(crate) async fn check_rate_limit(&self, username: &str) -> Result<(), LoginError> {
+        let options = self.rate_limit_options;
+
+        let limits = [
+            options
+                .per_ip
+                .zip(self.client_ip.map(|ip| format!("ip:{}", ip))),
+            options
+                .per_username
+                .map(|bucket| (bucket, format!("username:{}", username))),
+        ];
+
+        for (bucket, key) in limits.into_iter().flatten() {
+            if let Err(retry_after) = self.rate_limit_store.try_acquire(&key, &bucket).await {
+                log::warn!("Rate limited login attempt for {}", key);
+                return Err(LoginError::RateLimited { retry_after });
+            }
+        }
+
+        Ok(())
+    }
+
     pub async fn add_user(&self, user: &User, password: Option<&str>) -> Result<(), AddUserError> {
+        // Normalize the username. Basic authentication separates the username from the
+        // password at the first colon, so users with a colon could never sign in with it.
+        let user = &User {
+            username: self
+                .username_policy
+                .validate(&user.username)
+                .and_then(|username| {
+                    if username.contains(':') {
+                        Err(vec![UsernameViolation::InvalidCharacter(':')])
+                    } else {
+                        Ok(username)
+                    }
+                })
+                .map_err(AddUserError::InvalidUsername)?,
+            ..user.clone()
+        };
+
+        // Validate the user password
+        if let Some(password) = password {
+            self.password_validator
+                .validate(user, password)
+                .map_err(AddUserError::InvalidPassword)?;
+        }
+
         // Hash the user password
         let password_hash = password
             .map(|p| self.password_hasher.hash_password(user, p))
@@ -89,11 +395,12 @@ impl UserRepository {
                 AddUserError::Other(e)
             })?;
 
-        let mut user_store_guard = self.user_store.write().await;
-        let user_store = user_store_guard.as_mut();
+        let security_stamp = self.token_provider.generate_security_stamp();
+
+        let mut user_store = self.user_store.write().await;
 
         user_store
-            .add_user(user, password_hash.as_ref())
+            .add_user_with_security_stamp(user, password_hash.as_ref(), &security_stamp)
             .await
             .map_err(|e| {
                 log::error!("Failed to add user: {}", e);
@@ -102,6 +409,132 @@ impl UserRepository {
 
         Ok(())
     }
+
+    /// Store the usernames of all users in their normalized form. Users are only found
+    /// by their normalized username, so this has to run after the username policy was
+    /// introduced or changed. Users whose normalized username is taken by another user
+    /// are left unchanged and returned, so that they can be resolved by hand.
+    ///
+    /// HTTP Digest credentials are derived from the username and have to be added again
+    /// for renamed users.
+    pub async fn normalize_usernames(&self) -> Result<Vec<String>, NormalizeUsernamesError> {
+        let mut user_store = self.user_store.write().await;
+
+        let usernames = user_store.usernames().await.map_err(|e| {
+            log::error!("Failed to list usernames: {}", e);
+            NormalizeUsernamesError::from(e)
+        })?;
+
+        let mut conflicts = Vec::new();
+        for username in usernames {
+            let normalized = self.username_policy.normalize(&username);
+            if normalized == username {
+                continue;
+            }
+
+            match user_store.rename_user(&username, &normalized).await {
+                Ok(()) => log::info!("Renamed user {} to {}", username, normalized),
+                Err(RenameUserError::UsernameExists) => conflicts.push(username),
+                Err(e) => {
+                    log::error!("Failed to rename user: {}", e);
+                    return Err(e.into());
+                }
+            }
+        }
+
+        Ok(conflicts)
+    }
+
+    pub async fn change_password(
+        &self,
+        user: &User,
+        current_password: &str,
+        new_password: &str,
+    ) -> Result<(), ChangePasswordError> {
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let password_hash = user_store.password_hash(user).await.map_err(|e| {
+            log::error!("Failed to retrieve password hash: {}", e);
+            ChangePasswordError::Other(e.boxed())
+        })?;
+
+        let Some(password_hash) = password_hash else {
+            return Err(ChangePasswordError::IncorrectPassword);
+        };
+
+        if !self
+            .password_hasher
+            .verify_password(user, &password_hash, current_password)
+            .map_err(|e| {
+                log::error!("Failed to verify password: {}", e);
+                ChangePasswordError::Other(e)
+            })?
+        {
+            return Err(ChangePasswordError::IncorrectPassword);
+        }
+
+        self.password_validator
+            .validate(user, new_password)
+            .map_err(ChangePasswordError::InvalidPassword)?;
+
+        let password_hash = self
+            .password_hasher
+            .hash_password(user, new_password)
+            .map_err(|e| {
+                log::error!("Failed to hash password: {}", e);
+                ChangePasswordError::Other(e)
+            })?;
+
+        user_store
+            .set_password_hash(user, &password_hash)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to set password hash: {}", e);
+                ChangePasswordError::Other(e.boxed())
+            })?;
+
+        Self::update_digest_credentials(user_store, user, new_password)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update digest credentials: {}", e);
+                ChangePasswordError::Other(e.boxed())
+            })?;
+
+        self.update_security_stamp(user_store, user)
+            .await
+            .map_err(|e| {
+                log::error!("Failed to update security stamp: {}", e);
+                ChangePasswordError::Other(e.boxed())
+            })?;
+
+        Ok(())
+    }
+
+    /// Deliver a notification through the configured notifier.
+    pub(crate) async fn notify(&self, notification: &Notification) -> Result<(), NotifierError> {
+        let Some(notifier) = &self.notifier else {
+            log::error!("Cannot send notification because no Notifier is configured");
+            return Err(NotifierError::NotConfigured);
+        };
+
+        notifier.send(notification).await.map_err(|e| {
+            log::error!("Failed to send notification: {}", e);
+            e
+        })
+    }
+
+    /// Replace the security stamp of the user, which invalidates all tokens issued
+    /// for the user so far.
+    pub(crate) async fn update_security_stamp(
+        &self,
+        user_store: &mut dyn UserStoreScope,
+        user: &User,
+    ) -> Result<(), SecurityStampError> {
+        let security_stamp = self.token_provider.generate_security_stamp();
+
+        user_store.set_security_stamp(user, &security_stamp).await
+    }
 }
 
 #[rocket::async_trait]
@@ -149,6 +582,21 @@ pub enum LoginError {
     #[error("provided password is incorrect")]
     IncorrectPassword,
 
+    #[error("provided login token is invalid")]
+    InvalidLoginToken,
+
+    #[error("user is locked out until {until}")]
+    LockedOut { until: OffsetDateTime },
+
+    #[error("too many login attempts, retry after {retry_after:?}")]
+    RateLimited { retry_after: Duration },
+
+    #[error("the email address of the user is not confirmed")]
+    EmailNotConfirmed,
+
+    #[error("user has to provide a second factor")]
+    TwoFactorRequired { user: Box<User> },
+
     #[error("user could not be authenticated")]
     Other(#[from] Box<dyn std::error::Error>),
 }
@@ -158,10 +606,37 @@ pub enum AddUserError {
     #[error("a user with the given username already exists")]
     UsernameExists,
 
+    #[error("username does not satisfy the username policy")]
+    InvalidUsername(Vec<UsernameViolation>),
+
+    #[error("password does not satisfy the password policy")]
+    InvalidPassword(Vec<PasswordViolation>),
+
     #[error("user could not be added")]
     Other(#[from] Box<dyn std::error::Error>),
 }
 
+#[derive(Debug, thiserror::Error)]
+pub enum NormalizeUsernamesError {
+    #[error("the user store does not support renaming users")]
+    NotSupported,
+
+    #[error("usernames could not be normalized")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChangePasswordError {
+    #[error("provided password is incorrect")]
+    IncorrectPassword,
+
+    #[error("password does not satisfy the password policy")]
+    InvalidPassword(Vec<PasswordViolation>),
+
+    #[error("password could not be changed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
 impl From<crate::stores::AddUserError> for AddUserError {
     fn from(e: crate::stores::AddUserError) -> Self {
         match e {
@@ -170,3 +645,12 @@ impl From<crate::stores::AddUserError> for AddUserError {
         }
     }
 }
+
+impl From<RenameUserError> for NormalizeUsernamesError {
+    fn from(e: RenameUserError) -> Self {
+        match e {
+            RenameUserError::NotSupported => Self::NotSupported,
+            e => Self::Other(e.boxed()),
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use data_encoding::{BASE64URL_NOPAD, HEXLOWER};
+use rand::{rngs::OsRng, RngCore};
+use rocket::time::OffsetDateTime;
+
+use crate::{
+    stores::{SigningKeyStoreError, StoredSigningKey, UserSigningKeyStoreScope, UserStoreScope},
+    User, UserRepository,
+};
+
+impl UserRepository {
+    /// All request signing keys issued to the user.
+    pub async fn signing_keys(
+        &self,
+        user: &User,
+    ) -> Result<Vec<StoredSigningKey>, SigningKeyError> {
+        let mut user_store = self.user_store.write().await;
+        let signing_keys = Self::signing_key_store(user_store.as_mut())?;
+
+        Ok(signing_keys.user_signing_keys(user).await?)
+    }
+
+    /// Issue a new key for signing requests to the user. The id and secret of the
+    /// returned key have to be shared with the client, which signs requests with them.
+    pub async fn create_signing_key(
+        &self,
+        user: &User,
+        name: &str,
+        scopes: &[&str],
+    ) -> Result<StoredSigningKey, SigningKeyError> {
+        let mut id = [0; 8];
+        OsRng.fill_bytes(&mut id);
+
+        let mut secret = [0; 32];
+        OsRng.fill_bytes(&mut secret);
+
+        let signing_key = StoredSigningKey {
+            id: HEXLOWER.encode(&id),
+            name: name.to_owned(),
+            secret: BASE64URL_NOPAD.encode(&secret),
+            scopes: scopes.iter().map(|s| (*s).to_owned()).collect(),
+            created: OffsetDateTime::now_utc(),
+        };
+
+        let mut user_store = self.user_store.write().await;
+        let signing_keys = Self::signing_key_store(user_store.as_mut())?;
+        signing_keys.add_signing_key(user, &signing_key).await?;
+
+        Ok(signing_key)
+    }
+
+    /// Revoke a signing key of the user.
+    pub async fn revoke_signing_key(&self, user: &User, id: &str) -> Result<(), SigningKeyError> {
+        let mut user_store = self.user_store.write().await;
+        let signing_keys = Self::signing_key_store(user_store.as_mut())?;
+
+        Ok(signing_keys.remove_signing_key(user, id).await?)
+    }
+
+    /// Find a signing key and the user it was issued to.
+    pub(crate) async fn find_signing_key(
+        &self,
+        id: &str,
+    ) -> Result<Option<(User, StoredSigningKey)>, SigningKeyError> {
+        let mut user_store = self.user_store.write().await;
+        let signing_keys = Self::signing_key_store(user_store.as_mut())?;
+
+        Ok(signing_keys.find_signing_key(id).await?)
+    }
+
+    fn signing_key_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserSigningKeyStoreScope, SigningKeyError> {
+        user_store.signing_keys().ok_or_else(|| {
+            log::error!("The configured UserStore does not support signing keys");
+            SigningKeyError::NotSupported
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningKeyError {
+    #[error("the user store does not support signing keys")]
+    NotSupported,
+
+    #[error("no signing key with this id exists")]
+    UnknownKey,
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("signing key operation failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<SigningKeyStoreError> for SigningKeyError {
+    fn from(e: SigningKeyStoreError) -> Self {
+        log::error!("Failed to access signing keys: {}", e);
+
+        match e {
+            SigningKeyStoreError::UserNotFound => Self::UserNotFound,
+            SigningKeyStoreError::SigningKeyNotFound => Self::UnknownKey,
+            SigningKeyStoreError::SigningKeyExists => {
+                Self::Other("generated signing key id is not unique".into())
+            }
+            SigningKeyStoreError::Other(e) => Self::Other(e),
+        }
+    }
+}
@@ -0,0 +1,319 @@
+use rocket::time::OffsetDateTime;
+
+use crate::{
+    stores::{TwoFactorState, TwoFactorStateError, UserStoreScope, UserTwoFactorStoreScope},
+    two_factor::{generate_recovery_codes, normalize_recovery_code, TotpSecret},
+    util::BoxableError,
+    User, UserRepository,
+};
+
+impl UserRepository {
+    /// Check whether the user has to provide a second factor to log in.
+    pub async fn is_two_factor_enabled(&self, user: &User) -> Result<bool, TwoFactorError> {
+        let mut user_store = self.user_store.write().await;
+
+        let Some(two_factor) = user_store.two_factor() else {
+            return Ok(false);
+        };
+
+        Ok(two_factor.two_factor_state(user).await?.enabled)
+    }
+
+    /// Generate and store a new TOTP secret for the user. Two-factor authentication is
+    /// disabled until the user confirms the secret using [`enable_two_factor`].
+    ///
+    /// [`enable_two_factor`]: UserRepository::enable_two_factor
+    pub async fn create_totp_secret(&self, user: &User) -> Result<TotpSecret, TwoFactorError> {
+        let mut user_store = self.user_store.write().await;
+        let two_factor = Self::two_factor_store(user_store.as_mut())?;
+
+        let secret = self.totp.generate_secret();
+
+        // Keep the rest of the state, only the authenticator is replaced
+        let mut state = two_factor.two_factor_state(user).await?;
+        state.enabled = false;
+        state.totp_secret = Some(secret.as_bytes().to_vec());
+        state.last_totp_step = None;
+
+        two_factor.set_two_factor_state(user, &state).await?;
+
+        Ok(secret)
+    }
+
+    /// The `otpauth://` URI to add the given secret to an authenticator app.
+    pub fn totp_provisioning_uri(&self, user: &User, secret: &TotpSecret) -> String {
+        self.totp.provisioning_uri(secret, &user.username)
+    }
+
+    /// Enable two-factor authentication once the user proves they can generate codes
+    /// for the secret created by [`create_totp_secret`].
+    ///
+    /// [`create_totp_secret`]: UserRepository::create_totp_secret
+    pub async fn enable_two_factor(&self, user: &User, code: &str) -> Result<(), TwoFactorError> {
+        let mut user_store = self.user_store.write().await;
+        let two_factor = Self::two_factor_store(user_store.as_mut())?;
+
+        let mut state = two_factor.two_factor_state(user).await?;
+        state.last_totp_step = Some(self.verify_totp(&state, code)?);
+        state.enabled = true;
+
+        two_factor.set_two_factor_state(user, &state).await?;
+
+        Ok(())
+    }
+
+    /// Disable two-factor authentication and forget the TOTP secret.
+    pub async fn disable_two_factor(&self, user: &User) -> Result<(), TwoFactorError> {
+        let mut user_store = self.user_store.write().await;
+        let two_factor = Self::two_factor_store(user_store.as_mut())?;
+
+        two_factor
+            .set_two_factor_state(user, &TwoFactorState::default())
+            .await?;
+
+        Ok(())
+    }
+
+    /// Verify a TOTP code as the second factor of a login. Each code can only be used
+    /// once, and failed attempts count towards the lockout of the user.
+    pub async fn verify_two_factor_code(
+        &self,
+        user: &User,
+        code: &str,
+    ) -> Result<(), TwoFactorError> {
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let now = OffsetDateTime::now_utc();
+        let lockout = self
+            .lockout_state(user_store, user)
+            .await
+            .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+        if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+            return Err(TwoFactorError::LockedOut { until });
+        }
+
+        let two_factor = Self::two_factor_store(user_store)?;
+        let mut state = two_factor.two_factor_state(user).await?;
+
+        if !state.enabled {
+            return Err(TwoFactorError::NotEnabled);
+        }
+
+        // Enabling two-factor authentication requires a secret, so only the code can be
+        // invalid here. The error is not kept, since it cannot be held across an await.
+        match self.verify_totp(&state, code).ok() {
+            Some(step) => {
+                state.last_totp_step = Some(step);
+                two_factor.set_two_factor_state(user, &state).await?;
+            }
+            None => {
+                let locked_out_until = self
+                    .record_failed_attempt(user_store, user, lockout, now)
+                    .await
+                    .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+                return match locked_out_until {
+                    Some(until) => Err(TwoFactorError::LockedOut { until }),
+                    None => Err(TwoFactorError::InvalidCode),
+                };
+            }
+        }
+
+        self.reset_failed_attempts(user_store, user, lockout)
+            .await
+            .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+        Ok(())
+    }
+
+    /// Replace the recovery codes of the user with `count` new ones. The codes are only
+    /// stored hashed, so the returned plain codes have to be shown to the user now.
+    pub async fn generate_recovery_codes(
+        &self,
+        user: &User,
+        count: usize,
+    ) -> Result<Vec<String>, TwoFactorError> {
+        let codes = generate_recovery_codes(count);
+        let hashes = codes
+            .iter()
+            .map(|code| {
+                self.password_hasher
+                    .hash_password(user, &normalize_recovery_code(code))
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| {
+                log::error!("Failed to hash recovery code: {}", e);
+                TwoFactorError::Other(e)
+            })?;
+
+        let mut user_store = self.user_store.write().await;
+        let two_factor = Self::two_factor_store(user_store.as_mut())?;
+
+        let mut state = two_factor.two_factor_state(user).await?;
+        state.recovery_codes = hashes;
+
+        two_factor.set_two_factor_state(user, &state).await?;
+
+        Ok(codes)
+    }
+
+    /// The number of recovery codes the user has left.
+    pub async fn count_recovery_codes(&self, user: &User) -> Result<usize, TwoFactorError> {
+        let mut user_store = self.user_store.write().await;
+        let two_factor = Self::two_factor_store(user_store.as_mut())?;
+
+        Ok(two_factor
+            .two_factor_state(user)
+            .await?
+            .recovery_codes
+            .len())
+    }
+
+    /// Use a recovery code instead of a TOTP code as the second factor of a login. The
+    /// code is consumed, and failed attempts count towards the lockout of the user.
+    pub async fn redeem_recovery_code(
+        &self,
+        user: &User,
+        code: &str,
+    ) -> Result<(), TwoFactorError> {
+        let recovery_codes = {
+            let mut user_store_guard = self.user_store.write().await;
+            let user_store = user_store_guard.as_mut();
+
+            let now = OffsetDateTime::now_utc();
+            let lockout = self
+                .lockout_state(user_store, user)
+                .await
+                .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+            if let Some(until) = lockout.locked_out_until.filter(|until| *until > now) {
+                return Err(TwoFactorError::LockedOut { until });
+            }
+
+            let two_factor = Self::two_factor_store(user_store)?;
+            let state = two_factor.two_factor_state(user).await?;
+
+            if !state.enabled {
+                return Err(TwoFactorError::NotEnabled);
+            }
+
+            state.recovery_codes
+        };
+
+        // Recovery codes are hashed like passwords, so they are verified without holding
+        // the lock on the user store
+        let code = normalize_recovery_code(code);
+        let mut matched = None;
+        for hash in recovery_codes {
+            let verified = self
+                .password_hasher
+                .verify_password(user, &hash, &code)
+                .map_err(|e| {
+                    log::error!("Failed to verify recovery code: {}", e);
+                    TwoFactorError::Other(e)
+                })?;
+
+            if verified {
+                matched = Some(hash);
+                break;
+            }
+        }
+
+        let mut user_store_guard = self.user_store.write().await;
+        let user_store = user_store_guard.as_mut();
+
+        let now = OffsetDateTime::now_utc();
+        let lockout = self
+            .lockout_state(user_store, user)
+            .await
+            .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+        let two_factor = Self::two_factor_store(user_store)?;
+        let mut state = two_factor.two_factor_state(user).await?;
+
+        // The code may have been redeemed by a concurrent request in the meantime
+        let index = matched.and_then(|hash| state.recovery_codes.iter().position(|h| *h == hash));
+
+        let Some(index) = index else {
+            let locked_out_until = self
+                .record_failed_attempt(user_store, user, lockout, now)
+                .await
+                .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+            return match locked_out_until {
+                Some(until) => Err(TwoFactorError::LockedOut { until }),
+                None => Err(TwoFactorError::InvalidCode),
+            };
+        };
+
+        state.recovery_codes.remove(index);
+        two_factor.set_two_factor_state(user, &state).await?;
+
+        self.reset_failed_attempts(user_store, user, lockout)
+            .await
+            .map_err(|e| TwoFactorError::Other(e.boxed()))?;
+
+        Ok(())
+    }
+
+    fn verify_totp(&self, state: &TwoFactorState, code: &str) -> Result<u64, TwoFactorError> {
+        let Some(secret) = &state.totp_secret else {
+            return Err(TwoFactorError::NotConfigured);
+        };
+
+        self.totp
+            .verify(
+                &TotpSecret::from(secret.clone()),
+                code,
+                OffsetDateTime::now_utc(),
+                state.last_totp_step,
+            )
+            .ok_or(TwoFactorError::InvalidCode)
+    }
+
+    fn two_factor_store(
+        user_store: &mut dyn UserStoreScope,
+    ) -> Result<&mut dyn UserTwoFactorStoreScope, TwoFactorError> {
+        user_store.two_factor().ok_or_else(|| {
+            log::error!("The configured UserStore does not support two-factor authentication");
+            TwoFactorError::NotSupported
+        })
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TwoFactorError {
+    #[error("the user store does not support two-factor authentication")]
+    NotSupported,
+
+    #[error("user has no authenticator configured")]
+    NotConfigured,
+
+    #[error("two-factor authentication is not enabled for the user")]
+    NotEnabled,
+
+    #[error("the provided code is invalid")]
+    InvalidCode,
+
+    #[error("user is locked out until {until}")]
+    LockedOut { until: OffsetDateTime },
+
+    #[error("user could not be found")]
+    UserNotFound,
+
+    #[error("two-factor authentication failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+impl From<TwoFactorStateError> for TwoFactorError {
+    fn from(e: TwoFactorStateError) -> Self {
+        log::error!("Failed to access two-factor state: {}", e);
+
+        match e {
+            TwoFactorStateError::UserNotFound => Self::UserNotFound,
+            TwoFactorStateError::Other(e) => Self::Other(e),
+        }
+    }
+}
@@ -9,6 +9,8 @@ use crate::{
 #[derive(Debug, Clone)]
 pub struct User {
     pub username: String,
+    pub email: Option<String>,
+    pub email_confirmed: bool,
     pub claims: Claims,
     pub roles: Roles,
 }
@@ -17,6 +19,8 @@ impl User {
     pub fn with_username(username: impl Into<String>) -> Self {
         Self {
             username: username.into(),
+            email: None,
+            email_confirmed: false,
             claims: Claims::new(),
             roles: Roles::new(),
         }
@@ -68,6 +72,14 @@ impl<'r> rocket::request::FromRequest<'r> for &'r User {
     }
 }
 
+/// Returns the error of the `&User` request guard if it failed for the given request.
+pub(crate) fn authentication_error(req: &rocket::Request<'_>) -> Option<AuthenticationError> {
+    match req.local_cache(|| Outcome::<User, AuthenticationError>::Forward(())) {
+        Outcome::Failure((_, err)) => Some(*err),
+        _ => None,
+    }
+}
+
 impl Sentinel for &User {
     fn abort(rocket: &rocket::Rocket<rocket::Ignite>) -> bool {
         let err = "Authentication schemes are not configured. Attach Identity::fairing() on your rocket instance and make sure you have at least one scheme added using add_scheme().";
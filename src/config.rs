@@ -1,9 +1,19 @@
 use std::sync::Arc;
 
+use rocket::{serde::Deserialize, time::Duration};
+
 use crate::{
-    hashers::{argon2::Argon2PasswordHasher, PasswordHasher},
+    external::{ExternalProviders, HttpClient, OidcProvider},
+    hashers::PasswordHasher,
+    notifiers::Notifier,
+    oauth::AuthorizationServer,
+    rate_limit::{memory::MemoryRateLimitStore, RateLimitOptions, RateLimitStore},
     schemes::AuthenticationScheme,
     stores::UserStore,
+    tokens::TokenProvider,
+    two_factor::Totp,
+    validators::{PasswordPolicy, PasswordValidator, UsernamePolicy},
+    webauthn::WebAuthn,
     Identity,
 };
 
@@ -11,8 +21,20 @@ use crate::{
 pub struct Config {
     pub(crate) user_store: Option<Box<dyn UserStore>>,
     pub(crate) password_hasher: Option<Arc<dyn PasswordHasher>>,
+    pub(crate) password_validator: Arc<dyn PasswordValidator>,
+    pub(crate) username_policy: Arc<UsernamePolicy>,
     pub(crate) auth_schemes: Vec<Box<dyn AuthenticationScheme>>,
-    pub(crate) missing_auth_policy: MissingAuthPolicy,
+    pub(crate) missing_auth_policy: Option<MissingAuthPolicy>,
+    pub(crate) lockout_options: LockoutOptions,
+    pub(crate) sign_in_options: SignInOptions,
+    pub(crate) rate_limit_store: Arc<dyn RateLimitStore>,
+    pub(crate) rate_limit_options: RateLimitOptions,
+    pub(crate) totp: Arc<Totp>,
+    pub(crate) webauthn: Arc<WebAuthn>,
+    pub(crate) token_provider: Option<Arc<TokenProvider>>,
+    pub(crate) notifier: Option<Arc<dyn Notifier>>,
+    pub(crate) external_providers: ExternalProviders,
+    pub(crate) authorization_server: Option<AuthorizationServer>,
 }
 
 #[derive(Debug, Default)]
@@ -20,12 +42,58 @@ pub struct ConfigBuilder {
     config: Option<Config>,
 }
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "lowercase")]
 pub enum MissingAuthPolicy {
     Fail,
     Forward,
 }
 
+/// Controls locking out users after repeated failed login attempts.
+#[derive(Debug, Clone, Copy)]
+pub struct LockoutOptions {
+    /// Whether failed attempts are tracked at all.
+    pub enabled: bool,
+
+    /// Number of consecutive failed attempts that trigger a lockout.
+    pub max_failed_attempts: u32,
+
+    /// How long a user stays locked out.
+    pub lockout_duration: Duration,
+}
+
+impl LockoutOptions {
+    /// No lockouts, the default.
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            ..Self::recommended()
+        }
+    }
+
+    /// Lock out users for 5 minutes after 5 consecutive failed attempts.
+    pub fn recommended() -> Self {
+        Self {
+            enabled: true,
+            max_failed_attempts: 5,
+            lockout_duration: Duration::minutes(5),
+        }
+    }
+}
+
+impl Default for LockoutOptions {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
+
+/// Additional requirements a user has to meet to log in.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SignInOptions {
+    /// Refuse to log in users whose email address is not confirmed.
+    pub require_confirmed_email: bool,
+}
+
 impl ConfigBuilder {
     pub fn new() -> Self {
         Self { config: None }
@@ -34,9 +102,21 @@ impl ConfigBuilder {
     fn config(&mut self) -> &mut Config {
         self.config.get_or_insert_with(|| Config {
             user_store: None,
-            password_hasher: Some(Arc::new(Argon2PasswordHasher::new())),
+            password_hasher: None,
+            password_validator: Arc::new(PasswordPolicy::default()),
+            username_policy: Arc::new(UsernamePolicy::default()),
             auth_schemes: Vec::new(),
-            missing_auth_policy: MissingAuthPolicy::Fail,
+            missing_auth_policy: None,
+            lockout_options: LockoutOptions::default(),
+            sign_in_options: SignInOptions::default(),
+            rate_limit_store: Arc::new(MemoryRateLimitStore::new()),
+            rate_limit_options: RateLimitOptions::default(),
+            totp: Arc::new(Totp::default()),
+            webauthn: Arc::new(WebAuthn::default()),
+            token_provider: None,
+            notifier: None,
+            external_providers: ExternalProviders::new(),
+            authorization_server: None,
         })
     }
 
@@ -50,11 +130,91 @@ impl ConfigBuilder {
         self
     }
 
+    pub fn with_password_validator(
+        &mut self,
+        password_validator: impl PasswordValidator,
+    ) -> &mut Self {
+        self.config().password_validator = Arc::new(password_validator);
+        self
+    }
+
+    pub fn with_username_policy(&mut self, username_policy: UsernamePolicy) -> &mut Self {
+        self.config().username_policy = Arc::new(username_policy);
+        self
+    }
+
     pub fn with_missing_auth_policy(
         &mut self,
         missing_auth_policy: MissingAuthPolicy,
     ) -> &mut Self {
-        self.config().missing_auth_policy = missing_auth_policy;
+        self.config().missing_auth_policy = Some(missing_auth_policy);
+        self
+    }
+
+    pub fn with_lockout_options(&mut self, lockout_options: LockoutOptions) -> &mut Self {
+        self.config().lockout_options = lockout_options;
+        self
+    }
+
+    pub fn with_sign_in_options(&mut self, sign_in_options: SignInOptions) -> &mut Self {
+        self.config().sign_in_options = sign_in_options;
+        self
+    }
+
+    pub fn with_rate_limit_store(&mut self, rate_limit_store: impl RateLimitStore) -> &mut Self {
+        self.config().rate_limit_store = Arc::new(rate_limit_store);
+        self
+    }
+
+    pub fn with_rate_limit_options(&mut self, rate_limit_options: RateLimitOptions) -> &mut Self {
+        self.config().rate_limit_options = rate_limit_options;
+        self
+    }
+
+    pub fn with_totp(&mut self, totp: Totp) -> &mut Self {
+        self.config().totp = Arc::new(totp);
+        self
+    }
+
+    pub fn with_webauthn(&mut self, webauthn: WebAuthn) -> &mut Self {
+        self.config().webauthn = Arc::new(webauthn);
+        self
+    }
+
+    pub fn with_token_provider(&mut self, token_provider: TokenProvider) -> &mut Self {
+        self.config().token_provider = Some(Arc::new(token_provider));
+        self
+    }
+
+    pub fn with_notifier(&mut self, notifier: impl Notifier) -> &mut Self {
+        self.config().notifier = Some(Arc::new(notifier));
+        self
+    }
+
+    /// Allow users to sign in with an external OpenID Connect provider. Requires an
+    /// [`HttpClient`] to be configured using [`with_http_client`].
+    ///
+    /// [`with_http_client`]: ConfigBuilder::with_http_client
+    pub fn add_external_provider(&mut self, provider: OidcProvider) -> &mut Self {
+        self.config().external_providers.add(provider);
+        self
+    }
+
+    pub fn with_http_client(&mut self, http_client: impl HttpClient) -> &mut Self {
+        self.config()
+            .external_providers
+            .set_http_client(Arc::new(http_client));
+        self
+    }
+
+    /// Act as an OAuth 2.0 authorization server. Its endpoints are provided by
+    /// [`oauth::routes`](crate::oauth::routes) and require the
+    /// [`JwtBearer`](crate::schemes::jwt::JwtBearer) scheme to sign tokens.
+    pub fn with_authorization_server(
+        &mut self,
+        authorization_server: AuthorizationServer,
+    ) -> &mut Self {
+        self.config().authorization_server = Some(authorization_server);
         self
     }
 
@@ -0,0 +1,44 @@
+use std::error::Error;
+
+/// Performs the HTTP requests external login flows make to providers, e.g. to exchange
+/// an authorization code for tokens. Applications plug in the HTTP client they already
+/// use; tests can answer requests without network access.
+#[rocket::async_trait]
+pub trait HttpClient: Send + Sync + core::fmt::Debug + 'static {
+    /// Send a GET request.
+    async fn get(&self, url: &str) -> Result<HttpResponse, HttpClientError>;
+
+    /// Send a POST request with an `application/x-www-form-urlencoded` body.
+    async fn post_form(
+        &self,
+        url: &str,
+        form: &[(&str, &str)],
+    ) -> Result<HttpResponse, HttpClientError>;
+}
+
+/// The parts of an HTTP response external login flows look at.
+#[derive(Debug, Clone)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            body: body.into(),
+        }
+    }
+
+    /// Whether the status is in the 2xx range.
+    pub fn is_success(&self) -> bool {
+        (200..300).contains(&self.status)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum HttpClientError {
+    #[error("the request could not be sent")]
+    Other(#[from] Box<dyn Error>),
+}
@@ -0,0 +1,24 @@
+use crate::Claims;
+
+/// A user as identified by an external provider after a successful sign-in.
+#[derive(Debug, Clone)]
+pub struct ExternalIdentity {
+    /// The name of the provider the user signed in with.
+    pub provider: String,
+
+    /// The identifier of the user at the provider. Unlike the email address it never
+    /// changes, so it is used to link the identity to a local user.
+    pub subject: String,
+
+    /// The email address of the user, if the provider shared it.
+    pub email: Option<String>,
+
+    /// Whether the provider verified that the email address belongs to the user.
+    pub email_verified: bool,
+
+    /// The username the user prefers, if the provider shared it.
+    pub preferred_username: Option<String>,
+
+    /// All claims of the ID token.
+    pub claims: Claims,
+}
@@ -0,0 +1,9 @@
+mod client;
+mod identity;
+mod oidc;
+mod providers;
+
+pub use client::*;
+pub use identity::*;
+pub use oidc::*;
+pub use providers::*;
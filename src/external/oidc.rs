@@ -0,0 +1,321 @@
+use std::collections::HashMap;
+
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{decode, decode_header, jwk::JwkSet, Algorithm, DecodingKey, Validation};
+use rand::{rngs::OsRng, RngCore};
+use rocket::serde::{
+    json::{serde_json, Value},
+    Deserialize, Serialize,
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{util::percent_encode, ClaimValue, Claims};
+
+use super::{ExternalIdentity, HttpClient, HttpClientError};
+
+/// Signature algorithms accepted for ID tokens. Symmetric algorithms are not accepted,
+/// since anyone knowing the client secret could sign tokens with them.
+const ALLOWED_ALGORITHMS: &[Algorithm] = &[
+    Algorithm::RS256,
+    Algorithm::RS384,
+    Algorithm::RS512,
+    Algorithm::PS256,
+    Algorithm::PS384,
+    Algorithm::PS512,
+    Algorithm::ES256,
+    Algorithm::ES384,
+    Algorithm::EdDSA,
+];
+
+/// The endpoints of an OpenID Connect provider, as listed in its discovery document.
+#[derive(Debug, Clone)]
+pub struct OidcEndpoints {
+    /// Where users are redirected to sign in.
+    pub authorization: String,
+
+    /// Where authorization codes are exchanged for tokens.
+    pub token: String,
+
+    /// Where the keys ID tokens are signed with are published.
+    pub jwks_uri: String,
+}
+
+/// An OpenID Connect provider users can sign in with, using the authorization code flow
+/// with PKCE (RFC 7636).
+#[derive(Debug, Clone)]
+pub struct OidcProvider {
+    /// The name the provider is referred to by, e.g. `google`.
+    pub name: String,
+
+    /// The issuer ID tokens of the provider have to be issued by.
+    pub issuer: String,
+
+    /// The client id of the application registered with the provider.
+    pub client_id: String,
+
+    /// The client secret of the application, if it is a confidential client.
+    pub client_secret: Option<String>,
+
+    pub endpoints: OidcEndpoints,
+
+    /// Where the provider sends users back to after they signed in.
+    pub redirect_uri: String,
+
+    /// The scopes requested from the provider.
+    pub scopes: Vec<String>,
+}
+
+/// The state of an authorization request that has to be remembered until the user
+/// returns from the provider.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub struct PendingAuthorization {
+    pub provider: String,
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde")]
+struct TokenResponse {
+    id_token: String,
+}
+
+impl OidcProvider {
+    pub fn new(
+        name: impl Into<String>,
+        issuer: impl Into<String>,
+        client_id: impl Into<String>,
+        endpoints: OidcEndpoints,
+        redirect_uri: impl Into<String>,
+    ) -> Self {
+        Self {
+            name: name.into(),
+            issuer: issuer.into(),
+            client_id: client_id.into(),
+            client_secret: None,
+            endpoints,
+            redirect_uri: redirect_uri.into(),
+            scopes: vec![
+                "openid".to_owned(),
+                "email".to_owned(),
+                "profile".to_owned(),
+            ],
+        }
+    }
+
+    pub fn with_client_secret(mut self, client_secret: impl Into<String>) -> Self {
+        self.client_secret = Some(client_secret.into());
+        self
+    }
+
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|s| (*s).to_owned()).collect();
+        self
+    }
+
+    /// Create the URL the user is redirected to in order to sign in with the provider,
+    /// and the state to remember until the user returns.
+    pub fn authorization_request(&self) -> (String, PendingAuthorization) {
+        let pending = PendingAuthorization {
+            provider: self.name.clone(),
+            state: random_value(),
+            nonce: random_value(),
+            code_verifier: random_value(),
+        };
+
+        let code_challenge =
+            BASE64URL_NOPAD.encode(&Sha256::digest(pending.code_verifier.as_bytes()));
+        let scope = self.scopes.join(" ");
+
+        let query = [
+            ("response_type", "code"),
+            ("client_id", &self.client_id),
+            ("redirect_uri", &self.redirect_uri),
+            ("scope", &scope),
+            ("state", &pending.state),
+            ("nonce", &pending.nonce),
+            ("code_challenge", &code_challenge),
+            ("code_challenge_method", "S256"),
+        ]
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, percent_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+        let separator = if self.endpoints.authorization.contains('?') {
+            '&'
+        } else {
+            '?'
+        };
+
+        (
+            format!("{}{}{}", self.endpoints.authorization, separator, query),
+            pending,
+        )
+    }
+
+    /// Exchange the authorization code the user returned with for an ID token, and
+    /// validate it.
+    pub async fn exchange_code(
+        &self,
+        client: &dyn HttpClient,
+        pending: &PendingAuthorization,
+        state: &str,
+        code: &str,
+    ) -> Result<ExternalIdentity, OidcError> {
+        if pending.provider != self.name
+            || !bool::from(state.as_bytes().ct_eq(pending.state.as_bytes()))
+        {
+            return Err(OidcError::StateMismatch);
+        }
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("client_id", self.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+
+        if let Some(client_secret) = &self.client_secret {
+            form.push(("client_secret", client_secret));
+        }
+
+        let res = client.post_form(&self.endpoints.token, &form).await?;
+        if !res.is_success() {
+            return Err(OidcError::TokenEndpoint(res.status));
+        }
+
+        let tokens: TokenResponse =
+            serde_json::from_slice(&res.body).map_err(|_| OidcError::InvalidResponse)?;
+
+        let claims = self
+            .validate_id_token(client, &tokens.id_token, &pending.nonce)
+            .await?;
+
+        self.identity(claims)
+    }
+
+    async fn validate_id_token(
+        &self,
+        client: &dyn HttpClient,
+        id_token: &str,
+        nonce: &str,
+    ) -> Result<HashMap<String, Value>, OidcError> {
+        let header = decode_header(id_token)?;
+        if !ALLOWED_ALGORITHMS.contains(&header.alg) {
+            return Err(OidcError::UnsupportedAlgorithm(header.alg));
+        }
+
+        let res = client.get(&self.endpoints.jwks_uri).await?;
+        if !res.is_success() {
+            return Err(OidcError::JwksEndpoint(res.status));
+        }
+
+        let jwks: JwkSet =
+            serde_json::from_slice(&res.body).map_err(|_| OidcError::InvalidResponse)?;
+
+        let jwk = match &header.kid {
+            Some(kid) => jwks.find(kid),
+            None if jwks.keys.len() == 1 => jwks.keys.first(),
+            None => None,
+        };
+
+        let Some(jwk) = jwk.filter(|jwk| match jwk.common.algorithm {
+            Some(alg) => alg == header.alg,
+            None => true,
+        }) else {
+            return Err(OidcError::UnknownKey);
+        };
+
+        let key = DecodingKey::from_jwk(jwk)?;
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&self.issuer]);
+        validation.set_audience(&[&self.client_id]);
+        validation.set_required_spec_claims(&["exp", "iss", "aud", "sub"]);
+
+        let claims = decode::<HashMap<String, Value>>(id_token, &key, &validation)?.claims;
+
+        let token_nonce = claims.get("nonce").and_then(Value::as_str).unwrap_or("");
+        if !bool::from(token_nonce.as_bytes().ct_eq(nonce.as_bytes())) {
+            return Err(OidcError::NonceMismatch);
+        }
+
+        Ok(claims)
+    }
+
+    fn identity(&self, claims: HashMap<String, Value>) -> Result<ExternalIdentity, OidcError> {
+        let read_str = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_owned);
+
+        let Some(subject) = read_str("sub").filter(|s| !s.is_empty()) else {
+            return Err(OidcError::InvalidClaim("sub".to_owned()));
+        };
+
+        let email = read_str("email");
+        let email_verified = email.is_some()
+            && claims
+                .get("email_verified")
+                .and_then(Value::as_bool)
+                .unwrap_or(false);
+        let preferred_username = read_str("preferred_username");
+
+        let mut identity_claims = Claims::new();
+        for (name, value) in claims {
+            if let Some(value) = ClaimValue::from_json(value) {
+                identity_claims.add(&name, value);
+            }
+        }
+
+        Ok(ExternalIdentity {
+            provider: self.name.clone(),
+            subject,
+            email,
+            email_verified,
+            preferred_username,
+            claims: identity_claims,
+        })
+    }
+}
+
+fn random_value() -> String {
+    let mut value = [0; 32];
+    OsRng.fill_bytes(&mut value);
+    BASE64URL_NOPAD.encode(&value)
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OidcError {
+    #[error("the state does not match the pending authorization request")]
+    StateMismatch,
+
+    #[error("the token endpoint responded with status {0}")]
+    TokenEndpoint(u16),
+
+    #[error("the JWKS endpoint responded with status {0}")]
+    JwksEndpoint(u16),
+
+    #[error("the provider sent an invalid response")]
+    InvalidResponse,
+
+    #[error("the ID token is signed with the unsupported algorithm {0:?}")]
+    UnsupportedAlgorithm(Algorithm),
+
+    #[error("the key the ID token is signed with is unknown")]
+    UnknownKey,
+
+    #[error("the ID token is invalid")]
+    InvalidIdToken(#[from] jsonwebtoken::errors::Error),
+
+    #[error("the nonce of the ID token does not match the authorization request")]
+    NonceMismatch,
+
+    #[error("Invalid claim value")]
+    InvalidClaim(String),
+
+    #[error("the request to the provider failed")]
+    Http(#[from] HttpClientError),
+}
@@ -0,0 +1,42 @@
+use std::sync::Arc;
+
+use super::{HttpClient, OidcProvider};
+
+/// The external providers users can sign in with, and the HTTP client used to talk
+/// to them.
+#[derive(Debug, Clone, Default)]
+pub struct ExternalProviders {
+    providers: Vec<OidcProvider>,
+    http_client: Option<Arc<dyn HttpClient>>,
+}
+
+impl ExternalProviders {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, provider: OidcProvider) {
+        self.providers.push(provider);
+    }
+
+    pub fn set_http_client(&mut self, http_client: Arc<dyn HttpClient>) {
+        self.http_client = Some(http_client);
+    }
+
+    /// Find a provider by its name.
+    pub fn get(&self, name: &str) -> Option<&OidcProvider> {
+        self.providers.iter().find(|p| p.name == name)
+    }
+
+    pub fn http_client(&self) -> Option<&dyn HttpClient> {
+        self.http_client.as_deref()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &OidcProvider> {
+        self.providers.iter()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.providers.is_empty()
+    }
+}
@@ -1,3 +1,5 @@
+use std::sync::Arc;
+
 use rocket::{
     fairing::{self, Fairing, Info, Kind},
     http::Status,
@@ -8,7 +10,14 @@ use tokio::sync::RwLock;
 
 use yansi::Paint;
 
-use crate::{config::Config, schemes::AuthenticationSchemes, Identity, Services};
+use crate::{
+    auth::authentication_error,
+    config::{Config, MissingAuthPolicy},
+    hashers::{argon2::Argon2PasswordHasher, CachedDummyHash, PasswordHasher},
+    schemes::{AuthenticationError, AuthenticationSchemes},
+    settings::Settings,
+    Identity, Services,
+};
 
 impl Identity {
     pub fn fairing(config: Config) -> Self {
@@ -30,11 +39,38 @@ impl Fairing for Identity {
     /// On ignition we verify the configuration and setup the necessary managed state.
     async fn on_ignite(&self, rocket: Rocket<Build>) -> fairing::Result {
         let mut rocket = rocket;
-        let config = self.config.write().await.take().expect("Missing config");
+        let mut config = self.config.write().await.take().expect("Missing config");
+
+        // Complete the configuration with the settings from Rocket.toml
+        let settings = Settings::from_figment(rocket.figment())
+            .and_then(|settings| settings.apply(&mut config));
+
+        if let Err(e) = settings {
+            rocket::error!("{}", e);
+            return Err(rocket);
+        }
 
         let user_store = config.user_store;
-        let password_hasher = config.password_hasher;
-        let missing_auth_policy = config.missing_auth_policy;
+        let password_hasher: Arc<dyn PasswordHasher> = Arc::new(CachedDummyHash::new(
+            config
+                .password_hasher
+                .unwrap_or_else(|| Arc::new(Argon2PasswordHasher::new())),
+        ));
+        let password_validator = config.password_validator;
+        let username_policy = config.username_policy;
+        let missing_auth_policy = config
+            .missing_auth_policy
+            .unwrap_or(MissingAuthPolicy::Fail);
+        let lockout_options = config.lockout_options;
+        let sign_in_options = config.sign_in_options;
+        let rate_limit_store = config.rate_limit_store;
+        let rate_limit_options = config.rate_limit_options;
+        let totp = config.totp;
+        let webauthn = config.webauthn;
+        let token_provider = config.token_provider.unwrap_or_default();
+        let notifier = config.notifier;
+        let external_providers = Arc::new(config.external_providers);
+        let authorization_server = config.authorization_server;
         let mut auth_schemes = AuthenticationSchemes::new(config.auth_schemes);
 
         // Allow authentication schemes to setup themselves
@@ -47,14 +83,50 @@ impl Fairing for Identity {
             rocket = rocket.manage(user_store);
         }
 
-        // Add password hasher if configured
-        if let Some(password_hasher) = password_hasher {
-            rocket = rocket.manage(password_hasher);
-        }
+        // Add password hasher
+        rocket = rocket.manage(password_hasher);
+
+        // Add password validator
+        rocket = rocket.manage(password_validator);
+
+        // Add username policy
+        rocket = rocket.manage(username_policy);
 
         // Add missing auth policy
         rocket = rocket.manage(missing_auth_policy);
 
+        // Add lockout options
+        rocket = rocket.manage(lockout_options);
+
+        // Add sign-in options
+        rocket = rocket.manage(sign_in_options);
+
+        // Add rate limiting
+        rocket = rocket.manage(rate_limit_store);
+        rocket = rocket.manage(rate_limit_options);
+
+        // Add TOTP settings
+        rocket = rocket.manage(totp);
+
+        // Add WebAuthn relying party
+        rocket = rocket.manage(webauthn);
+
+        // Add token provider
+        rocket = rocket.manage(token_provider);
+
+        // Add notifier if configured
+        if let Some(notifier) = notifier {
+            rocket = rocket.manage(notifier);
+        }
+
+        // Add external providers
+        rocket = rocket.manage(external_providers);
+
+        // Add authorization server if configured
+        if let Some(authorization_server) = authorization_server {
+            rocket = rocket.manage(authorization_server);
+        }
+
         // Add auth schemes
         rocket = rocket.manage(auth_schemes);
 
@@ -78,8 +150,21 @@ impl Fairing for Identity {
     }
 
     /// On response we check if the response was 401 Unauthorized and if so we add a
-    /// WWW-Authenticate header with the configured authentication schemes.
+    /// WWW-Authenticate header with the configured authentication schemes. If the response
+    /// was 429 Too Many Requests because of throttled authentication, we add a Retry-After
+    /// header.
     async fn on_response<'r>(&self, req: &'r rocket::Request<'_>, res: &mut rocket::Response<'r>) {
+        if res.status() == Status::TooManyRequests {
+            if let Some(AuthenticationError::TooManyRequests { retry_after }) =
+                authentication_error(req)
+            {
+                let seconds = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+                res.set_raw_header("Retry-After", seconds.max(1).to_string());
+            }
+
+            return;
+        }
+
         // Only listen for status 401 Unauthorized
         if res.status() != Status::Unauthorized {
             return;
@@ -89,7 +174,7 @@ impl Fairing for Identity {
         let auth_schemes = req.authentication_schemes();
 
         for scheme in auth_schemes.iter() {
-            scheme.challenge(res).await;
+            scheme.challenge_request(req, res).await;
         }
     }
 }
@@ -16,6 +16,20 @@ impl Argon2PasswordHasher {
             ctx: argon2::Argon2::default(),
         }
     }
+
+    /// Hash passwords with Argon2id using the given memory cost in KiB, number of
+    /// iterations and degree of parallelism.
+    pub fn with_params(
+        memory_cost: u32,
+        iterations: u32,
+        parallelism: u32,
+    ) -> std::result::Result<Self, argon2::Error> {
+        let params = argon2::Params::new(memory_cost, iterations, parallelism, None)?;
+
+        Ok(Self {
+            ctx: argon2::Argon2::new(argon2::Algorithm::Argon2id, argon2::Version::V0x13, params),
+        })
+    }
 }
 
 impl PasswordHasher for Argon2PasswordHasher {
@@ -1,3 +1,5 @@
+use std::sync::{Arc, OnceLock};
+
 use crate::{util::Result, User};
 
 pub trait PasswordHasher: Send + Sync + core::fmt::Debug + 'static {
@@ -9,6 +11,56 @@ pub trait PasswordHasher: Send + Sync + core::fmt::Debug + 'static {
         password_hash: &PasswordHash,
         password: &str,
     ) -> Result<bool>;
+
+    /// A fixed hash to verify against when no real hash is available, e.g. because the
+    /// user does not exist. This keeps the time taken independent of whether the user
+    /// exists. The default hashes an empty password; the configured hasher only
+    /// computes it once.
+    fn dummy_hash(&self) -> Result<PasswordHash> {
+        self.hash_password(&User::with_username(""), "")
+    }
+}
+
+/// Wraps a hasher so that its dummy hash is only computed once and then only verified
+/// against.
+#[derive(Debug)]
+pub(crate) struct CachedDummyHash {
+    hasher: Arc<dyn PasswordHasher>,
+    dummy_hash: OnceLock<PasswordHash>,
+}
+
+impl CachedDummyHash {
+    pub fn new(hasher: Arc<dyn PasswordHasher>) -> Self {
+        Self {
+            hasher,
+            dummy_hash: OnceLock::new(),
+        }
+    }
+}
+
+impl PasswordHasher for CachedDummyHash {
+    fn hash_password(&self, user: &User, password: &str) -> Result<PasswordHash> {
+        self.hasher.hash_password(user, password)
+    }
+
+    fn verify_password(
+        &self,
+        user: &User,
+        password_hash: &PasswordHash,
+        password: &str,
+    ) -> Result<bool> {
+        self.hasher.verify_password(user, password_hash, password)
+    }
+
+    fn dummy_hash(&self) -> Result<PasswordHash> {
+        if let Some(hash) = self.dummy_hash.get() {
+            return Ok(hash.clone());
+        }
+
+        let hash = self.hasher.dummy_hash()?;
+
+        Ok(self.dummy_hash.get_or_init(|| hash).clone())
+    }
 }
 
 #[derive(Debug, Clone, PartialEq, Eq)]
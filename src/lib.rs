@@ -3,10 +3,19 @@ mod fairing;
 mod services;
 
 pub mod config;
+pub mod external;
 pub mod hashers;
+pub mod notifiers;
+pub mod oauth;
+pub mod rate_limit;
 pub mod schemes;
+pub mod settings;
 pub mod stores;
+pub mod tokens;
+pub mod two_factor;
 pub mod util;
+pub mod validators;
+pub mod webauthn;
 
 pub use auth::*;
 pub use fairing::*;
@@ -0,0 +1,44 @@
+use std::sync::{Arc, Mutex};
+
+use crate::notifiers::{Notification, Notifier, NotifierError};
+
+/// Keeps sent notifications in memory instead of delivering them. Useful for tests
+/// and development.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryNotifier {
+    sent: Arc<Mutex<Vec<Notification>>>,
+}
+
+impl MemoryNotifier {
+    pub fn new() -> Self {
+        Self {
+            sent: Arc::new(Mutex::new(Vec::new())),
+        }
+    }
+
+    /// All notifications sent so far. Clones of this notifier share their notifications.
+    pub fn sent(&self) -> Vec<Notification> {
+        self.sent.lock().expect("Notifications poisoned").clone()
+    }
+
+    /// The most recently sent notification.
+    pub fn last(&self) -> Option<Notification> {
+        self.sent
+            .lock()
+            .expect("Notifications poisoned")
+            .last()
+            .cloned()
+    }
+}
+
+#[rocket::async_trait]
+impl Notifier for MemoryNotifier {
+    async fn send(&self, notification: &Notification) -> Result<(), NotifierError> {
+        self.sent
+            .lock()
+            .expect("Notifications poisoned")
+            .push(notification.clone());
+
+        Ok(())
+    }
+}
@@ -0,0 +1,5 @@
+mod notifier;
+
+pub mod memory;
+
+pub use notifier::*;
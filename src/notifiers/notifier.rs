@@ -0,0 +1,60 @@
+use std::error::Error;
+
+use crate::User;
+
+/// Delivers messages to users, e.g. by email. The application decides how a
+/// notification is presented, usually as a link containing the token.
+#[rocket::async_trait]
+pub trait Notifier: Send + Sync + core::fmt::Debug + 'static {
+    /// Deliver a notification to the user it is addressed to.
+    async fn send(&self, notification: &Notification) -> Result<(), NotifierError>;
+}
+
+/// A message to a user.
+#[derive(Debug, Clone)]
+pub enum Notification {
+    /// Asks the user to confirm their email address.
+    EmailConfirmation {
+        user: User,
+        email: String,
+        token: String,
+    },
+
+    /// Allows the user to choose a new password.
+    PasswordReset { user: User, token: String },
+
+    /// Signs the user in without a password.
+    LoginLink { user: User, token: String },
+}
+
+impl Notification {
+    /// The user the notification is addressed to.
+    pub fn user(&self) -> &User {
+        match self {
+            Self::EmailConfirmation { user, .. } => user,
+            Self::PasswordReset { user, .. } => user,
+            Self::LoginLink { user, .. } => user,
+        }
+    }
+
+    /// The token contained in the notification.
+    pub fn token(&self) -> &str {
+        match self {
+            Self::EmailConfirmation { token, .. } => token,
+            Self::PasswordReset { token, .. } => token,
+            Self::LoginLink { token, .. } => token,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum NotifierError {
+    #[error("no notifier is configured")]
+    NotConfigured,
+
+    #[error("the user has no address the notification can be delivered to")]
+    MissingAddress,
+
+    #[error("an error occurred while trying to deliver the notification")]
+    Other(#[from] Box<dyn Error>),
+}
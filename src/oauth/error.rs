@@ -0,0 +1,80 @@
+use rocket::{
+    http::{Header, Status},
+    response::{self, Responder},
+    serde::{json::Json, Serialize},
+    Request,
+};
+
+/// An error response of the authorization server (RFC 6749 sections 4.1.2.1 and 5.2).
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum OAuthError {
+    #[error("{0}")]
+    InvalidRequest(&'static str),
+
+    #[error("client authentication failed")]
+    InvalidClient,
+
+    #[error("the grant is invalid, expired or was issued to another client")]
+    InvalidGrant,
+
+    #[error("the client is not allowed to use this grant type")]
+    UnauthorizedClient,
+
+    #[error("the grant type is not supported")]
+    UnsupportedGrantType,
+
+    #[error("the response type is not supported")]
+    UnsupportedResponseType,
+
+    #[error("the requested scope is invalid")]
+    InvalidScope,
+
+    #[error("the request could not be processed")]
+    ServerError,
+}
+
+impl OAuthError {
+    /// The error code sent to the client.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::InvalidRequest(_) => "invalid_request",
+            Self::InvalidClient => "invalid_client",
+            Self::InvalidGrant => "invalid_grant",
+            Self::UnauthorizedClient => "unauthorized_client",
+            Self::UnsupportedGrantType => "unsupported_grant_type",
+            Self::UnsupportedResponseType => "unsupported_response_type",
+            Self::InvalidScope => "invalid_scope",
+            Self::ServerError => "server_error",
+        }
+    }
+
+    pub fn status(&self) -> Status {
+        match self {
+            Self::InvalidClient => Status::Unauthorized,
+            Self::ServerError => Status::InternalServerError,
+            _ => Status::BadRequest,
+        }
+    }
+}
+
+#[derive(Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ErrorBody {
+    error: &'static str,
+    error_description: String,
+}
+
+impl<'r> Responder<'r, 'static> for OAuthError {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let body = ErrorBody {
+            error: self.code(),
+            error_description: self.to_string(),
+        };
+
+        let mut res = Json(body).respond_to(req)?;
+        res.set_status(self.status());
+        res.set_header(Header::new("Cache-Control", "no-store"));
+
+        Ok(res)
+    }
+}
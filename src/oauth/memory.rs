@@ -0,0 +1,117 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rocket::time::OffsetDateTime;
+
+use crate::oauth::{AuthorizationCode, OAuthClient, OAuthStore, OAuthStoreError, RefreshToken};
+
+/// Keeps clients and grants in memory. Grants are not shared between processes and
+/// are lost on restart.
+#[derive(Debug, Default, Clone)]
+pub struct MemoryOAuthStore {
+    state: Arc<Mutex<State>>,
+}
+
+#[derive(Debug, Default)]
+struct State {
+    clients: HashMap<String, OAuthClient>,
+    authorization_codes: HashMap<Vec<u8>, AuthorizationCode>,
+    refresh_tokens: HashMap<Vec<u8>, RefreshToken>,
+}
+
+impl MemoryOAuthStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn state(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().expect("OAuth store poisoned")
+    }
+}
+
+#[rocket::async_trait]
+impl OAuthStore for MemoryOAuthStore {
+    async fn find_client(&self, client_id: &str) -> Result<Option<OAuthClient>, OAuthStoreError> {
+        Ok(self.state().clients.get(client_id).cloned())
+    }
+
+    async fn add_client(&self, client: &OAuthClient) -> Result<(), OAuthStoreError> {
+        let mut state = self.state();
+
+        if state.clients.contains_key(&client.client_id) {
+            return Err(OAuthStoreError::ClientExists);
+        }
+
+        state
+            .clients
+            .insert(client.client_id.clone(), client.clone());
+
+        Ok(())
+    }
+
+    async fn remove_client(&self, client_id: &str) -> Result<(), OAuthStoreError> {
+        let mut state = self.state();
+
+        if state.clients.remove(client_id).is_none() {
+            return Err(OAuthStoreError::ClientNotFound);
+        }
+
+        state
+            .authorization_codes
+            .retain(|_, c| c.client_id != client_id);
+        state.refresh_tokens.retain(|_, t| t.client_id != client_id);
+
+        Ok(())
+    }
+
+    async fn add_authorization_code(
+        &self,
+        code_hash: &[u8],
+        code: &AuthorizationCode,
+    ) -> Result<(), OAuthStoreError> {
+        let mut state = self.state();
+
+        // Drop expired codes that were never redeemed
+        let now = OffsetDateTime::now_utc();
+        state.authorization_codes.retain(|_, c| c.expires > now);
+
+        state
+            .authorization_codes
+            .insert(code_hash.to_vec(), code.clone());
+
+        Ok(())
+    }
+
+    async fn take_authorization_code(
+        &self,
+        code_hash: &[u8],
+    ) -> Result<Option<AuthorizationCode>, OAuthStoreError> {
+        Ok(self.state().authorization_codes.remove(code_hash))
+    }
+
+    async fn add_refresh_token(
+        &self,
+        token_hash: &[u8],
+        token: &RefreshToken,
+    ) -> Result<(), OAuthStoreError> {
+        let mut state = self.state();
+
+        let now = OffsetDateTime::now_utc();
+        state.refresh_tokens.retain(|_, t| t.expires > now);
+
+        state
+            .refresh_tokens
+            .insert(token_hash.to_vec(), token.clone());
+
+        Ok(())
+    }
+
+    async fn take_refresh_token(
+        &self,
+        token_hash: &[u8],
+    ) -> Result<Option<RefreshToken>, OAuthStoreError> {
+        Ok(self.state().refresh_tokens.remove(token_hash))
+    }
+}
@@ -0,0 +1,11 @@
+mod error;
+mod routes;
+mod server;
+mod store;
+
+pub mod memory;
+
+pub use error::*;
+pub use routes::*;
+pub use server::*;
+pub use store::*;
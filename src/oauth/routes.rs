@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use rocket::{
+    form::Form,
+    get,
+    http::RawStr,
+    post,
+    request::{FromRequest, Outcome},
+    response::Redirect,
+    Request, Route, State,
+};
+
+use crate::{
+    schemes::{
+        basic::{basic_credentials, decode_credentials, BasicCharset},
+        jwt::JwtTokenProvider,
+    },
+    User, UserRepository,
+};
+
+use super::{AuthorizationRequest, AuthorizationServer, OAuthError, TokenRequest, TokenResponse};
+
+/// The endpoints of the [`AuthorizationServer`], to be mounted e.g. at `/oauth`:
+///
+/// - `GET /authorize` issues an authorization code for the signed in user, usually
+///   authenticated with the cookie scheme, and redirects back to the client.
+/// - `POST /token` issues tokens to clients.
+pub fn routes() -> Vec<Route> {
+    rocket::routes![authorize, token]
+}
+
+#[allow(clippy::too_many_arguments)]
+#[get("/authorize?<response_type>&<client_id>&<redirect_uri>&<scope>&<state>&<code_challenge>&<code_challenge_method>")]
+async fn authorize(
+    response_type: String,
+    client_id: String,
+    redirect_uri: Option<String>,
+    scope: Option<String>,
+    state: Option<String>,
+    code_challenge: Option<String>,
+    code_challenge_method: Option<String>,
+    user: &User,
+    server: &State<AuthorizationServer>,
+) -> Result<Redirect, OAuthError> {
+    let request = AuthorizationRequest {
+        response_type,
+        client_id,
+        redirect_uri,
+        scope,
+        state,
+        code_challenge,
+        code_challenge_method,
+    };
+
+    let url = server.authorize(user, &request).await?;
+
+    Ok(Redirect::to(url))
+}
+
+#[post("/token", data = "<params>")]
+async fn token(
+    params: Form<HashMap<String, String>>,
+    credentials: Option<ClientCredentials>,
+    users: &UserRepository,
+    tokens: JwtTokenProvider<'_>,
+    server: &State<AuthorizationServer>,
+) -> Result<TokenResponse, OAuthError> {
+    let credentials = credentials
+        .as_ref()
+        .map(|c| (c.client_id.as_str(), c.client_secret.as_str()));
+
+    let mut params = params.into_inner();
+    let request = TokenRequest {
+        grant_type: params.remove("grant_type").unwrap_or_default(),
+        scope: params.remove("scope"),
+        username: params.remove("username"),
+        password: params.remove("password"),
+        code: params.remove("code"),
+        redirect_uri: params.remove("redirect_uri"),
+        code_verifier: params.remove("code_verifier"),
+        refresh_token: params.remove("refresh_token"),
+        client_id: params.remove("client_id"),
+        client_secret: params.remove("client_secret"),
+    };
+
+    server.token(users, &tokens, &request, credentials).await
+}
+
+/// Client credentials from the `Authorization` header (RFC 6749 section 2.3.1).
+struct ClientCredentials {
+    client_id: String,
+    client_secret: String,
+}
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ClientCredentials {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        let Some((client_id, client_secret)) = req
+            .headers()
+            .get("Authorization")
+            .find_map(basic_credentials)
+            .and_then(|credentials| decode_credentials(credentials, BasicCharset::Utf8))
+        else {
+            return Outcome::Forward(());
+        };
+
+        // Both parts are form-urlencoded before being joined
+        match (
+            RawStr::new(&client_id).url_decode(),
+            RawStr::new(&client_secret).url_decode(),
+        ) {
+            (Ok(client_id), Ok(client_secret)) => Outcome::Success(ClientCredentials {
+                client_id: client_id.into_owned(),
+                client_secret: client_secret.into_owned(),
+            }),
+            _ => Outcome::Forward(()),
+        }
+    }
+}
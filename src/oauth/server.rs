@@ -0,0 +1,613 @@
+use std::{collections::HashMap, sync::Arc};
+
+use data_encoding::BASE64URL_NOPAD;
+use rand::{rngs::OsRng, RngCore};
+use rocket::{
+    http::Header,
+    response::{self, Responder},
+    serde::{
+        json::{Json, Value},
+        Serialize,
+    },
+    time::{Duration, OffsetDateTime},
+    Request,
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::{
+    schemes::jwt::{JwtToken, JwtTokenProvider},
+    util::percent_encode,
+    LoginError, Roles, User, UserRepository,
+};
+
+use super::{
+    AuthorizationCode, GrantType, OAuthClient, OAuthError, OAuthStore, OAuthStoreError,
+    RefreshToken,
+};
+
+/// An OAuth 2.0 authorization server (RFC 6749) issuing tokens to registered clients.
+/// Its endpoints are provided by [`routes`](super::routes). Access tokens are JWTs
+/// signed with the [`JwtConfig`](crate::schemes::jwt::JwtConfig) of the
+/// [`JwtBearer`](crate::schemes::jwt::JwtBearer) scheme.
+#[derive(Debug, Clone)]
+pub struct AuthorizationServer {
+    store: Arc<dyn OAuthStore>,
+
+    /// How long access tokens are valid.
+    pub access_token_lifetime: Duration,
+
+    /// How long refresh tokens are valid. Each refresh token can only be used once, and
+    /// a new one is issued with each new access token.
+    pub refresh_token_lifetime: Duration,
+
+    /// How long a client has to redeem an authorization code.
+    pub authorization_code_lifetime: Duration,
+
+    /// The path [`routes`](super::routes) are mounted at, used to advertise the
+    /// endpoints in the discovery document.
+    pub base_path: String,
+
+    /// The grant types the server accepts at all, advertised in the discovery document.
+    /// Clients are further restricted to the grant types they are registered with.
+    pub grant_types: Vec<GrantType>,
+}
+
+/// The parameters of an authorization request (section 4.1.1).
+#[derive(Debug, Clone)]
+pub struct AuthorizationRequest {
+    pub response_type: String,
+    pub client_id: String,
+    pub redirect_uri: Option<String>,
+    pub scope: Option<String>,
+    pub state: Option<String>,
+    pub code_challenge: Option<String>,
+    pub code_challenge_method: Option<String>,
+}
+
+/// The parameters of a token request (sections 4.1.3, 4.3.2, 4.4.2 and 6).
+#[derive(Debug, Clone, Default)]
+pub struct TokenRequest {
+    pub grant_type: String,
+    pub scope: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+    pub code: Option<String>,
+    pub redirect_uri: Option<String>,
+    pub code_verifier: Option<String>,
+    pub refresh_token: Option<String>,
+    pub client_id: Option<String>,
+    pub client_secret: Option<String>,
+}
+
+/// A successful token response (section 5.1).
+#[derive(Debug, Clone, Serialize)]
+#[serde(crate = "rocket::serde")]
+pub struct TokenResponse {
+    pub access_token: JwtToken,
+    pub token_type: &'static str,
+    pub expires_in: i64,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+
+    pub scope: String,
+}
+
+impl AuthorizationServer {
+    pub fn new(store: impl OAuthStore) -> Self {
+        Self {
+            store: Arc::new(store),
+            access_token_lifetime: Duration::hours(1),
+            refresh_token_lifetime: Duration::days(30),
+            authorization_code_lifetime: Duration::minutes(5),
+            base_path: "/oauth".to_owned(),
+            grant_types: vec![
+                GrantType::AuthorizationCode,
+                GrantType::RefreshToken,
+                GrantType::ClientCredentials,
+                GrantType::Password,
+            ],
+        }
+    }
+
+    /// Register a public client, e.g. a single page or mobile app, that cannot keep a
+    /// secret. Public clients have to use PKCE.
+    pub async fn register_client(&self, client: OAuthClient) -> Result<(), OAuthStoreError> {
+        let client = OAuthClient {
+            secret_hash: None,
+            ..client
+        };
+
+        self.store.add_client(&client).await
+    }
+
+    /// Register a confidential client. Returns the generated client secret, which has
+    /// to be handed to the client now since only its hash is stored.
+    pub async fn register_confidential_client(
+        &self,
+        client: OAuthClient,
+    ) -> Result<String, OAuthStoreError> {
+        let secret = random_value();
+        let client = OAuthClient {
+            secret_hash: Some(hash(&secret)),
+            ..client
+        };
+
+        self.store.add_client(&client).await?;
+
+        Ok(secret)
+    }
+
+    /// The subject of access tokens a client requests on its own behalf with the client
+    /// credentials grant. Usernames cannot contain a colon, so it never names a user.
+    pub fn client_subject(client_id: &str) -> String {
+        format!("client:{}", client_id)
+    }
+
+    /// Remove a client. Its outstanding grants become invalid.
+    pub async fn remove_client(&self, client_id: &str) -> Result<(), OAuthStoreError> {
+        self.store.remove_client(client_id).await
+    }
+
+    /// Handle the authorization request of a signed in user (section 4.1.1). Returns
+    /// the URL the user is redirected back to the client with, carrying either an
+    /// authorization code or an error.
+    ///
+    /// Fails without a redirect if the client or redirect URI is invalid, since the
+    /// user must not be sent to an unverified location.
+    pub async fn authorize(
+        &self,
+        user: &User,
+        request: &AuthorizationRequest,
+    ) -> Result<String, OAuthError> {
+        let Some(client) = self.find_client(&request.client_id).await? else {
+            return Err(OAuthError::InvalidRequest("the client is unknown"));
+        };
+
+        let redirect_uri = match &request.redirect_uri {
+            Some(uri) if client.redirect_uris.contains(uri) => uri.clone(),
+            Some(_) => {
+                return Err(OAuthError::InvalidRequest(
+                    "the redirect URI is not registered for the client",
+                ))
+            }
+            None if client.redirect_uris.len() == 1 => client.redirect_uris[0].clone(),
+            None => return Err(OAuthError::InvalidRequest("a redirect URI is required")),
+        };
+
+        let result = self
+            .issue_authorization_code(user, &client, &redirect_uri, request)
+            .await;
+
+        let mut params = match &result {
+            Ok(code) => vec![("code", code.as_str())],
+            Err(e) => {
+                log::warn!(
+                    "Rejected authorization request of {}: {}",
+                    client.client_id,
+                    e
+                );
+                vec![("error", e.code())]
+            }
+        };
+
+        if let Some(state) = &request.state {
+            params.push(("state", state));
+        }
+
+        Ok(append_query(&redirect_uri, &params))
+    }
+
+    /// Handle a token request. The client authenticates either with credentials from
+    /// the `Authorization` header (`basic_credentials`) or in the request body.
+    pub async fn token(
+        &self,
+        users: &UserRepository,
+        tokens: &JwtTokenProvider<'_>,
+        request: &TokenRequest,
+        basic_credentials: Option<(&str, &str)>,
+    ) -> Result<TokenResponse, OAuthError> {
+        let client = self.authenticate_client(request, basic_credentials).await?;
+
+        let Some(grant_type) = GrantType::parse(&request.grant_type)
+            .filter(|grant_type| self.grant_types.contains(grant_type))
+        else {
+            return Err(OAuthError::UnsupportedGrantType);
+        };
+
+        if !client.grant_types.contains(&grant_type) {
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        let result = match grant_type {
+            GrantType::Password => self.password_grant(users, tokens, &client, request).await,
+            GrantType::ClientCredentials => {
+                self.client_credentials_grant(users, tokens, &client, request)
+                    .await
+            }
+            GrantType::AuthorizationCode => {
+                self.authorization_code_grant(users, tokens, &client, request)
+                    .await
+            }
+            GrantType::RefreshToken => {
+                self.refresh_token_grant(users, tokens, &client, request)
+                    .await
+            }
+        };
+
+        if let Err(e) = &result {
+            log::warn!(
+                "Rejected {} token request of {}: {}",
+                grant_type.as_str(),
+                client.client_id,
+                e
+            );
+        }
+
+        result
+    }
+
+    async fn issue_authorization_code(
+        &self,
+        user: &User,
+        client: &OAuthClient,
+        redirect_uri: &str,
+        request: &AuthorizationRequest,
+    ) -> Result<String, OAuthError> {
+        if request.response_type != "code"
+            || !self.grant_types.contains(&GrantType::AuthorizationCode)
+        {
+            return Err(OAuthError::UnsupportedResponseType);
+        }
+
+        if !client.grant_types.contains(&GrantType::AuthorizationCode) {
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        let code_challenge = match (
+            &request.code_challenge,
+            request.code_challenge_method.as_deref(),
+        ) {
+            (Some(challenge), Some("S256")) => Some(challenge.clone()),
+            (Some(_), _) => {
+                return Err(OAuthError::InvalidRequest(
+                    "only the S256 code challenge method is supported",
+                ))
+            }
+            (None, _) if client.is_public() => {
+                return Err(OAuthError::InvalidRequest(
+                    "public clients have to use PKCE",
+                ))
+            }
+            (None, _) => None,
+        };
+
+        let scopes = grant_scopes(&client.scopes, request.scope.as_deref())?;
+
+        let code = random_value();
+        let grant = AuthorizationCode {
+            client_id: client.client_id.clone(),
+            username: user.username.clone(),
+            redirect_uri: redirect_uri.to_owned(),
+            scopes,
+            code_challenge,
+            expires: OffsetDateTime::now_utc() + self.authorization_code_lifetime,
+        };
+
+        self.store
+            .add_authorization_code(&hash(&code), &grant)
+            .await
+            .map_err(server_error)?;
+
+        Ok(code)
+    }
+
+    async fn password_grant(
+        &self,
+        users: &UserRepository,
+        tokens: &JwtTokenProvider<'_>,
+        client: &OAuthClient,
+        request: &TokenRequest,
+    ) -> Result<TokenResponse, OAuthError> {
+        let (Some(username), Some(password)) = (&request.username, &request.password) else {
+            return Err(OAuthError::InvalidRequest(
+                "username and password are required",
+            ));
+        };
+
+        let scopes = grant_scopes(&client.scopes, request.scope.as_deref())?;
+
+        let user = users
+            .authenticate(username, password)
+            .await
+            .map_err(|e| match e {
+                LoginError::Other(e) => server_error(e),
+                _ => OAuthError::InvalidGrant,
+            })?;
+
+        self.issue_tokens(users, tokens, client, user, scopes).await
+    }
+
+    async fn client_credentials_grant(
+        &self,
+        users: &UserRepository,
+        tokens: &JwtTokenProvider<'_>,
+        client: &OAuthClient,
+        request: &TokenRequest,
+    ) -> Result<TokenResponse, OAuthError> {
+        if client.is_public() {
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        // A client named like a user could be mistaken for them by the application
+        if users
+            .find_by_username(&client.client_id)
+            .await
+            .map_err(server_error)?
+            .is_some()
+        {
+            log::error!(
+                "Client {} has the username of a user and cannot act on its own behalf",
+                client.client_id
+            );
+            return Err(OAuthError::UnauthorizedClient);
+        }
+
+        let scopes = grant_scopes(&client.scopes, request.scope.as_deref())?;
+
+        // The client acts on its own behalf, with its scopes as roles
+        let user = User {
+            roles: Roles::from(scopes.clone()),
+            ..User::with_username(Self::client_subject(&client.client_id))
+        };
+
+        self.token_response(tokens, client, &user, scopes, None)
+    }
+
+    async fn authorization_code_grant(
+        &self,
+        users: &UserRepository,
+        tokens: &JwtTokenProvider<'_>,
+        client: &OAuthClient,
+        request: &TokenRequest,
+    ) -> Result<TokenResponse, OAuthError> {
+        let (Some(code), Some(redirect_uri)) = (&request.code, &request.redirect_uri) else {
+            return Err(OAuthError::InvalidRequest(
+                "code and redirect_uri are required",
+            ));
+        };
+
+        let Some(grant) = self
+            .store
+            .take_authorization_code(&hash(code))
+            .await
+            .map_err(server_error)?
+        else {
+            return Err(OAuthError::InvalidGrant);
+        };
+
+        if grant.client_id != client.client_id
+            || grant.redirect_uri != *redirect_uri
+            || grant.expires <= OffsetDateTime::now_utc()
+        {
+            return Err(OAuthError::InvalidGrant);
+        }
+
+        if let Some(code_challenge) = &grant.code_challenge {
+            let Some(code_verifier) = &request.code_verifier else {
+                return Err(OAuthError::InvalidGrant);
+            };
+
+            let computed = BASE64URL_NOPAD.encode(&Sha256::digest(code_verifier.as_bytes()));
+            if !bool::from(computed.as_bytes().ct_eq(code_challenge.as_bytes())) {
+                return Err(OAuthError::InvalidGrant);
+            }
+        }
+
+        let user = self.find_user(users, &grant.username).await?;
+
+        self.issue_tokens(users, tokens, client, user, grant.scopes)
+            .await
+    }
+
+    async fn refresh_token_grant(
+        &self,
+        users: &UserRepository,
+        tokens: &JwtTokenProvider<'_>,
+        client: &OAuthClient,
+        request: &TokenRequest,
+    ) -> Result<TokenResponse, OAuthError> {
+        let Some(refresh_token) = &request.refresh_token else {
+            return Err(OAuthError::InvalidRequest("refresh_token is required"));
+        };
+
+        let Some(grant) = self
+            .store
+            .take_refresh_token(&hash(refresh_token))
+            .await
+            .map_err(server_error)?
+        else {
+            return Err(OAuthError::InvalidGrant);
+        };
+
+        if grant.client_id != client.client_id || grant.expires <= OffsetDateTime::now_utc() {
+            return Err(OAuthError::InvalidGrant);
+        }
+
+        // The scope can only be narrowed down
+        let scopes = grant_scopes(&grant.scopes, request.scope.as_deref())?;
+
+        let user = self.find_user(users, &grant.username).await?;
+        if security_stamp(users, &user).await? != grant.security_stamp {
+            return Err(OAuthError::InvalidGrant);
+        }
+
+        self.issue_tokens(users, tokens, client, user, scopes).await
+    }
+
+    /// Issue an access token to a client acting on behalf of a user, and a refresh token
+    /// if the client may use them.
+    async fn issue_tokens(
+        &self,
+        users: &UserRepository,
+        tokens: &JwtTokenProvider<'_>,
+        client: &OAuthClient,
+        user: User,
+        scopes: Vec<String>,
+    ) -> Result<TokenResponse, OAuthError> {
+        let refresh_token = if client.grant_types.contains(&GrantType::RefreshToken) {
+            let refresh_token = random_value();
+            let grant = RefreshToken {
+                client_id: client.client_id.clone(),
+                username: user.username.clone(),
+                scopes: scopes.clone(),
+                security_stamp: security_stamp(users, &user).await?,
+                expires: OffsetDateTime::now_utc() + self.refresh_token_lifetime,
+            };
+
+            self.store
+                .add_refresh_token(&hash(&refresh_token), &grant)
+                .await
+                .map_err(server_error)?;
+
+            Some(refresh_token)
+        } else {
+            None
+        };
+
+        self.token_response(tokens, client, &user, scopes, refresh_token)
+    }
+
+    fn token_response(
+        &self,
+        tokens: &JwtTokenProvider<'_>,
+        client: &OAuthClient,
+        user: &User,
+        scopes: Vec<String>,
+        refresh_token: Option<String>,
+    ) -> Result<TokenResponse, OAuthError> {
+        let scope = scopes.join(" ");
+
+        let mut claims = HashMap::new();
+        claims.insert(
+            "client_id".to_owned(),
+            Value::from(client.client_id.clone()),
+        );
+        claims.insert("scope".to_owned(), Value::from(scope.clone()));
+
+        let access_token = tokens
+            .create_token_with_claims(user, self.access_token_lifetime, claims)
+            .map_err(server_error)?;
+
+        Ok(TokenResponse {
+            access_token,
+            token_type: "Bearer",
+            expires_in: self.access_token_lifetime.whole_seconds(),
+            refresh_token,
+            scope,
+        })
+    }
+
+    async fn authenticate_client(
+        &self,
+        request: &TokenRequest,
+        basic_credentials: Option<(&str, &str)>,
+    ) -> Result<OAuthClient, OAuthError> {
+        let (client_id, secret) = match (basic_credentials, &request.client_id) {
+            (Some((client_id, secret)), _) => (client_id, Some(secret)),
+            (None, Some(client_id)) => (client_id.as_str(), request.client_secret.as_deref()),
+            (None, None) => return Err(OAuthError::InvalidClient),
+        };
+
+        let Some(client) = self.find_client(client_id).await? else {
+            return Err(OAuthError::InvalidClient);
+        };
+
+        match (&client.secret_hash, secret.filter(|s| !s.is_empty())) {
+            (Some(secret_hash), Some(secret)) if bool::from(hash(secret).ct_eq(secret_hash)) => {
+                Ok(client)
+            }
+            (None, None) => Ok(client),
+            _ => Err(OAuthError::InvalidClient),
+        }
+    }
+
+    async fn find_client(&self, client_id: &str) -> Result<Option<OAuthClient>, OAuthError> {
+        self.store
+            .find_client(client_id)
+            .await
+            .map_err(server_error)
+    }
+
+    async fn find_user(&self, users: &UserRepository, username: &str) -> Result<User, OAuthError> {
+        users
+            .find_by_username(username)
+            .await
+            .map_err(server_error)?
+            .ok_or(OAuthError::InvalidGrant)
+    }
+}
+
+impl<'r> Responder<'r, 'static> for TokenResponse {
+    fn respond_to(self, req: &'r Request<'_>) -> response::Result<'static> {
+        let mut res = Json(self).respond_to(req)?;
+        res.set_header(Header::new("Cache-Control", "no-store"));
+
+        Ok(res)
+    }
+}
+
+/// Determine the granted scopes. Without a requested scope, all allowed scopes are
+/// granted.
+fn grant_scopes(allowed: &[String], requested: Option<&str>) -> Result<Vec<String>, OAuthError> {
+    let Some(requested) = requested.filter(|s| !s.trim().is_empty()) else {
+        return Ok(allowed.to_vec());
+    };
+
+    let mut scopes = Vec::new();
+    for scope in requested.split_whitespace() {
+        if !allowed.iter().any(|s| s == scope) {
+            return Err(OAuthError::InvalidScope);
+        }
+
+        if !scopes.iter().any(|s| s == scope) {
+            scopes.push(scope.to_owned());
+        }
+    }
+
+    Ok(scopes)
+}
+
+async fn security_stamp(users: &UserRepository, user: &User) -> Result<String, OAuthError> {
+    let user_store = users.user_store.read().await;
+
+    user_store.security_stamp(user).await.map_err(server_error)
+}
+
+fn append_query(uri: &str, params: &[(&str, &str)]) -> String {
+    let query = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, percent_encode(value)))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let separator = if uri.contains('?') { '&' } else { '?' };
+
+    format!("{}{}{}", uri, separator, query)
+}
+
+fn server_error(e: impl std::fmt::Display) -> OAuthError {
+    log::error!("Failed to process OAuth request: {}", e);
+    OAuthError::ServerError
+}
+
+fn hash(value: &str) -> Vec<u8> {
+    Sha256::digest(value.as_bytes()).to_vec()
+}
+
+fn random_value() -> String {
+    let mut value = [0; 32];
+    OsRng.fill_bytes(&mut value);
+    BASE64URL_NOPAD.encode(&value)
+}
@@ -0,0 +1,176 @@
+use std::error::Error;
+
+use rocket::time::OffsetDateTime;
+
+/// Persists the clients registered with the authorization server and the grants it
+/// issued to them.
+#[rocket::async_trait]
+pub trait OAuthStore: Send + Sync + core::fmt::Debug + 'static {
+    /// Find a registered client by its id.
+    async fn find_client(&self, client_id: &str) -> Result<Option<OAuthClient>, OAuthStoreError>;
+
+    /// Register a client.
+    async fn add_client(&self, client: &OAuthClient) -> Result<(), OAuthStoreError>;
+
+    /// Remove a registered client.
+    async fn remove_client(&self, client_id: &str) -> Result<(), OAuthStoreError>;
+
+    /// Store an authorization code by the hash of the code.
+    async fn add_authorization_code(
+        &self,
+        code_hash: &[u8],
+        code: &AuthorizationCode,
+    ) -> Result<(), OAuthStoreError>;
+
+    /// Remove and return an authorization code by the hash of the code, so that each
+    /// code can only be redeemed once.
+    async fn take_authorization_code(
+        &self,
+        code_hash: &[u8],
+    ) -> Result<Option<AuthorizationCode>, OAuthStoreError>;
+
+    /// Store a refresh token by the hash of the token.
+    async fn add_refresh_token(
+        &self,
+        token_hash: &[u8],
+        token: &RefreshToken,
+    ) -> Result<(), OAuthStoreError>;
+
+    /// Remove and return a refresh token by the hash of the token, so that each refresh
+    /// token can only be used once.
+    async fn take_refresh_token(
+        &self,
+        token_hash: &[u8],
+    ) -> Result<Option<RefreshToken>, OAuthStoreError>;
+}
+
+/// The grant types of RFC 6749 supported by the authorization server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum GrantType {
+    /// Resource owner password credentials (section 4.3). Only meant for legacy clients.
+    Password,
+
+    /// Client credentials (section 4.4), for clients acting on their own behalf.
+    ClientCredentials,
+
+    /// Authorization code (section 4.1), with PKCE (RFC 7636).
+    AuthorizationCode,
+
+    /// Refresh token (section 6).
+    RefreshToken,
+}
+
+impl GrantType {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Password => "password",
+            Self::ClientCredentials => "client_credentials",
+            Self::AuthorizationCode => "authorization_code",
+            Self::RefreshToken => "refresh_token",
+        }
+    }
+
+    pub fn parse(grant_type: &str) -> Option<Self> {
+        match grant_type {
+            "password" => Some(Self::Password),
+            "client_credentials" => Some(Self::ClientCredentials),
+            "authorization_code" => Some(Self::AuthorizationCode),
+            "refresh_token" => Some(Self::RefreshToken),
+            _ => None,
+        }
+    }
+}
+
+/// A client registered with the authorization server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OAuthClient {
+    pub client_id: String,
+
+    /// The SHA-256 hash of the client secret of confidential clients. Public clients,
+    /// e.g. single page and mobile apps, have no secret.
+    pub secret_hash: Option<Vec<u8>>,
+
+    /// The URIs users may be redirected to after authorizing the client.
+    pub redirect_uris: Vec<String>,
+
+    /// The grant types the client may use.
+    pub grant_types: Vec<GrantType>,
+
+    /// The scopes the client may request.
+    pub scopes: Vec<String>,
+}
+
+impl OAuthClient {
+    pub fn new(client_id: impl Into<String>) -> Self {
+        Self {
+            client_id: client_id.into(),
+            secret_hash: None,
+            redirect_uris: Vec::new(),
+            grant_types: vec![GrantType::AuthorizationCode, GrantType::RefreshToken],
+            scopes: Vec::new(),
+        }
+    }
+
+    pub fn with_redirect_uris(mut self, redirect_uris: &[&str]) -> Self {
+        self.redirect_uris = redirect_uris.iter().map(|s| (*s).to_owned()).collect();
+        self
+    }
+
+    pub fn with_grant_types(mut self, grant_types: &[GrantType]) -> Self {
+        self.grant_types = grant_types.to_vec();
+        self
+    }
+
+    pub fn with_scopes(mut self, scopes: &[&str]) -> Self {
+        self.scopes = scopes.iter().map(|s| (*s).to_owned()).collect();
+        self
+    }
+
+    /// Whether the client has no secret and therefore cannot authenticate itself.
+    pub fn is_public(&self) -> bool {
+        self.secret_hash.is_none()
+    }
+}
+
+/// An authorization code issued to a client on behalf of a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AuthorizationCode {
+    pub client_id: String,
+    pub username: String,
+
+    /// The redirect URI the code was sent to, which has to be repeated when redeeming it.
+    pub redirect_uri: String,
+
+    pub scopes: Vec<String>,
+
+    /// The PKCE code challenge (S256) the code verifier has to match.
+    pub code_challenge: Option<String>,
+
+    pub expires: OffsetDateTime,
+}
+
+/// A refresh token issued to a client on behalf of a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RefreshToken {
+    pub client_id: String,
+    pub username: String,
+    pub scopes: Vec<String>,
+
+    /// The security stamp of the user when the token was issued. The token becomes
+    /// invalid when the stamp changes, e.g. because the password was changed.
+    pub security_stamp: String,
+
+    pub expires: OffsetDateTime,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum OAuthStoreError {
+    #[error("a client with this id already exists")]
+    ClientExists,
+
+    #[error("client was not found")]
+    ClientNotFound,
+
+    #[error("an error occurred while trying to access the OAuth store")]
+    Other(#[from] Box<dyn Error>),
+}
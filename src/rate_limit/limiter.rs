@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+/// Persists token buckets used to throttle login attempts.
+#[rocket::async_trait]
+pub trait RateLimitStore: Send + Sync + core::fmt::Debug + 'static {
+    /// Try to take a token from the bucket identified by `key`. If the bucket is empty,
+    /// return the time until the next token becomes available.
+    async fn try_acquire(&self, key: &str, bucket: &TokenBucket) -> Result<(), Duration>;
+}
+
+/// Configuration of a token bucket. A full bucket allows `capacity` attempts in a burst,
+/// after which one attempt is allowed every `refill_interval`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TokenBucket {
+    pub capacity: u32,
+    pub refill_interval: Duration,
+}
+
+impl TokenBucket {
+    pub fn new(capacity: u32, refill_interval: Duration) -> Self {
+        Self {
+            capacity,
+            refill_interval,
+        }
+    }
+}
+
+/// Controls throttling of login attempts, independently of account lockout.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitOptions {
+    /// Bucket applied per client IP address. Requests without a known client IP
+    /// are not limited by this bucket.
+    pub per_ip: Option<TokenBucket>,
+
+    /// Bucket applied per (normalized) username.
+    pub per_username: Option<TokenBucket>,
+}
+
+impl RateLimitOptions {
+    /// No throttling, the default.
+    pub fn disabled() -> Self {
+        Self {
+            per_ip: None,
+            per_username: None,
+        }
+    }
+
+    /// Allow bursts of 30 attempts per IP address and 10 per username, refilled every
+    /// 2 and 6 seconds respectively.
+    pub fn recommended() -> Self {
+        Self {
+            per_ip: Some(TokenBucket::new(30, Duration::from_secs(2))),
+            per_username: Some(TokenBucket::new(10, Duration::from_secs(6))),
+        }
+    }
+}
+
+impl Default for RateLimitOptions {
+    fn default() -> Self {
+        Self::disabled()
+    }
+}
@@ -0,0 +1,179 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+use crate::rate_limit::{RateLimitStore, TokenBucket};
+
+/// Interval in which buckets that are full again are dropped.
+const PRUNE_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default maximum number of tracked keys.
+const MAX_KEYS: usize = 100_000;
+
+/// Keeps token buckets in memory. Buckets are not shared between processes.
+///
+/// Buckets that are full again are dropped about once a minute. At most `max_keys`
+/// buckets are kept, further keys evict an arbitrary bucket, so that a flood of
+/// distinct keys cannot exhaust memory.
+#[derive(Debug, Clone)]
+pub struct MemoryRateLimitStore {
+    buckets: Arc<Mutex<Buckets>>,
+    max_keys: usize,
+}
+
+#[derive(Debug)]
+struct Buckets {
+    states: HashMap<String, BucketState>,
+    pruned: Instant,
+}
+
+#[derive(Debug, Clone, Copy)]
+struct BucketState {
+    tokens: f64,
+    updated: Instant,
+
+    /// When the bucket is full again and can be dropped. Kept per bucket, since
+    /// buckets of different keys may have different configurations.
+    full: Instant,
+}
+
+impl MemoryRateLimitStore {
+    pub fn new() -> Self {
+        Self {
+            buckets: Arc::new(Mutex::new(Buckets {
+                states: HashMap::new(),
+                pruned: Instant::now(),
+            })),
+            max_keys: MAX_KEYS,
+        }
+    }
+
+    /// The maximum number of buckets kept at once.
+    pub fn with_max_keys(mut self, max_keys: usize) -> Self {
+        self.max_keys = max_keys.max(1);
+        self
+    }
+
+    fn acquire_at(&self, key: &str, bucket: &TokenBucket, now: Instant) -> Result<(), Duration> {
+        let mut buckets = self.buckets.lock().expect("Rate limit buckets poisoned");
+
+        if now.saturating_duration_since(buckets.pruned) >= PRUNE_INTERVAL {
+            buckets.states.retain(|_, state| state.full > now);
+            buckets.pruned = now;
+        }
+
+        if buckets.states.len() >= self.max_keys && !buckets.states.contains_key(key) {
+            if let Some(evicted) = buckets.states.keys().next().cloned() {
+                buckets.states.remove(&evicted);
+            }
+        }
+
+        let state = buckets.states.entry(key.to_owned()).or_insert(BucketState {
+            tokens: bucket.capacity as f64,
+            updated: now,
+            full: now,
+        });
+
+        state.tokens = refill(state, bucket, now);
+        state.updated = now;
+
+        let result = if state.tokens >= 1.0 {
+            state.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(bucket.refill_interval.mul_f64(1.0 - state.tokens))
+        };
+
+        let missing = bucket.capacity as f64 - state.tokens;
+        state.full = now + bucket.refill_interval.mul_f64(missing.max(0.0));
+
+        result
+    }
+}
+
+/// The number of tokens in the bucket at `now`.
+fn refill(state: &BucketState, bucket: &TokenBucket, now: Instant) -> f64 {
+    let elapsed = now.saturating_duration_since(state.updated);
+    let refilled = elapsed.as_secs_f64() / bucket.refill_interval.as_secs_f64().max(f64::EPSILON);
+
+    (state.tokens + refilled).min(bucket.capacity as f64)
+}
+
+impl Default for MemoryRateLimitStore {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[rocket::async_trait]
+impl RateLimitStore for MemoryRateLimitStore {
+    async fn try_acquire(&self, key: &str, bucket: &TokenBucket) -> Result<(), Duration> {
+        self.acquire_at(key, bucket, Instant::now())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use crate::rate_limit::TokenBucket;
+
+    use super::{MemoryRateLimitStore, PRUNE_INTERVAL};
+
+    #[test]
+    fn test_bucket_refills() {
+        let store = MemoryRateLimitStore::new();
+        let bucket = TokenBucket::new(2, Duration::from_secs(10));
+        let start = Instant::now();
+
+        assert!(store.acquire_at("key", &bucket, start).is_ok());
+        assert!(store.acquire_at("key", &bucket, start).is_ok());
+        assert_eq!(
+            store.acquire_at("key", &bucket, start),
+            Err(Duration::from_secs(10))
+        );
+
+        // Other keys are independent
+        assert!(store.acquire_at("other", &bucket, start).is_ok());
+
+        let later = start + Duration::from_secs(10);
+        assert!(store.acquire_at("key", &bucket, later).is_ok());
+        assert!(store.acquire_at("key", &bucket, later).is_err());
+    }
+
+    #[test]
+    fn test_full_buckets_are_pruned_by_their_own_configuration() {
+        let store = MemoryRateLimitStore::new();
+        let fast = TokenBucket::new(1, Duration::from_secs(1));
+        let slow = TokenBucket::new(1, Duration::from_secs(3600));
+        let start = Instant::now();
+
+        assert!(store.acquire_at("fast", &fast, start).is_ok());
+        assert!(store.acquire_at("slow", &slow, start).is_ok());
+
+        // Only the bucket that is full again is dropped, whatever bucket the caller uses
+        let later = start + PRUNE_INTERVAL;
+        assert!(store.acquire_at("other", &fast, later).is_ok());
+
+        let buckets = store.buckets.lock().unwrap();
+        assert!(!buckets.states.contains_key("fast"));
+        assert!(buckets.states.contains_key("slow"));
+    }
+
+    #[test]
+    fn test_number_of_keys_is_bounded() {
+        let store = MemoryRateLimitStore::new().with_max_keys(3);
+        let bucket = TokenBucket::new(1, Duration::from_secs(3600));
+        let start = Instant::now();
+
+        for i in 0..10 {
+            assert!(store
+                .acquire_at(&format!("key{}", i), &bucket, start)
+                .is_ok());
+        }
+
+        assert_eq!(store.buckets.lock().unwrap().states.len(), 3);
+    }
+}
@@ -0,0 +1,5 @@
+mod limiter;
+
+pub mod memory;
+
+pub use limiter::*;
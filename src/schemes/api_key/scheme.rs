@@ -0,0 +1,94 @@
+use crate::{schemes::impls::prelude::*, ApiKeyError};
+
+/// Authenticates service clients by API keys issued with
+/// [`UserRepository::create_api_key`](crate::UserRepository::create_api_key).
+#[derive(Debug)]
+pub struct ApiKey {
+    header: Option<String>,
+    query_param: Option<String>,
+}
+
+impl ApiKey {
+    pub fn default_header() -> &'static str {
+        "X-API-Key"
+    }
+
+    /// Read keys from the given header.
+    pub fn new(header: impl Into<String>) -> Self {
+        Self {
+            header: Some(header.into()),
+            query_param: None,
+        }
+    }
+
+    /// Read keys from the given query parameter.
+    pub fn from_query(query_param: impl Into<String>) -> Self {
+        Self {
+            header: None,
+            query_param: Some(query_param.into()),
+        }
+    }
+
+    /// Additionally read keys from the given query parameter. Keys in query parameters
+    /// are likely to end up in logs, so prefer headers where clients support them.
+    pub fn with_query_param(mut self, query_param: impl Into<String>) -> Self {
+        self.query_param = Some(query_param.into());
+        self
+    }
+
+    fn read_key<'r>(&self, req: &'r rocket::Request<'_>) -> Option<&'r str> {
+        if let Some(key) = self
+            .header
+            .as_ref()
+            .and_then(|header| req.headers().get_one(header))
+        {
+            return Some(key);
+        }
+
+        self.query_param
+            .as_ref()
+            .and_then(|param| req.query_value::<&str>(param))
+            .and_then(Result::ok)
+    }
+}
+
+impl Default for ApiKey {
+    fn default() -> Self {
+        Self::new(Self::default_header())
+    }
+}
+
+#[rocket::async_trait]
+impl AuthenticationScheme for ApiKey {
+    fn name(&self) -> String {
+        match (&self.header, &self.query_param) {
+            (Some(header), Some(param)) => format!("ApiKey(header={}, query={})", header, param),
+            (Some(header), None) => format!("ApiKey(header={})", header),
+            (None, Some(param)) => format!("ApiKey(query={})", param),
+            (None, None) => "ApiKey".to_owned(),
+        }
+    }
+
+    async fn authenticate(&self, req: &rocket::Request) -> Outcome {
+        let Some(key) = self.read_key(req) else {
+            return Outcome::Forward(());
+        };
+
+        let users = req.user_repository().await;
+
+        match users.authenticate_api_key(key).await {
+            Ok(user) => Outcome::Success(user),
+            Err(ApiKeyError::InvalidKey | ApiKeyError::Expired) => {
+                Outcome::Failure(AuthenticationError::Unauthenticated)
+            }
+            Err(e) => {
+                log::error!("Failed to authenticate API key: {}", e);
+                Outcome::Failure(AuthenticationError::Other)
+            }
+        }
+    }
+
+    async fn challenge(&self, res: &mut rocket::Response) {
+        res.adjoin_header(rocket::http::Header::new("WWW-Authenticate", "ApiKey"));
+    }
+}
@@ -1,59 +1,121 @@
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use base64::{
+    alphabet,
+    engine::{DecodePaddingMode, GeneralPurpose, GeneralPurposeConfig},
+    Engine,
+};
 use rocket::Request;
 
-use crate::{schemes::impls::prelude::*, LoginError};
+use crate::{schemes::impls::prelude::*, util::auth_credentials, LoginError};
 
+/// Standard base64 that accepts credentials with or without padding, since clients
+/// differ in whether they pad.
+const BASE64: GeneralPurpose = GeneralPurpose::new(
+    &alphabet::STANDARD,
+    GeneralPurposeConfig::new().with_decode_padding_mode(DecodePaddingMode::Indifferent),
+);
+
+/// HTTP Basic authentication (RFC 7617).
 #[derive(Debug)]
 pub struct Basic {
     realm: String,
+    charset: BasicCharset,
     challenge: String,
 }
 
+/// How the credentials of Basic authentication are decoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BasicCharset {
+    /// Advertise `charset="UTF-8"` in the challenge and only accept UTF-8 credentials.
+    Utf8,
+
+    /// Do not advertise a charset, and decode credentials as UTF-8 if they are valid
+    /// UTF-8 and as ISO-8859-1 otherwise, as older clients send them.
+    Iso8859_1,
+}
+
 impl Basic {
     pub fn new(realm: &str) -> Self {
         Self {
             realm: realm.to_string(),
-            challenge: format!(r#"Basic realm="{}", charset="UTF-8""#, realm),
+            charset: BasicCharset::Utf8,
+            challenge: Self::create_challenge(realm, BasicCharset::Utf8),
         }
     }
 
-    async fn authenticate_with_header(header: &str, req: &Request<'_>) -> Outcome {
-        // We expect a Basic scheme
-        let Some(credentials) = header.strip_prefix("Basic ") else {
-            return Outcome::Forward(());
-        };
-
-        let credentials = credentials.trim();
+    /// Decode credentials with the given charset. Defaults to UTF-8.
+    pub fn with_charset(mut self, charset: BasicCharset) -> Self {
+        self.charset = charset;
+        self.challenge = Self::create_challenge(&self.realm, charset);
+        self
+    }
 
-        let credentials = match BASE64.decode(credentials) {
-            Ok(creds) => creds,
-            Err(err) => {
-                log::error!("Failed to decode credentials: {}", err);
-                return Outcome::Failure(AuthenticationError::InvalidParams);
-            }
-        };
+    fn create_challenge(realm: &str, charset: BasicCharset) -> String {
+        match charset {
+            BasicCharset::Utf8 => format!(r#"Basic realm="{}", charset="UTF-8""#, realm),
+            BasicCharset::Iso8859_1 => format!(r#"Basic realm="{}""#, realm),
+        }
+    }
 
-        let credentials = match String::from_utf8(credentials) {
-            Ok(creds) => creds,
-            Err(err) => {
-                log::error!("Failed to decode credentials: {}", err);
-                return Outcome::Failure(AuthenticationError::InvalidParams);
-            }
+    async fn authenticate_with_header(&self, header: &str, req: &Request<'_>) -> Outcome {
+        // We expect a Basic scheme
+        let Some(credentials) = basic_credentials(header) else {
+            return Outcome::Forward(());
         };
 
-        let Some((username, pass)) = credentials.split_once(':') else {
+        // Malformed credentials are treated like wrong credentials, so the client is
+        // challenged again
+        let Some((username, pass)) = decode_credentials(credentials, self.charset) else {
             return Outcome::Failure(AuthenticationError::Unauthenticated);
         };
 
         let repository = req.user_repository().await;
 
-        match repository.authenticate(username, pass).await {
+        match repository.authenticate(&username, &pass).await {
             Ok(user) => Outcome::Success(user),
             Err(err) => Outcome::Failure(err.into()),
         }
     }
 }
 
+/// The credentials of an `Authorization` header with the Basic scheme.
+pub(crate) fn basic_credentials(header: &str) -> Option<&str> {
+    auth_credentials(header, "Basic")
+}
+
+/// Decode Basic credentials into the username and password. The username cannot
+/// contain a colon, but the password can.
+pub(crate) fn decode_credentials(
+    credentials: &str,
+    charset: BasicCharset,
+) -> Option<(String, String)> {
+    let credentials = match BASE64.decode(credentials) {
+        Ok(creds) => creds,
+        Err(err) => {
+            log::warn!("Failed to decode credentials: {}", err);
+            return None;
+        }
+    };
+
+    let credentials = match (String::from_utf8(credentials), charset) {
+        (Ok(creds), _) => creds,
+        (Err(err), BasicCharset::Utf8) => {
+            log::warn!("Failed to decode credentials: {}", err);
+            return None;
+        }
+        // Every byte is a character in ISO-8859-1, with the same code point
+        (Err(err), BasicCharset::Iso8859_1) => {
+            err.into_bytes().into_iter().map(char::from).collect()
+        }
+    };
+
+    let Some((username, pass)) = credentials.split_once(':') else {
+        log::warn!("Basic credentials do not contain a colon");
+        return None;
+    };
+
+    Some((username.to_owned(), pass.to_owned()))
+}
+
 #[rocket::async_trait]
 impl AuthenticationScheme for Basic {
     fn name(&self) -> String {
@@ -62,7 +124,7 @@ impl AuthenticationScheme for Basic {
 
     async fn authenticate(&self, req: &rocket::Request) -> Outcome {
         for header in req.headers().get("Authorization") {
-            match (Basic::authenticate_with_header(header, req)).await {
+            match self.authenticate_with_header(header, req).await {
                 Outcome::Success(user) => return Outcome::Success(user),
                 Outcome::Failure(err) => return Outcome::Failure(err),
                 Outcome::Forward(()) => {}
@@ -87,6 +149,13 @@ impl From<LoginError> for AuthenticationError {
             LoginError::UserNotFound => AuthenticationError::Unauthenticated,
             LoginError::MissingPassword => AuthenticationError::Unauthenticated,
             LoginError::IncorrectPassword => AuthenticationError::Unauthenticated,
+            LoginError::InvalidLoginToken => AuthenticationError::Unauthenticated,
+            LoginError::LockedOut { .. } => AuthenticationError::Unauthenticated,
+            LoginError::EmailNotConfirmed => AuthenticationError::Unauthenticated,
+            LoginError::TwoFactorRequired { .. } => AuthenticationError::Unauthenticated,
+            LoginError::RateLimited { retry_after } => {
+                AuthenticationError::TooManyRequests { retry_after }
+            }
             LoginError::Other(_) => AuthenticationError::Other,
         }
     }
@@ -0,0 +1,308 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rocket::{
+    http::RawStr,
+    mtls::{
+        self,
+        x509::{FromDer, GeneralName, TbsCertificate, X509Certificate},
+    },
+    Request,
+};
+
+use crate::schemes::{impls::prelude::*, TrustedProxies};
+
+/// Authenticates services by the client certificates they present, so they do not
+/// need shared secrets. Rocket verifies certificates presented over TLS when mutual TLS
+/// is configured (see [`MutualTls`](rocket::config::MutualTls)), and a
+/// [`CertificateMapper`] maps the verified certificate to a user.
+///
+/// When TLS is terminated by a reverse proxy, the proxy can forward the certificate it
+/// verified in a header, e.g. `$ssl_client_escaped_cert` with nginx. The header is only
+/// read from requests of [`TrustedProxies`].
+pub struct ClientCertificate {
+    mapper: Box<dyn CertificateMapper>,
+    proxy_header: Option<(String, TrustedProxies)>,
+}
+
+/// What a client certificate says about the client.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct CertificateIdentity {
+    /// The distinguished name of the subject, e.g. `CN=billing, O=Example`.
+    pub subject: String,
+
+    /// The distinguished name of the issuer.
+    pub issuer: String,
+
+    pub common_name: Option<String>,
+
+    /// DNS names of the subject alternative name extension.
+    pub dns_names: Vec<String>,
+
+    /// Email addresses of the subject and the subject alternative name extension.
+    pub emails: Vec<String>,
+
+    /// URIs of the subject alternative name extension, e.g. SPIFFE IDs.
+    pub uris: Vec<String>,
+
+    /// The serial number as colon separated hex.
+    pub serial: String,
+}
+
+/// Maps the identity of a verified client certificate to a user.
+#[rocket::async_trait]
+pub trait CertificateMapper: Send + Sync + 'static {
+    /// The user the certificate identifies, or None if it does not identify a user.
+    async fn map_user(&self, identity: &CertificateIdentity, req: &Request<'_>) -> Option<User>;
+}
+
+#[rocket::async_trait]
+impl<F> CertificateMapper for F
+where
+    F: Fn(&CertificateIdentity) -> Option<User> + Send + Sync + 'static,
+{
+    async fn map_user(&self, identity: &CertificateIdentity, _req: &Request<'_>) -> Option<User> {
+        self(identity)
+    }
+}
+
+/// The parts of a certificate [`FieldMapper`] can read the username from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CertificateField {
+    CommonName,
+    DnsName,
+    Email,
+    Uri,
+}
+
+/// Uses a field of the certificate as the username, either of a user without roles or
+/// of an existing user.
+#[derive(Debug, Clone, Copy)]
+pub struct FieldMapper {
+    field: CertificateField,
+    existing_users: bool,
+}
+
+impl FieldMapper {
+    pub fn new(field: CertificateField) -> Self {
+        Self {
+            field,
+            existing_users: false,
+        }
+    }
+
+    /// Only accept certificates of existing users, which are then signed in with their
+    /// roles and claims.
+    pub fn existing_users(mut self) -> Self {
+        self.existing_users = true;
+        self
+    }
+}
+
+#[rocket::async_trait]
+impl CertificateMapper for FieldMapper {
+    async fn map_user(&self, identity: &CertificateIdentity, req: &Request<'_>) -> Option<User> {
+        let username = match self.field {
+            CertificateField::CommonName => identity.common_name.as_ref(),
+            CertificateField::DnsName => identity.dns_names.first(),
+            CertificateField::Email => identity.emails.first(),
+            CertificateField::Uri => identity.uris.first(),
+        }?;
+
+        if !self.existing_users {
+            return Some(User::with_username(username));
+        }
+
+        let users = req.user_repository().await;
+        match users.find_by_username(username).await {
+            Ok(user) => user,
+            Err(err) => {
+                log::error!("Failed to find user for client certificate: {}", err);
+                None
+            }
+        }
+    }
+}
+
+impl ClientCertificate {
+    pub fn new(mapper: impl CertificateMapper) -> Self {
+        Self {
+            mapper: Box::new(mapper),
+            proxy_header: None,
+        }
+    }
+
+    /// Also accept certificates forwarded by trusted proxies in the given header, as
+    /// URL-encoded PEM or base64-encoded DER.
+    pub fn with_proxy_header(
+        mut self,
+        header: impl Into<String>,
+        trusted_proxies: TrustedProxies,
+    ) -> Self {
+        self.proxy_header = Some((header.into(), trusted_proxies));
+        self
+    }
+
+    async fn authenticate_with_identity(
+        &self,
+        identity: CertificateIdentity,
+        req: &Request<'_>,
+    ) -> Outcome {
+        match self.mapper.map_user(&identity, req).await {
+            Some(user) => Outcome::Success(user),
+            None => {
+                log::warn!(
+                    "Client certificate {} does not identify a user",
+                    identity.subject
+                );
+                Outcome::Failure(AuthenticationError::Unauthenticated)
+            }
+        }
+    }
+
+    fn forwarded_identity(
+        &self,
+        req: &Request<'_>,
+    ) -> Option<Result<CertificateIdentity, CertificateError>> {
+        let (header, trusted_proxies) = self.proxy_header.as_ref()?;
+        let value = req.headers().get_one(header)?;
+
+        if !trusted_proxies.is_trusted(req) {
+            log::warn!(
+                "Ignoring {} header of untrusted client {:?}",
+                header,
+                req.remote()
+            );
+            return None;
+        }
+
+        Some(forwarded_identity(value))
+    }
+}
+
+fn forwarded_identity(value: &str) -> Result<CertificateIdentity, CertificateError> {
+    let value = RawStr::new(value)
+        .url_decode()
+        .map_err(|_| CertificateError::Encoding)?;
+
+    let encoded = match value.split_once("-----BEGIN CERTIFICATE-----") {
+        Some((_, pem)) => {
+            pem.split_once("-----END CERTIFICATE-----")
+                .ok_or(CertificateError::Encoding)?
+                .0
+        }
+        None => &value,
+    };
+
+    let encoded = encoded
+        .chars()
+        .filter(|c| !c.is_whitespace())
+        .collect::<String>();
+    let der = BASE64
+        .decode(encoded)
+        .map_err(|_| CertificateError::Encoding)?;
+
+    let (rest, certificate) =
+        X509Certificate::from_der(&der).map_err(|_| CertificateError::Parse)?;
+    if !rest.is_empty() {
+        return Err(CertificateError::Parse);
+    }
+
+    // The proxy verified the chain, but the certificate may have expired since
+    if !certificate.validity().is_valid() {
+        return Err(CertificateError::Expired);
+    }
+
+    Ok(CertificateIdentity::from(&certificate.tbs_certificate))
+}
+
+impl From<&TbsCertificate<'_>> for CertificateIdentity {
+    fn from(certificate: &TbsCertificate<'_>) -> Self {
+        let mut identity = CertificateIdentity {
+            subject: certificate.subject().to_string(),
+            issuer: certificate.issuer().to_string(),
+            common_name: certificate
+                .subject()
+                .iter_common_name()
+                .find_map(|cn| cn.as_str().ok())
+                .map(str::to_owned),
+            serial: certificate.raw_serial_as_string(),
+            emails: certificate
+                .subject()
+                .iter_email()
+                .filter_map(|email| email.as_str().ok())
+                .map(str::to_owned)
+                .collect(),
+            ..Default::default()
+        };
+
+        if let Ok(Some(san)) = certificate.subject_alternative_name() {
+            for name in &san.value.general_names {
+                match name {
+                    GeneralName::DNSName(name) => identity.dns_names.push((*name).to_owned()),
+                    GeneralName::RFC822Name(email) => identity.emails.push((*email).to_owned()),
+                    GeneralName::URI(uri) => identity.uris.push((*uri).to_owned()),
+                    _ => {}
+                }
+            }
+        }
+
+        identity
+    }
+}
+
+#[rocket::async_trait]
+impl AuthenticationScheme for ClientCertificate {
+    fn name(&self) -> String {
+        match &self.proxy_header {
+            Some((header, _)) => format!("ClientCertificate(header={})", header),
+            None => "ClientCertificate".to_owned(),
+        }
+    }
+
+    async fn authenticate(&self, req: &Request) -> Outcome {
+        match req.guard::<mtls::Certificate<'_>>().await {
+            rocket::outcome::Outcome::Success(certificate) => {
+                let identity = CertificateIdentity::from(&*certificate);
+                return self.authenticate_with_identity(identity, req).await;
+            }
+            rocket::outcome::Outcome::Failure((_, err)) => {
+                log::error!("Invalid client certificate: {}", err);
+                return Outcome::Failure(AuthenticationError::Unauthenticated);
+            }
+            rocket::outcome::Outcome::Forward(()) => {}
+        }
+
+        match self.forwarded_identity(req) {
+            Some(Ok(identity)) => self.authenticate_with_identity(identity, req).await,
+            Some(Err(err)) => {
+                log::error!("Invalid forwarded client certificate: {}", err);
+                Outcome::Failure(AuthenticationError::InvalidParams)
+            }
+            // No client certificate, we cannot handle the request
+            None => Outcome::Forward(()),
+        }
+    }
+
+    async fn challenge(&self, _res: &mut rocket::Response) {
+        // Client certificates are requested during the TLS handshake
+    }
+}
+
+impl core::fmt::Debug for ClientCertificate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ClientCertificate")
+            .field("proxy_header", &self.proxy_header)
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum CertificateError {
+    #[error("the certificate is not URL-encoded PEM or base64-encoded DER")]
+    Encoding,
+
+    #[error("the certificate could not be parsed")]
+    Parse,
+
+    #[error("the certificate is expired or not yet valid")]
+    Expired,
+}
@@ -1,10 +1,62 @@
+use rocket::{
+    http::{Cookie, SameSite},
+    time::Duration,
+};
+
 use crate::schemes::impls::prelude::*;
 
 use super::session_data::SessionData;
 
 #[derive(Debug)]
 pub struct CookieScheme {
-    cookie_name: String,
+    options: CookieOptions,
+}
+
+/// Options for the session cookie. [`CookieSession`](super::CookieSession) signs users
+/// in with the options of the configured scheme.
+#[derive(Debug, Clone)]
+pub struct CookieOptions {
+    /// The name of the session cookie. The cookies of pending sign-ins are named after
+    /// it.
+    pub name: String,
+
+    /// Whether the cookie is only sent over HTTPS. Without a value, it is if TLS is
+    /// enabled.
+    pub secure: Option<bool>,
+
+    /// Without a value, `SameSite=Strict` is used.
+    pub same_site: Option<SameSite>,
+
+    /// How long the session lasts. Without a value, it lasts until the browser is
+    /// closed.
+    pub max_age: Option<Duration>,
+}
+
+impl CookieOptions {
+    pub(crate) fn apply(&self, cookie: &mut Cookie<'_>) {
+        if let Some(secure) = self.secure {
+            cookie.set_secure(secure);
+        }
+
+        if let Some(same_site) = self.same_site {
+            cookie.set_same_site(same_site);
+        }
+
+        if let Some(max_age) = self.max_age {
+            cookie.set_max_age(max_age);
+        }
+    }
+}
+
+impl Default for CookieOptions {
+    fn default() -> Self {
+        Self {
+            name: CookieScheme::default_cookie_name().to_owned(),
+            secure: None,
+            same_site: None,
+            max_age: None,
+        }
+    }
 }
 
 impl CookieScheme {
@@ -12,10 +64,30 @@ impl CookieScheme {
         "rocket_identity"
     }
 
+    /// The name of the cookie marking a pending two-factor sign-in for a session cookie.
+    pub fn two_factor_cookie_name(cookie_name: &str) -> String {
+        format!("{}_2fa", cookie_name)
+    }
+
+    /// The name of the cookie holding the challenge of a passkey ceremony.
+    pub fn passkey_cookie_name(cookie_name: &str) -> String {
+        format!("{}_webauthn", cookie_name)
+    }
+
+    /// The name of the cookie holding a pending sign-in with an external provider.
+    pub fn external_cookie_name(cookie_name: &str) -> String {
+        format!("{}_external", cookie_name)
+    }
+
     pub fn new(cookie_name: impl Into<String>) -> Self {
-        Self {
-            cookie_name: cookie_name.into(),
-        }
+        Self::with_options(CookieOptions {
+            name: cookie_name.into(),
+            ..CookieOptions::default()
+        })
+    }
+
+    pub fn with_options(options: CookieOptions) -> Self {
+        Self { options }
     }
 }
 
@@ -28,14 +100,23 @@ impl Default for CookieScheme {
 #[rocket::async_trait]
 impl AuthenticationScheme for CookieScheme {
     fn name(&self) -> String {
-        format!("Cookie({})", self.cookie_name)
+        format!("Cookie({})", self.options.name)
+    }
+
+    fn setup(&mut self, rocket: rocket::Rocket<rocket::Build>) -> rocket::Rocket<rocket::Build> {
+        // With several cookie schemes, sessions use the options of the first one
+        if rocket.state::<CookieOptions>().is_some() {
+            return rocket;
+        }
+
+        rocket.manage(self.options.clone())
     }
 
     async fn authenticate(&self, req: &rocket::Request) -> Outcome {
         let users = req.user_repository().await;
         let cookies = req.cookies();
 
-        let Some(session_cookie) = cookies.get_private(&self.cookie_name) else {
+        let Some(session_cookie) = cookies.get_private(&self.options.name) else {
             return Outcome::Forward(());
         };
 
@@ -1,23 +1,49 @@
 use std::borrow::Cow;
 
 use rocket::{
-    http::CookieJar,
+    http::{Cookie, CookieJar},
     request::{FromRequest, Outcome},
+    serde::json::serde_json,
+    time::Duration,
     Request,
 };
 
-use crate::User;
+use crate::{
+    external::PendingAuthorization,
+    stores::{ExternalLogin, Passkey},
+    webauthn::{
+        AssertionCredential, CreationOptions, PasskeyChallenge, RegistrationCredential,
+        RequestOptions,
+    },
+    ExternalLoginError, LoginError, PasskeyError, TwoFactorError, User, UserRepository,
+};
+
+use super::{
+    session_data::{SessionData, TwoFactorSessionData},
+    CookieOptions, CookieScheme,
+};
+
+/// How long a user has to provide the second factor after the password was verified.
+const TWO_FACTOR_LIFETIME: Duration = Duration::minutes(5);
 
-use super::{session_data::SessionData, CookieScheme};
+/// How long a user has to sign in at an external provider.
+const EXTERNAL_SIGN_IN_LIFETIME: Duration = Duration::minutes(10);
 
 #[derive(Debug)]
 pub struct CookieSession<'r> {
     cookie_jar: &'r CookieJar<'r>,
+    options: Option<&'r CookieOptions>,
 }
 
 impl<'r> CookieSession<'r> {
+    /// The name of the session cookie of the configured [`CookieScheme`].
+    pub fn cookie_name(&self) -> &'r str {
+        self.options
+            .map_or(CookieScheme::default_cookie_name(), |o| o.name.as_str())
+    }
+
     pub fn sign_in(&self, user: &User) {
-        self.sign_in_with_cookie(user, CookieScheme::default_cookie_name())
+        self.sign_in_with_cookie(user, self.cookie_name().to_owned())
     }
 
     pub fn sign_in_with_cookie(&self, user: &User, cookie_name: impl Into<Cow<'static, str>>) {
@@ -25,11 +51,419 @@ impl<'r> CookieSession<'r> {
             username: user.username.clone(),
         };
 
+        let mut cookie = session.into_cookie(cookie_name);
+        if let Some(options) = self.options {
+            options.apply(&mut cookie);
+        }
+
+        self.cookie_jar.add_private(cookie);
+    }
+
+    /// Exchange a login token, e.g. from a login link, for a session. If the user has
+    /// two-factor authentication enabled, a pending two-factor sign-in is started instead
+    /// and [`LoginError::TwoFactorRequired`] is returned.
+    pub async fn sign_in_with_login_token(
+        &self,
+        users: &UserRepository,
+        username: &str,
+        token: &str,
+    ) -> Result<User, LoginError> {
+        self.sign_in_with_login_token_and_cookie(users, username, token, self.cookie_name())
+            .await
+    }
+
+    pub async fn sign_in_with_login_token_and_cookie(
+        &self,
+        users: &UserRepository,
+        username: &str,
+        token: &str,
+        cookie_name: &str,
+    ) -> Result<User, LoginError> {
+        match users.authenticate_with_login_token(username, token).await {
+            Ok(user) => {
+                self.sign_in_with_cookie(&user, cookie_name.to_owned());
+                Ok(user)
+            }
+            Err(LoginError::TwoFactorRequired { user }) => {
+                self.begin_two_factor_with_cookie(&user, cookie_name);
+                Err(LoginError::TwoFactorRequired { user })
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Remember a user whose password was verified but who still has to provide a second
+    /// factor. The user is not signed in until [`complete_two_factor`] succeeds.
+    ///
+    /// [`complete_two_factor`]: CookieSession::complete_two_factor
+    pub fn begin_two_factor(&self, user: &User) {
+        self.begin_two_factor_with_cookie(user, self.cookie_name())
+    }
+
+    pub fn begin_two_factor_with_cookie(&self, user: &User, cookie_name: &str) {
+        let pending = TwoFactorSessionData::new(user.username.clone(), TWO_FACTOR_LIFETIME);
+
+        self.cookie_jar.add_private(pending.into_cookie(
+            CookieScheme::two_factor_cookie_name(cookie_name),
+            TWO_FACTOR_LIFETIME,
+        ));
+    }
+
+    /// The user of a pending two-factor sign-in, if there is one and it has not expired.
+    pub async fn two_factor_user(
+        &self,
+        users: &UserRepository,
+    ) -> Result<Option<User>, TwoFactorSignInError> {
+        self.two_factor_user_with_cookie(users, self.cookie_name())
+            .await
+    }
+
+    pub async fn two_factor_user_with_cookie(
+        &self,
+        users: &UserRepository,
+        cookie_name: &str,
+    ) -> Result<Option<User>, TwoFactorSignInError> {
+        let cookie_name = CookieScheme::two_factor_cookie_name(cookie_name);
+
+        let Some(cookie) = self.cookie_jar.get_private(&cookie_name) else {
+            return Ok(None);
+        };
+
+        let pending = match TwoFactorSessionData::try_from(cookie) {
+            Ok(pending) if !pending.is_expired() => pending,
+            Ok(_) => return Ok(None),
+            Err(e) => {
+                log::error!("Failed to deserialize two-factor session data: {}", e);
+                return Ok(None);
+            }
+        };
+
+        users
+            .find_by_username(&pending.username)
+            .await
+            .map_err(|e| TwoFactorSignInError::Other(e.into()))
+    }
+
+    /// Verify the second factor of a pending two-factor sign-in and upgrade it to a
+    /// normal session.
+    pub async fn complete_two_factor(
+        &self,
+        users: &UserRepository,
+        code: &str,
+    ) -> Result<User, TwoFactorSignInError> {
+        self.complete_two_factor_with_cookie(users, code, self.cookie_name())
+            .await
+    }
+
+    pub async fn complete_two_factor_with_cookie(
+        &self,
+        users: &UserRepository,
+        code: &str,
+        cookie_name: &str,
+    ) -> Result<User, TwoFactorSignInError> {
+        self.complete_pending(users, SecondFactor::Code(code), cookie_name)
+            .await
+    }
+
+    /// Complete a pending two-factor sign-in with a recovery code instead of a TOTP code.
+    /// The recovery code is consumed.
+    pub async fn complete_two_factor_with_recovery_code(
+        &self,
+        users: &UserRepository,
+        code: &str,
+    ) -> Result<User, TwoFactorSignInError> {
+        self.complete_two_factor_with_recovery_code_and_cookie(users, code, self.cookie_name())
+            .await
+    }
+
+    pub async fn complete_two_factor_with_recovery_code_and_cookie(
+        &self,
+        users: &UserRepository,
+        code: &str,
+        cookie_name: &str,
+    ) -> Result<User, TwoFactorSignInError> {
+        self.complete_pending(users, SecondFactor::RecoveryCode(code), cookie_name)
+            .await
+    }
+
+    /// Start registering a passkey for a signed in user. The returned options are passed
+    /// to `navigator.credentials.create()` in the browser.
+    pub async fn begin_passkey_registration(
+        &self,
+        users: &UserRepository,
+        user: &User,
+    ) -> Result<CreationOptions, PasskeyError> {
+        self.begin_passkey_registration_with_cookie(users, user, self.cookie_name())
+            .await
+    }
+
+    pub async fn begin_passkey_registration_with_cookie(
+        &self,
+        users: &UserRepository,
+        user: &User,
+        cookie_name: &str,
+    ) -> Result<CreationOptions, PasskeyError> {
+        let (options, challenge) = users.start_passkey_registration(user).await?;
+        self.add_passkey_challenge(users, &challenge, cookie_name);
+
+        Ok(options)
+    }
+
+    /// Verify the new credential created by the browser and store it as a passkey.
+    pub async fn complete_passkey_registration(
+        &self,
+        users: &UserRepository,
+        user: &User,
+        credential: &RegistrationCredential,
+    ) -> Result<Passkey, PasskeySignInError> {
+        self.complete_passkey_registration_with_cookie(users, user, credential, self.cookie_name())
+            .await
+    }
+
+    pub async fn complete_passkey_registration_with_cookie(
+        &self,
+        users: &UserRepository,
+        user: &User,
+        credential: &RegistrationCredential,
+        cookie_name: &str,
+    ) -> Result<Passkey, PasskeySignInError> {
+        let Some(challenge) = self.take_passkey_challenge(cookie_name) else {
+            return Err(PasskeySignInError::NotPending);
+        };
+
+        Ok(users
+            .finish_passkey_registration(user, &challenge, credential)
+            .await?)
+    }
+
+    /// Start signing in with a passkey. The returned options are passed to
+    /// `navigator.credentials.get()` in the browser.
+    pub async fn begin_passkey_sign_in(
+        &self,
+        users: &UserRepository,
+    ) -> Result<RequestOptions, PasskeyError> {
+        self.begin_passkey_sign_in_with_cookie(users, self.cookie_name())
+            .await
+    }
+
+    pub async fn begin_passkey_sign_in_with_cookie(
+        &self,
+        users: &UserRepository,
+        cookie_name: &str,
+    ) -> Result<RequestOptions, PasskeyError> {
+        let (options, challenge) = users.start_passkey_authentication(None).await?;
+        self.add_passkey_challenge(users, &challenge, cookie_name);
+
+        Ok(options)
+    }
+
+    /// Verify the assertion created by the browser and sign in the user the passkey
+    /// belongs to. If the user has two-factor authentication enabled, a pending two-factor
+    /// sign-in is started instead and [`LoginError::TwoFactorRequired`] is returned as the
+    /// cause.
+    pub async fn complete_passkey_sign_in(
+        &self,
+        users: &UserRepository,
+        credential: &AssertionCredential,
+    ) -> Result<User, PasskeySignInError> {
+        self.complete_passkey_sign_in_with_cookie(users, credential, self.cookie_name())
+            .await
+    }
+
+    pub async fn complete_passkey_sign_in_with_cookie(
+        &self,
+        users: &UserRepository,
+        credential: &AssertionCredential,
+        cookie_name: &str,
+    ) -> Result<User, PasskeySignInError> {
+        let Some(challenge) = self.take_passkey_challenge(cookie_name) else {
+            return Err(PasskeySignInError::NotPending);
+        };
+
+        match users
+            .finish_passkey_authentication(&challenge, credential)
+            .await
+        {
+            Ok(user) => {
+                self.sign_in_with_cookie(&user, cookie_name.to_owned());
+                Ok(user)
+            }
+            Err(PasskeyError::Login(LoginError::TwoFactorRequired { user })) => {
+                self.begin_two_factor_with_cookie(&user, cookie_name);
+                Err(PasskeyError::Login(LoginError::TwoFactorRequired { user }).into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Start signing in with an external provider. Redirect the user to the returned
+    /// URL; the provider sends the user back to its redirect URI with a `state` and a
+    /// `code`, which are passed to [`complete_external_sign_in`].
+    ///
+    /// [`complete_external_sign_in`]: CookieSession::complete_external_sign_in
+    pub fn begin_external_sign_in(
+        &self,
+        users: &UserRepository,
+        provider: &str,
+    ) -> Result<String, ExternalLoginError> {
+        self.begin_external_sign_in_with_cookie(users, provider, self.cookie_name())
+    }
+
+    pub fn begin_external_sign_in_with_cookie(
+        &self,
+        users: &UserRepository,
+        provider: &str,
+        cookie_name: &str,
+    ) -> Result<String, ExternalLoginError> {
+        let (url, pending) = users.start_external_login(provider)?;
+
+        let cookie = Cookie::build(
+            CookieScheme::external_cookie_name(cookie_name),
+            serde_json::to_string(&pending).expect("This should never fail"),
+        )
+        .max_age(EXTERNAL_SIGN_IN_LIFETIME)
+        .finish();
+
+        self.cookie_jar.add_private(cookie);
+
+        Ok(url)
+    }
+
+    /// Complete signing in with an external provider and sign in the linked user. If the
+    /// user has two-factor authentication enabled, a pending two-factor sign-in is started
+    /// instead and [`LoginError::TwoFactorRequired`] is returned as the cause.
+    pub async fn complete_external_sign_in(
+        &self,
+        users: &UserRepository,
+        state: &str,
+        code: &str,
+    ) -> Result<User, ExternalSignInError> {
+        self.complete_external_sign_in_with_cookie(users, state, code, self.cookie_name())
+            .await
+    }
+
+    pub async fn complete_external_sign_in_with_cookie(
+        &self,
+        users: &UserRepository,
+        state: &str,
+        code: &str,
+        cookie_name: &str,
+    ) -> Result<User, ExternalSignInError> {
+        let Some(pending) = self.take_external_sign_in(cookie_name) else {
+            return Err(ExternalSignInError::NotPending);
+        };
+
+        match users.finish_external_login(&pending, state, code).await {
+            Ok(user) => {
+                self.sign_in_with_cookie(&user, cookie_name.to_owned());
+                Ok(user)
+            }
+            Err(ExternalLoginError::Login(LoginError::TwoFactorRequired { user })) => {
+                self.begin_two_factor_with_cookie(&user, cookie_name);
+                Err(ExternalLoginError::Login(LoginError::TwoFactorRequired { user }).into())
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Complete an authorization request started with [`begin_external_sign_in`] by
+    /// linking the external identity to the signed in user instead of signing in.
+    ///
+    /// [`begin_external_sign_in`]: CookieSession::begin_external_sign_in
+    pub async fn complete_external_link(
+        &self,
+        users: &UserRepository,
+        user: &User,
+        state: &str,
+        code: &str,
+    ) -> Result<ExternalLogin, ExternalSignInError> {
+        let Some(pending) = self.take_external_sign_in(self.cookie_name()) else {
+            return Err(ExternalSignInError::NotPending);
+        };
+
+        Ok(users
+            .finish_external_link(user, &pending, state, code)
+            .await?)
+    }
+
+    /// Remove the pending sign-in with an external provider, so that each authorization
+    /// request can only be completed once.
+    fn take_external_sign_in(&self, cookie_name: &str) -> Option<PendingAuthorization> {
+        let cookie_name = CookieScheme::external_cookie_name(cookie_name);
+        let cookie = self.cookie_jar.get_private(&cookie_name)?;
+        self.cookie_jar.remove_private(Cookie::named(cookie_name));
+
+        match serde_json::from_str(cookie.value()) {
+            Ok(pending) => Some(pending),
+            Err(e) => {
+                log::error!("Failed to deserialize pending external sign-in: {}", e);
+                None
+            }
+        }
+    }
+
+    fn add_passkey_challenge(
+        &self,
+        users: &UserRepository,
+        challenge: &PasskeyChallenge,
+        cookie_name: &str,
+    ) {
+        let cookie = Cookie::build(
+            CookieScheme::passkey_cookie_name(cookie_name),
+            serde_json::to_string(challenge).expect("This should never fail"),
+        )
+        .max_age(users.webauthn.timeout)
+        .finish();
+
+        self.cookie_jar.add_private(cookie);
+    }
+
+    /// Remove the challenge of a passkey ceremony, so that each challenge can only be
+    /// answered once.
+    fn take_passkey_challenge(&self, cookie_name: &str) -> Option<PasskeyChallenge> {
+        let cookie_name = CookieScheme::passkey_cookie_name(cookie_name);
+        let cookie = self.cookie_jar.get_private(&cookie_name)?;
+        self.cookie_jar.remove_private(Cookie::named(cookie_name));
+
+        match serde_json::from_str(cookie.value()) {
+            Ok(challenge) => Some(challenge),
+            Err(e) => {
+                log::error!("Failed to deserialize passkey challenge: {}", e);
+                None
+            }
+        }
+    }
+
+    async fn complete_pending(
+        &self,
+        users: &UserRepository,
+        second_factor: SecondFactor<'_>,
+        cookie_name: &str,
+    ) -> Result<User, TwoFactorSignInError> {
+        let Some(user) = self.two_factor_user_with_cookie(users, cookie_name).await? else {
+            return Err(TwoFactorSignInError::NotPending);
+        };
+
+        match second_factor {
+            SecondFactor::Code(code) => users.verify_two_factor_code(&user, code).await?,
+            SecondFactor::RecoveryCode(code) => users.redeem_recovery_code(&user, code).await?,
+        }
+
         self.cookie_jar
-            .add_private(session.into_cookie(cookie_name));
+            .remove_private(Cookie::named(CookieScheme::two_factor_cookie_name(
+                cookie_name,
+            )));
+        self.sign_in_with_cookie(&user, cookie_name.to_owned());
+
+        Ok(user)
     }
 }
 
+enum SecondFactor<'a> {
+    Code(&'a str),
+    RecoveryCode(&'a str),
+}
+
 // implement FromRequest for CookieSession
 #[rocket::async_trait]
 impl<'r> FromRequest<'r> for CookieSession<'r> {
@@ -37,7 +471,41 @@ impl<'r> FromRequest<'r> for CookieSession<'r> {
 
     async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
         let cookie_jar = req.cookies();
+        let options = req.rocket().state::<CookieOptions>();
 
-        Outcome::Success(CookieSession { cookie_jar })
+        Outcome::Success(CookieSession {
+            cookie_jar,
+            options,
+        })
     }
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum TwoFactorSignInError {
+    #[error("there is no pending two-factor sign-in")]
+    NotPending,
+
+    #[error("second factor could not be verified")]
+    TwoFactor(#[from] TwoFactorError),
+
+    #[error("two-factor sign-in failed")]
+    Other(#[from] Box<dyn std::error::Error>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeySignInError {
+    #[error("there is no pending passkey ceremony")]
+    NotPending,
+
+    #[error("passkey could not be verified")]
+    Passkey(#[from] PasskeyError),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExternalSignInError {
+    #[error("there is no pending sign-in with an external provider")]
+    NotPending,
+
+    #[error("sign-in with the external provider failed")]
+    External(#[from] ExternalLoginError),
+}
@@ -3,6 +3,7 @@ use std::borrow::Cow;
 use rocket::{
     http::Cookie,
     serde::{Deserialize, Serialize, json::serde_json},
+    time::{Duration, OffsetDateTime},
 };
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,3 +25,45 @@ impl<'a> TryFrom<Cookie<'a>> for SessionData {
         serde_json::from_str(cookie.value())
     }
 }
+
+/// A user that provided a correct password but still has to provide a second factor.
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(crate = "rocket::serde")]
+pub(crate) struct TwoFactorSessionData {
+    pub username: String,
+    pub expires: i64,
+}
+
+impl TwoFactorSessionData {
+    pub fn new(username: String, lifetime: Duration) -> Self {
+        Self {
+            username,
+            expires: (OffsetDateTime::now_utc() + lifetime).unix_timestamp(),
+        }
+    }
+
+    pub fn is_expired(&self) -> bool {
+        OffsetDateTime::now_utc().unix_timestamp() >= self.expires
+    }
+
+    pub fn into_cookie(
+        self,
+        name: impl Into<Cow<'static, str>>,
+        lifetime: Duration,
+    ) -> Cookie<'static> {
+        Cookie::build(
+            name,
+            serde_json::to_string(&self).expect("This should never fail"),
+        )
+        .max_age(lifetime)
+        .finish()
+    }
+}
+
+impl<'a> TryFrom<Cookie<'a>> for TwoFactorSessionData {
+    type Error = serde_json::Error;
+
+    fn try_from(cookie: Cookie<'a>) -> Result<Self, Self::Error> {
+        serde_json::from_str(cookie.value())
+    }
+}
@@ -0,0 +1,3 @@
+mod scheme;
+
+pub use scheme::*;
@@ -0,0 +1,304 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use data_encoding::BASE64URL_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use rocket::{
+    time::{Duration, OffsetDateTime},
+    Request,
+};
+use sha2::Sha256;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    schemes::impls::prelude::*,
+    stores::{DigestAlgorithm, DigestCredentials},
+    util::{auth_credentials, parse_auth_params},
+};
+
+const TIMESTAMP_LEN: usize = 8;
+const RANDOM_LEN: usize = 8;
+const MAC_LEN: usize = 16;
+
+/// HTTP Digest authentication (RFC 7616) with `qop=auth`, for clients that cannot use
+/// [`Basic`](crate::schemes::basic::Basic) over TLS.
+///
+/// Users need digest credentials for the realm, which are set with
+/// [`UserRepository::set_digest_password`](crate::UserRepository::set_digest_password)
+/// and require a store implementing
+/// [`UserDigestStoreScope`](crate::stores::UserDigestStoreScope).
+///
+/// Nonces are signed with a key generated at startup and expire after the nonce
+/// lifetime. Within its lifetime a nonce can be reused with an increasing nonce count.
+pub struct Digest {
+    realm: String,
+    algorithms: Vec<DigestAlgorithm>,
+    nonce_lifetime: Duration,
+    nonce_key: [u8; 32],
+    opaque: String,
+
+    /// The last nonce count seen for each nonce, until the nonce expires.
+    nonce_counts: Mutex<HashMap<String, (u32, OffsetDateTime)>>,
+}
+
+impl Digest {
+    /// Offer SHA-256 and MD5 in the given realm.
+    pub fn new(realm: &str) -> Self {
+        let mut nonce_key = [0; 32];
+        OsRng.fill_bytes(&mut nonce_key);
+
+        let mut opaque = [0; 16];
+        OsRng.fill_bytes(&mut opaque);
+
+        Self {
+            realm: realm.to_owned(),
+            algorithms: vec![DigestAlgorithm::Sha256, DigestAlgorithm::Md5],
+            nonce_lifetime: Duration::minutes(5),
+            nonce_key,
+            opaque: BASE64URL_NOPAD.encode(&opaque),
+            nonce_counts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Offer the given algorithms in order of preference, e.g. only SHA-256 once no
+    /// client needs MD5 anymore.
+    pub fn with_algorithms(mut self, algorithms: &[DigestAlgorithm]) -> Self {
+        self.algorithms = algorithms.to_vec();
+        self
+    }
+
+    /// How long nonces are accepted after they were issued.
+    pub fn with_nonce_lifetime(mut self, nonce_lifetime: Duration) -> Self {
+        self.nonce_lifetime = nonce_lifetime;
+        self
+    }
+
+    async fn authenticate_with_header(&self, header: &str, req: &Request<'_>) -> Outcome {
+        // We expect a Digest scheme
+        let Some(params) = auth_credentials(header, "Digest") else {
+            return Outcome::Forward(());
+        };
+
+        let Some(params) = parse_auth_params(params) else {
+            log::error!("Failed to parse digest response");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let param = |name: &str| params.get(name).map(String::as_str);
+
+        // Responses to a different realm are left to other schemes
+        if param("realm") != Some(self.realm.as_str()) {
+            return Outcome::Forward(());
+        }
+
+        let (Some(username), Some(nonce), Some(uri), Some(response), Some(cnonce), Some(nc)) = (
+            param("username"),
+            param("nonce"),
+            param("uri"),
+            param("response"),
+            param("cnonce"),
+            param("nc"),
+        ) else {
+            log::error!("Digest response is missing parameters");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let algorithm = param("algorithm").unwrap_or("MD5");
+        let Some(algorithm) = self
+            .algorithms
+            .iter()
+            .copied()
+            .find(|a| a.name().eq_ignore_ascii_case(algorithm))
+        else {
+            log::error!("Digest response uses unsupported algorithm {}", algorithm);
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        if param("qop") != Some("auth")
+            || param("userhash").is_some_and(|userhash| userhash != "false")
+            || param("opaque") != Some(self.opaque.as_str())
+            || *req.uri() != uri
+        {
+            log::error!("Digest response does not match the challenge or request");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        }
+
+        let Ok(nc) = u32::from_str_radix(nc, 16) else {
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let Some(expires) = self.verify_nonce(nonce) else {
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        };
+
+        // The client has to retry with a fresh nonce, which the challenge marks as stale
+        if expires <= now {
+            req.local_cache(|| StaleNonce(Some(self.realm.clone())));
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        let ha2 = algorithm.hash(&format!("{}:{}", req.method().as_str(), uri));
+        let verify = |credentials: &DigestCredentials| {
+            let expected = algorithm.hash(&format!(
+                "{}:{}:{}:{}:auth:{}",
+                credentials.ha1(algorithm),
+                nonce,
+                param("nc").unwrap_or_default(),
+                cnonce,
+                ha2
+            ));
+
+            bool::from(
+                expected
+                    .as_bytes()
+                    .ct_eq(response.to_ascii_lowercase().as_bytes()),
+            )
+        };
+
+        let users = req.user_repository().await;
+        let user = match users
+            .authenticate_digest(username, &self.realm, verify)
+            .await
+        {
+            Ok(user) => user,
+            Err(err) => return Outcome::Failure(err.into()),
+        };
+
+        // Responses cannot be replayed, since the nonce count has to increase
+        if !self.record_nonce_count(nonce, nc, expires, now) {
+            log::warn!("Replayed digest response for {}", user.username);
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        Outcome::Success(user)
+    }
+
+    /// Add a challenge per algorithm with a fresh nonce. `stale` tells the client that
+    /// only its nonce expired.
+    fn add_challenges(&self, res: &mut rocket::Response<'_>, stale: bool) {
+        let nonce = self.create_nonce();
+        let stale = if stale { ", stale=true" } else { "" };
+
+        // One challenge per algorithm, the preferred one first
+        for algorithm in &self.algorithms {
+            res.adjoin_header(rocket::http::Header::new(
+                "WWW-Authenticate",
+                format!(
+                    r#"Digest realm="{}", qop="auth", algorithm={}, nonce="{}", opaque="{}", charset=UTF-8{}"#,
+                    self.realm,
+                    algorithm.name(),
+                    nonce,
+                    self.opaque,
+                    stale
+                ),
+            ));
+        }
+    }
+
+    fn create_nonce(&self) -> String {
+        let mut nonce = Vec::with_capacity(TIMESTAMP_LEN + RANDOM_LEN + MAC_LEN);
+        nonce.extend_from_slice(&OffsetDateTime::now_utc().unix_timestamp().to_be_bytes());
+
+        let mut random = [0; RANDOM_LEN];
+        OsRng.fill_bytes(&mut random);
+        nonce.extend_from_slice(&random);
+
+        let mac = self.nonce_mac(&nonce);
+        nonce.extend_from_slice(&mac[..MAC_LEN]);
+
+        BASE64URL_NOPAD.encode(&nonce)
+    }
+
+    /// Returns when the nonce expires if it was issued by this scheme.
+    fn verify_nonce(&self, nonce: &str) -> Option<OffsetDateTime> {
+        let nonce = BASE64URL_NOPAD.decode(nonce.as_bytes()).ok()?;
+        if nonce.len() != TIMESTAMP_LEN + RANDOM_LEN + MAC_LEN {
+            return None;
+        }
+
+        let (data, mac) = nonce.split_at(TIMESTAMP_LEN + RANDOM_LEN);
+        if !bool::from(self.nonce_mac(data)[..MAC_LEN].ct_eq(mac)) {
+            return None;
+        }
+
+        let issued = i64::from_be_bytes(data[..TIMESTAMP_LEN].try_into().ok()?);
+        let expires = OffsetDateTime::from_unix_timestamp(issued).ok()? + self.nonce_lifetime;
+
+        Some(expires)
+    }
+
+    fn nonce_mac(&self, data: &[u8]) -> Vec<u8> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.nonce_key).expect("HMAC accepts any key length");
+        mac.update(data);
+        mac.finalize().into_bytes().to_vec()
+    }
+
+    /// Remember the nonce count of a nonce. Returns false if it did not increase.
+    fn record_nonce_count(
+        &self,
+        nonce: &str,
+        nc: u32,
+        expires: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> bool {
+        let mut nonce_counts = self.nonce_counts.lock().expect("Nonce counts poisoned");
+        nonce_counts.retain(|_, (_, expires)| *expires > now);
+
+        match nonce_counts.get_mut(nonce) {
+            Some((last, _)) if *last >= nc => false,
+            Some((last, _)) => {
+                *last = nc;
+                true
+            }
+            None => {
+                nonce_counts.insert(nonce.to_owned(), (nc, expires));
+                true
+            }
+        }
+    }
+}
+
+#[rocket::async_trait]
+impl AuthenticationScheme for Digest {
+    fn name(&self) -> String {
+        format!("Digest(realm={})", self.realm)
+    }
+
+    async fn authenticate(&self, req: &rocket::Request) -> Outcome {
+        for header in req.headers().get("Authorization") {
+            match self.authenticate_with_header(header, req).await {
+                Outcome::Success(user) => return Outcome::Success(user),
+                Outcome::Failure(err) => return Outcome::Failure(err),
+                Outcome::Forward(()) => {}
+            }
+        }
+
+        // No Authorization headers, we cannot handle the request
+        Outcome::Forward(())
+    }
+
+    async fn challenge(&self, res: &mut rocket::Response) {
+        self.add_challenges(res, false);
+    }
+
+    async fn challenge_request(&self, req: &rocket::Request<'_>, res: &mut rocket::Response<'_>) {
+        let StaleNonce(realm) = req.local_cache(|| StaleNonce(None));
+        self.add_challenges(res, realm.as_deref() == Some(self.realm.as_str()));
+    }
+}
+
+/// The realm of a Digest scheme that rejected the request because of an expired nonce.
+struct StaleNonce(Option<String>);
+
+impl core::fmt::Debug for Digest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Digest")
+            .field("realm", &self.realm)
+            .field("algorithms", &self.algorithms)
+            .field("nonce_lifetime", &self.nonce_lifetime)
+            .field("nonce_key", &"hidden")
+            .finish()
+    }
+}
@@ -0,0 +1,238 @@
+use std::{
+    collections::{HashMap, HashSet},
+    sync::{Arc, Mutex},
+};
+
+use rocket::{
+    serde::json::Value,
+    time::{Duration, OffsetDateTime},
+    Request,
+};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    external::{HttpClient, HttpClientError},
+    schemes::impls::prelude::*,
+};
+
+/// Authenticates opaque bearer tokens by asking the authorization server that issued
+/// them whether they are active, using token introspection (RFC 7662).
+///
+/// Active tokens are cached until they expire, but for at most five minutes by default,
+/// so that the authorization server is not asked on every request. Revoking a token
+/// therefore takes effect once the cached result expires; tokens without `exp` are
+/// introspected on every request.
+pub struct Introspection {
+    endpoint: String,
+    client_credentials: Option<(String, String)>,
+    http_client: Arc<dyn HttpClient>,
+    max_cache_duration: Duration,
+    cache: Mutex<HashMap<Vec<u8>, CachedUser>>,
+}
+
+#[derive(Debug)]
+struct CachedUser {
+    user: User,
+    expires: OffsetDateTime,
+}
+
+impl Introspection {
+    /// Introspect tokens at the given endpoint, sending requests with `http_client`.
+    pub fn new(endpoint: impl Into<String>, http_client: impl HttpClient) -> Self {
+        Self {
+            endpoint: endpoint.into(),
+            client_credentials: None,
+            http_client: Arc::new(http_client),
+            max_cache_duration: Duration::minutes(5),
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Authenticate to the introspection endpoint, which most authorization servers
+    /// require. The credentials are sent in the request body.
+    pub fn with_client_credentials(
+        mut self,
+        client_id: impl Into<String>,
+        client_secret: impl Into<String>,
+    ) -> Self {
+        self.client_credentials = Some((client_id.into(), client_secret.into()));
+        self
+    }
+
+    /// How long an active token is trusted without asking the authorization server
+    /// again, which bounds how long a revoked token is still accepted.
+    pub fn with_max_cache_duration(mut self, max_cache_duration: Duration) -> Self {
+        self.max_cache_duration = max_cache_duration;
+        self
+    }
+
+    async fn authenticate_with_header(&self, header: &str) -> Outcome {
+        // We expect a Bearer scheme
+        let Some(token) = header.strip_prefix("Bearer ") else {
+            return Outcome::Forward(());
+        };
+
+        let token = token.trim();
+        let key = Sha256::digest(token.as_bytes()).to_vec();
+        let now = OffsetDateTime::now_utc();
+
+        if let Some(cached) = self.cache().get(&key) {
+            if cached.expires > now {
+                return Outcome::Success(cached.user.clone());
+            }
+        }
+
+        let response = match self.introspect(token).await {
+            Ok(response) => response,
+            Err(err) => {
+                log::error!("Failed to introspect token: {}", err);
+                return Outcome::Failure(AuthenticationError::Other);
+            }
+        };
+
+        if response.get("active").and_then(Value::as_bool) != Some(true) {
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        let expires = match response.get("exp").map(Value::as_i64) {
+            None => None,
+            Some(Some(exp)) => OffsetDateTime::from_unix_timestamp(exp).ok(),
+            Some(None) => {
+                log::error!("Failed to get user data from introspection: invalid exp");
+                return Outcome::Failure(AuthenticationError::InvalidParams);
+            }
+        };
+
+        if expires.is_some_and(|expires| expires <= now) {
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        let user = match read_user(response) {
+            Ok(user) => user,
+            Err(err) => {
+                log::error!("Failed to get user data from introspection: {}", err);
+                return Outcome::Failure(AuthenticationError::InvalidParams);
+            }
+        };
+
+        if let Some(expires) = expires {
+            let mut cache = self.cache();
+            cache.retain(|_, cached| cached.expires > now);
+            cache.insert(
+                key,
+                CachedUser {
+                    user: user.clone(),
+                    expires: expires.min(now + self.max_cache_duration),
+                },
+            );
+        }
+
+        Outcome::Success(user)
+    }
+
+    async fn introspect(&self, token: &str) -> Result<HashMap<String, Value>, IntrospectionError> {
+        let mut form = vec![("token", token), ("token_type_hint", "access_token")];
+        if let Some((client_id, client_secret)) = &self.client_credentials {
+            form.push(("client_id", client_id));
+            form.push(("client_secret", client_secret));
+        }
+
+        let response = self.http_client.post_form(&self.endpoint, &form).await?;
+        if !response.is_success() {
+            return Err(IntrospectionError::Status(response.status));
+        }
+
+        rocket::serde::json::serde_json::from_slice(&response.body)
+            .map_err(|_| IntrospectionError::InvalidResponse)
+    }
+
+    fn cache(&self) -> std::sync::MutexGuard<'_, HashMap<Vec<u8>, CachedUser>> {
+        self.cache.lock().expect("Introspection cache poisoned")
+    }
+}
+
+/// Create the user from an introspection response. The username is read from `sub`, or
+/// `username` if there is no subject, the roles from `scope`. All members become claims.
+fn read_user(response: HashMap<String, Value>) -> Result<User, IntrospectionError> {
+    let read_str = |name: &str| response.get(name).and_then(Value::as_str);
+
+    let username = read_str("sub")
+        .or_else(|| read_str("username"))
+        .filter(|username| !username.is_empty())
+        .ok_or(IntrospectionError::MissingSub)?
+        .to_owned();
+
+    let roles = read_str("scope")
+        .unwrap_or_default()
+        .split_whitespace()
+        .map(str::to_owned)
+        .collect::<HashSet<_>>();
+
+    let mut claims = Claims::new();
+    for (name, value) in response {
+        if let Some(value) = ClaimValue::from_json(value) {
+            claims.add(&name, value);
+        }
+    }
+
+    Ok(User {
+        username,
+        email: None,
+        email_confirmed: false,
+        claims,
+        roles: Roles::from_inner(roles),
+    })
+}
+
+#[rocket::async_trait]
+impl AuthenticationScheme for Introspection {
+    fn name(&self) -> String {
+        format!("Introspection({})", self.endpoint)
+    }
+
+    async fn authenticate(&self, req: &Request) -> Outcome {
+        for header in req.headers().get("Authorization") {
+            match self.authenticate_with_header(header).await {
+                Outcome::Success(user) => return Outcome::Success(user),
+                Outcome::Failure(err) => return Outcome::Failure(err),
+                Outcome::Forward(()) => {}
+            }
+        }
+
+        // No Authorization headers, we cannot handle the request
+        Outcome::Forward(())
+    }
+
+    async fn challenge(&self, res: &mut rocket::Response) {
+        res.adjoin_header(rocket::http::Header::new("WWW-Authenticate", "Bearer"));
+    }
+}
+
+impl core::fmt::Debug for Introspection {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Introspection")
+            .field("endpoint", &self.endpoint)
+            .field(
+                "client_id",
+                &self.client_credentials.as_ref().map(|(id, _)| id),
+            )
+            .field("http_client", &self.http_client)
+            .field("max_cache_duration", &self.max_cache_duration)
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum IntrospectionError {
+    #[error("the introspection endpoint responded with status {0}")]
+    Status(u16),
+
+    #[error("the introspection response is not a JSON object")]
+    InvalidResponse,
+
+    #[error("the introspection response has no sub")]
+    MissingSub,
+
+    #[error(transparent)]
+    HttpClient(#[from] HttpClientError),
+}
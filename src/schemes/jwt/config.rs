@@ -1,16 +1,361 @@
-use jsonwebtoken::{DecodingKey, EncodingKey};
+use std::sync::{Arc, RwLock, RwLockReadGuard, RwLockWriteGuard};
 
+use data_encoding::BASE64URL_NOPAD;
+use jsonwebtoken::{
+    jwk::{AlgorithmParameters, Jwk, JwkSet, PublicKeyUse},
+    Algorithm, DecodingKey, EncodingKey,
+};
+use rocket::{
+    serde::json::serde_json,
+    time::{Duration, OffsetDateTime},
+};
+use sha2::{Digest, Sha256};
+
+/// The keys tokens are signed and validated with. Clones share the same keys, so a
+/// clone kept outside of Rocket can be used to rotate them at runtime.
 #[derive(Clone)]
 pub struct JwtConfig {
-    pub encoding_key: EncodingKey,
-    pub deconding_key: DecodingKey,
+    keys: Arc<RwLock<KeyRing>>,
+
+    /// The issuer written into tokens and required when validating them.
+    pub issuer: Option<String>,
+
+    /// How long tokens created with
+    /// [`JwtTokenProvider::create_token`](super::JwtTokenProvider::create_token) are
+    /// valid.
+    pub token_lifetime: Duration,
+}
+
+#[derive(Debug)]
+struct KeyRing {
+    signing_key: JwtKey,
+    verification_keys: Vec<VerificationKey>,
+}
+
+#[derive(Debug)]
+struct VerificationKey {
+    key: JwtKey,
+
+    /// When the key stops being accepted, if ever.
+    until: Option<OffsetDateTime>,
+}
+
+/// A key identified by its key id, which is written into the header of the tokens
+/// it signs.
+#[derive(Clone)]
+pub struct JwtKey {
+    key_id: String,
+    algorithm: Algorithm,
+    encoding_key: Option<EncodingKey>,
+    decoding_key: DecodingKey,
+    public_key: Option<Jwk>,
+}
+
+impl JwtKey {
+    /// A key signing tokens with a shared secret (HS256). The secret is never
+    /// published, so only services knowing it can validate tokens.
+    pub fn hmac(key_id: impl Into<String>, secret: &[u8]) -> Self {
+        Self {
+            key_id: key_id.into(),
+            algorithm: Algorithm::HS256,
+            encoding_key: Some(EncodingKey::from_secret(secret)),
+            decoding_key: DecodingKey::from_secret(secret),
+            public_key: None,
+        }
+    }
+
+    /// A key signing tokens with a shared secret using HS256, HS384 or HS512.
+    pub fn hmac_with_algorithm(
+        key_id: impl Into<String>,
+        secret: &[u8],
+        algorithm: Algorithm,
+    ) -> Result<Self, JwtConfigError> {
+        if !matches!(
+            algorithm,
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512
+        ) {
+            return Err(JwtConfigError::UnsupportedAlgorithm(algorithm));
+        }
+
+        Ok(Self {
+            algorithm,
+            ..Self::hmac(key_id, secret)
+        })
+    }
+
+    /// A key signing tokens with a private key, publishing the matching public key.
+    /// The algorithm is taken from the public key, as is the key id if it has one.
+    /// Otherwise its JWK thumbprint (RFC 7638) is used.
+    pub fn asymmetric(encoding_key: EncodingKey, public_key: Jwk) -> Result<Self, JwtConfigError> {
+        Ok(Self {
+            encoding_key: Some(encoding_key),
+            ..Self::public(public_key)?
+        })
+    }
+
+    /// A key only validating tokens, e.g. the published key of a previous signing key.
+    pub fn public(public_key: Jwk) -> Result<Self, JwtConfigError> {
+        let Some(algorithm) = public_key.common.algorithm else {
+            return Err(JwtConfigError::MissingAlgorithm);
+        };
+
+        if let AlgorithmParameters::OctetKey(_) = public_key.algorithm {
+            return Err(JwtConfigError::SymmetricKey);
+        }
+
+        let Some(key_id) = public_key
+            .common
+            .key_id
+            .clone()
+            .or_else(|| thumbprint(&public_key))
+        else {
+            return Err(JwtConfigError::MissingKeyId);
+        };
+
+        let decoding_key = DecodingKey::from_jwk(&public_key)?;
+
+        let mut public_key = public_key;
+        public_key.common.key_id = Some(key_id.clone());
+        public_key
+            .common
+            .public_key_use
+            .get_or_insert(PublicKeyUse::Signature);
+
+        Ok(Self {
+            key_id,
+            algorithm,
+            encoding_key: None,
+            decoding_key,
+            public_key: Some(public_key),
+        })
+    }
+
+    /// A key id derived from a secret, which does not reveal the secret.
+    pub fn hmac_key_id(secret: &[u8]) -> String {
+        BASE64URL_NOPAD.encode(&Sha256::digest(secret)[..8])
+    }
+
+    pub fn key_id(&self) -> &str {
+        &self.key_id
+    }
+
+    pub fn algorithm(&self) -> Algorithm {
+        self.algorithm
+    }
+
+    pub(crate) fn encoding_key(&self) -> Option<&EncodingKey> {
+        self.encoding_key.as_ref()
+    }
+
+    pub(crate) fn decoding_key(&self) -> &DecodingKey {
+        &self.decoding_key
+    }
+}
+
+impl JwtConfig {
+    /// Sign tokens with the given key.
+    pub fn new(signing_key: JwtKey) -> Result<Self, JwtConfigError> {
+        if signing_key.encoding_key.is_none() {
+            return Err(JwtConfigError::NotASigningKey);
+        }
+
+        let keys = KeyRing {
+            signing_key,
+            verification_keys: Vec::new(),
+        };
+
+        Ok(Self {
+            keys: Arc::new(RwLock::new(keys)),
+            issuer: None,
+            token_lifetime: Duration::days(180),
+        })
+    }
+
+    /// Sign tokens with a shared secret (HS256). The key id is derived from the secret.
+    pub fn hmac(secret: &[u8]) -> Self {
+        Self::new(JwtKey::hmac(JwtKey::hmac_key_id(secret), secret)).expect("HMAC keys can sign")
+    }
+
+    /// Sign tokens with a private key and publish the matching public key.
+    pub fn asymmetric(encoding_key: EncodingKey, public_key: Jwk) -> Result<Self, JwtConfigError> {
+        Self::new(JwtKey::asymmetric(encoding_key, public_key)?)
+    }
+
+    pub fn with_issuer(mut self, issuer: impl Into<String>) -> Self {
+        self.issuer = Some(issuer.into());
+        self
+    }
+
+    /// Also accept tokens signed with the given key, e.g. a previous signing key,
+    /// until it is removed with [`remove_key`](Self::remove_key).
+    pub fn with_verification_key(self, key: JwtKey) -> Self {
+        self.keys_mut()
+            .verification_keys
+            .push(VerificationKey { key, until: None });
+        self
+    }
+
+    /// Start signing tokens with a new key. The previous signing key keeps validating
+    /// tokens for `keep_previous_for`, which should be at least the lifetime of the
+    /// tokens it signed.
+    pub fn rotate(
+        &self,
+        signing_key: JwtKey,
+        keep_previous_for: Duration,
+    ) -> Result<(), JwtConfigError> {
+        if signing_key.encoding_key.is_none() {
+            return Err(JwtConfigError::NotASigningKey);
+        }
+
+        let now = OffsetDateTime::now_utc();
+        let mut keys = self.keys_mut();
+
+        keys.verification_keys
+            .retain(|k| k.key.key_id != signing_key.key_id && k.is_valid(now));
+
+        let previous = std::mem::replace(&mut keys.signing_key, signing_key);
+        if previous.key_id != keys.signing_key.key_id {
+            keys.verification_keys.push(VerificationKey {
+                key: previous,
+                until: Some(now + keep_previous_for),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Stop accepting tokens signed with a previous key, e.g. because it was
+    /// compromised. The current signing key cannot be removed. Returns whether a key
+    /// was removed.
+    pub fn remove_key(&self, key_id: &str) -> bool {
+        let mut keys = self.keys_mut();
+        let count = keys.verification_keys.len();
+
+        keys.verification_keys.retain(|k| k.key.key_id != key_id);
+
+        keys.verification_keys.len() != count
+    }
+
+    /// The key tokens are currently signed with.
+    pub fn signing_key(&self) -> JwtKey {
+        self.keys().signing_key.clone()
+    }
+
+    /// The key to validate a token with the given key id. Tokens without a key id were
+    /// issued before key ids were written and are validated with the signing key.
+    pub fn verification_key(&self, key_id: Option<&str>) -> Option<JwtKey> {
+        let keys = self.keys();
+
+        let Some(key_id) = key_id else {
+            return Some(keys.signing_key.clone());
+        };
+
+        if keys.signing_key.key_id == key_id {
+            return Some(keys.signing_key.clone());
+        }
+
+        let now = OffsetDateTime::now_utc();
+        keys.verification_keys
+            .iter()
+            .find(|k| k.key.key_id == key_id && k.is_valid(now))
+            .map(|k| k.key.clone())
+    }
+
+    /// The JWKS document with the public keys of all keys tokens are accepted from.
+    pub fn jwks(&self) -> JwkSet {
+        let keys = self.keys();
+        let now = OffsetDateTime::now_utc();
+
+        let verification_keys = keys
+            .verification_keys
+            .iter()
+            .filter(|k| k.is_valid(now))
+            .map(|k| &k.key);
+
+        JwkSet {
+            keys: std::iter::once(&keys.signing_key)
+                .chain(verification_keys)
+                .filter_map(|k| k.public_key.clone())
+                .collect(),
+        }
+    }
+
+    fn keys(&self) -> RwLockReadGuard<'_, KeyRing> {
+        self.keys.read().expect("JWT keys poisoned")
+    }
+
+    fn keys_mut(&self) -> RwLockWriteGuard<'_, KeyRing> {
+        self.keys.write().expect("JWT keys poisoned")
+    }
+}
+
+impl VerificationKey {
+    fn is_valid(&self, now: OffsetDateTime) -> bool {
+        match self.until {
+            Some(until) => until > now,
+            None => true,
+        }
+    }
+}
+
+/// The JWK thumbprint (RFC 7638) of a public key.
+fn thumbprint(public_key: &Jwk) -> Option<String> {
+    let members: &[&str] = match public_key.algorithm {
+        AlgorithmParameters::EllipticCurve(_) => &["crv", "kty", "x", "y"],
+        AlgorithmParameters::RSA(_) => &["e", "kty", "n"],
+        AlgorithmParameters::OctetKeyPair(_) => &["crv", "kty", "x"],
+        AlgorithmParameters::OctetKey(_) => return None,
+    };
+
+    let params = serde_json::to_value(&public_key.algorithm).ok()?;
+    let members = members
+        .iter()
+        .map(|m| Some(format!("\"{}\":{}", m, params.get(m)?)))
+        .collect::<Option<Vec<_>>>()?;
+
+    let canonical = format!("{{{}}}", members.join(","));
+
+    Some(BASE64URL_NOPAD.encode(&Sha256::digest(canonical.as_bytes())))
+}
+
+impl core::fmt::Debug for JwtKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtKey")
+            .field("key_id", &self.key_id)
+            .field("algorithm", &self.algorithm)
+            .field("encoding_key", &"hidden")
+            .field("decoding_key", &"hidden")
+            .finish()
+    }
 }
 
 impl core::fmt::Debug for JwtConfig {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         f.debug_struct("JwtConfig")
-            .field("encoding_key", &"hidden")
-            .field("deconding_key", &"hidden")
+            .field("keys", &*self.keys())
+            .field("issuer", &self.issuer)
+            .field("token_lifetime", &self.token_lifetime)
             .finish()
     }
 }
+
+#[derive(Debug, thiserror::Error)]
+pub enum JwtConfigError {
+    #[error("the public key does not specify an algorithm")]
+    MissingAlgorithm,
+
+    #[error("symmetric keys cannot be published")]
+    SymmetricKey,
+
+    #[error("the algorithm {0:?} cannot be used with this key")]
+    UnsupportedAlgorithm(Algorithm),
+
+    #[error("the key has no key id")]
+    MissingKeyId,
+
+    #[error("the key can only validate tokens")]
+    NotASigningKey,
+
+    #[error("invalid public key: {0}")]
+    InvalidKey(#[from] jsonwebtoken::errors::Error),
+}
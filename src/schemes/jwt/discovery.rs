@@ -0,0 +1,119 @@
+use jsonwebtoken::jwk::JwkSet;
+use rocket::{
+    get,
+    request::{FromRequest, Outcome},
+    serde::{json::Json, Serialize},
+    Request, Route, State,
+};
+
+use crate::oauth::{AuthorizationServer, GrantType};
+
+use super::JwtConfig;
+
+/// The path of the JWKS document served by [`discovery_routes`].
+pub const JWKS_PATH: &str = "/.well-known/jwks.json";
+
+/// Routes allowing other services to validate the issued tokens, to be mounted at `/`:
+///
+/// - `GET /.well-known/openid-configuration` serves the discovery document. It
+///   requires [`JwtConfig::issuer`] to be set, which has to be the base URL the
+///   application is reachable at. If an [`AuthorizationServer`] is configured, its
+///   endpoints are advertised as well.
+/// - `GET /.well-known/jwks.json` serves the public keys of the [`JwtConfig`].
+pub fn discovery_routes() -> Vec<Route> {
+    rocket::routes![openid_configuration, jwks]
+}
+
+/// The provider metadata of OpenID Connect Discovery 1.0 (section 3).
+#[derive(Debug, Serialize)]
+#[serde(crate = "rocket::serde")]
+struct ProviderMetadata {
+    issuer: String,
+    jwks_uri: String,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    authorization_endpoint: Option<String>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    token_endpoint: Option<String>,
+
+    response_types_supported: Vec<&'static str>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    grant_types_supported: Vec<&'static str>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    code_challenge_methods_supported: Vec<&'static str>,
+
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    token_endpoint_auth_methods_supported: Vec<&'static str>,
+
+    subject_types_supported: Vec<&'static str>,
+    id_token_signing_alg_values_supported: Vec<String>,
+}
+
+#[get("/.well-known/openid-configuration")]
+fn openid_configuration(
+    config: &State<JwtConfig>,
+    server: ConfiguredServer<'_>,
+) -> Option<Json<ProviderMetadata>> {
+    let Some(issuer) = &config.issuer else {
+        log::warn!("Cannot serve the discovery document without an issuer");
+        return None;
+    };
+
+    // The issuer has to match the one in tokens exactly, only the endpoints are joined
+    let base_url = issuer.trim_end_matches('/');
+    let endpoint = |path: &str| format!("{}{}", base_url, path);
+
+    let mut metadata = ProviderMetadata {
+        issuer: issuer.clone(),
+        jwks_uri: endpoint(JWKS_PATH),
+        authorization_endpoint: None,
+        token_endpoint: None,
+        response_types_supported: Vec::new(),
+        grant_types_supported: Vec::new(),
+        code_challenge_methods_supported: Vec::new(),
+        token_endpoint_auth_methods_supported: Vec::new(),
+        subject_types_supported: vec!["public"],
+        id_token_signing_alg_values_supported: vec![format!(
+            "{:?}",
+            config.signing_key().algorithm()
+        )],
+    };
+
+    if let Some(server) = server.0 {
+        let base_path = server.base_path.trim_end_matches('/');
+
+        if server.grant_types.contains(&GrantType::AuthorizationCode) {
+            metadata.authorization_endpoint = Some(endpoint(&format!("{}/authorize", base_path)));
+            metadata.response_types_supported = vec!["code"];
+        }
+
+        metadata.token_endpoint = Some(endpoint(&format!("{}/token", base_path)));
+        metadata.grant_types_supported = server.grant_types.iter().map(GrantType::as_str).collect();
+        metadata.code_challenge_methods_supported = vec!["S256"];
+        metadata.token_endpoint_auth_methods_supported =
+            vec!["client_secret_basic", "client_secret_post", "none"];
+    }
+
+    Some(Json(metadata))
+}
+
+/// The authorization server if one is configured. Unlike `Option<&State<_>>`, this
+/// does not prevent launching without one.
+struct ConfiguredServer<'r>(Option<&'r AuthorizationServer>);
+
+#[rocket::async_trait]
+impl<'r> FromRequest<'r> for ConfiguredServer<'r> {
+    type Error = ();
+
+    async fn from_request(req: &'r Request<'_>) -> Outcome<Self, Self::Error> {
+        Outcome::Success(ConfiguredServer(req.rocket().state()))
+    }
+}
+
+#[get("/.well-known/jwks.json")]
+fn jwks(config: &State<JwtConfig>) -> Json<JwkSet> {
+    Json(config.jwks())
+}
@@ -1,9 +1,11 @@
 mod claims;
 mod config;
+mod discovery;
 mod scheme;
 mod token;
 
 pub use claims::*;
 pub use config::*;
+pub use discovery::*;
 pub use scheme::*;
 pub use token::*;
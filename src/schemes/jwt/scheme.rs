@@ -1,6 +1,6 @@
 use std::collections::{HashMap, HashSet};
 
-use jsonwebtoken::{decode, Algorithm, TokenData, Validation};
+use jsonwebtoken::{decode, decode_header, TokenData, Validation};
 use rocket::{serde::json::Value, Request};
 
 use crate::schemes::impls::prelude::*;
@@ -25,6 +25,8 @@ impl TryFrom<&ParsedToken> for User {
 
         Ok(User {
             username,
+            email: None,
+            email_confirmed: false,
             claims: Claims::new(),
             roles,
         })
@@ -84,15 +86,31 @@ impl JwtBearer {
 
         let token = token.trim();
 
-        let key = &req
+        let config = req
             .rocket()
             .state::<JwtConfig>()
-            .expect("Missing JwtConfig")
-            .deconding_key;
+            .expect("Missing JwtConfig");
 
-        let validation = Validation::new(Algorithm::HS256);
+        let key_id = match decode_header(token) {
+            Ok(header) => header.kid,
+            Err(err) => {
+                log::error!("Failed to decode token: {}", err);
+                return Outcome::Failure(AuthenticationError::InvalidParams);
+            }
+        };
+
+        // Tokens signed with a retired key are accepted until the key is removed
+        let Some(key) = config.verification_key(key_id.as_deref()) else {
+            log::error!("Failed to decode token: unknown key {:?}", key_id);
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let mut validation = Validation::new(key.algorithm());
+        if let Some(issuer) = &config.issuer {
+            validation.set_issuer(&[issuer]);
+        }
 
-        let token = match decode::<HashMap<String, Value>>(token, key, &validation) {
+        let token = match decode::<HashMap<String, Value>>(token, key.decoding_key(), &validation) {
             Ok(token) => token,
             Err(err) => {
                 log::error!("Failed to decode token: {}", err);
@@ -1,9 +1,9 @@
 use std::collections::HashMap;
 
-use jsonwebtoken::{encode, EncodingKey, Header};
+use jsonwebtoken::{encode, Header};
 use rocket::{
     request::{FromRequest, Outcome},
-    serde::{Deserialize, Serialize},
+    serde::{json::Value, Deserialize, Serialize},
     time::{Duration, OffsetDateTime},
     Request,
 };
@@ -16,26 +16,55 @@ use super::{Claims, JwtConfig};
 #[serde(crate = "rocket::serde")]
 pub struct JwtToken(String);
 
+impl JwtToken {
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+}
+
 pub struct JwtTokenProvider<'r> {
-    key: &'r EncodingKey,
+    config: &'r JwtConfig,
 }
 
 impl<'r> JwtTokenProvider<'r> {
     pub fn create_token(&self, user: &User) -> Result<JwtToken, JwtTokenError> {
+        self.create_token_with_claims(user, self.config.token_lifetime, HashMap::new())
+    }
+
+    /// Create a token for the user that expires after `lifetime`, with additional claims.
+    pub fn create_token_with_claims(
+        &self,
+        user: &User,
+        lifetime: Duration,
+        claims: HashMap<String, Value>,
+    ) -> Result<JwtToken, JwtTokenError> {
         let now = OffsetDateTime::now_utc();
 
-        let mut other = HashMap::new();
+        let mut other = claims;
         other.insert("roles".to_string(), user.roles.iter().collect());
 
+        if let Some(issuer) = &self.config.issuer {
+            other.insert("iss".to_string(), Value::from(issuer.clone()));
+        }
+
         let claims = Claims {
             sub: user.username.clone(),
             nbf: now.into(),
             iat: now.into(),
-            exp: (now + Duration::days(180)).into(),
+            exp: (now + lifetime).into(),
             other,
         };
 
-        let token = encode(&Header::default(), &claims, self.key)?;
+        let key = self.config.signing_key();
+        let header = Header {
+            kid: Some(key.key_id().to_owned()),
+            ..Header::new(key.algorithm())
+        };
+
+        let encoding_key = key
+            .encoding_key()
+            .expect("Signing keys have an encoding key");
+        let token = encode(&header, &claims, encoding_key)?;
 
         Ok(JwtToken(token))
     }
@@ -51,9 +80,7 @@ impl<'r> FromRequest<'r> for JwtTokenProvider<'r> {
             .state::<JwtConfig>()
             .expect("Missing JwtConfig");
 
-        Outcome::Success(JwtTokenProvider {
-            key: &config.encoding_key,
-        })
+        Outcome::Success(JwtTokenProvider { config })
     }
 }
 
@@ -1,9 +1,18 @@
 mod scheme;
+mod trusted_proxies;
 
+pub mod api_key;
 pub mod basic;
+#[cfg(feature = "mtls")]
+pub mod client_certificate;
 pub mod cookie;
+pub mod digest;
+pub mod introspection;
 pub mod jwt;
+pub mod proxy_header;
+pub mod signature;
 
 pub mod impls;
 
 pub use scheme::*;
+pub use trusted_proxies::*;
@@ -0,0 +1,203 @@
+use std::collections::HashMap;
+
+use rocket::Request;
+
+use crate::{
+    schemes::{impls::prelude::*, TrustedProxies},
+    AddUserError,
+};
+
+/// Authenticates users signed in by an authenticating gateway in front of the
+/// application, e.g. oauth2-proxy or Pomerium, which forwards the username and groups
+/// in headers. The headers are only read from requests of [`TrustedProxies`], since
+/// clients could set them to anything.
+///
+/// By default the username is read from `X-Forwarded-User` and the comma separated
+/// groups from `X-Forwarded-Groups`, and every group becomes a role.
+#[derive(Debug)]
+pub struct ProxyHeader {
+    trusted_proxies: TrustedProxies,
+    user_header: String,
+    groups_header: String,
+    group_roles: HashMap<String, Vec<String>>,
+    users: ProxyUsers,
+}
+
+/// Whether users forwarded by the gateway have to exist in the user store.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ProxyUsers {
+    /// Users are created from the headers and not stored.
+    Transient,
+
+    /// Only existing users are accepted.
+    Existing,
+
+    /// Users that do not exist yet are added to the user store.
+    Provisioned,
+}
+
+impl ProxyHeader {
+    pub fn default_user_header() -> &'static str {
+        "X-Forwarded-User"
+    }
+
+    pub fn default_groups_header() -> &'static str {
+        "X-Forwarded-Groups"
+    }
+
+    /// Trust the headers of requests from the given proxies.
+    pub fn new(trusted_proxies: TrustedProxies) -> Self {
+        Self {
+            trusted_proxies,
+            user_header: Self::default_user_header().to_owned(),
+            groups_header: Self::default_groups_header().to_owned(),
+            group_roles: HashMap::new(),
+            users: ProxyUsers::Transient,
+        }
+    }
+
+    /// Read the username from the given header.
+    pub fn with_user_header(mut self, header: impl Into<String>) -> Self {
+        self.user_header = header.into();
+        self
+    }
+
+    /// Read the groups from the given header.
+    pub fn with_groups_header(mut self, header: impl Into<String>) -> Self {
+        self.groups_header = header.into();
+        self
+    }
+
+    /// Grant members of the group the given role. Once groups are mapped, groups
+    /// without a mapping do not grant any roles.
+    pub fn map_group(mut self, group: impl Into<String>, role: impl Into<String>) -> Self {
+        self.group_roles
+            .entry(group.into())
+            .or_default()
+            .push(role.into());
+        self
+    }
+
+    /// Only accept users that exist in the user store. They are signed in with their
+    /// stored roles and claims in addition to the roles of their groups.
+    pub fn existing_users(mut self) -> Self {
+        self.users = ProxyUsers::Existing;
+        self
+    }
+
+    /// Add users that do not exist yet to the user store, without a password, when
+    /// they first sign in through the gateway.
+    pub fn auto_provision(mut self) -> Self {
+        self.users = ProxyUsers::Provisioned;
+        self
+    }
+
+    fn group_roles<'a>(&'a self, groups: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        groups
+            .split(',')
+            .map(str::trim)
+            .filter(|group| !group.is_empty())
+            .flat_map(move |group| {
+                if self.group_roles.is_empty() {
+                    return vec![group];
+                }
+
+                self.group_roles
+                    .get(group)
+                    .map(|roles| roles.iter().map(String::as_str).collect())
+                    .unwrap_or_default()
+            })
+    }
+
+    async fn find_user(&self, username: &str, req: &Request<'_>) -> Result<Option<User>, Outcome> {
+        if self.users == ProxyUsers::Transient {
+            return Ok(Some(User::with_username(username)));
+        }
+
+        let users = req.user_repository().await;
+        let find = || async {
+            users.find_by_username(username).await.map_err(|e| {
+                log::error!("Failed to find forwarded user: {}", e);
+                Outcome::Failure(AuthenticationError::Other)
+            })
+        };
+
+        if let Some(user) = find().await? {
+            return Ok(Some(user));
+        }
+
+        if self.users == ProxyUsers::Existing {
+            return Ok(None);
+        }
+
+        let added = match users.add_user(&User::with_username(username), None).await {
+            // Another request may have added the user in the meantime
+            Ok(()) | Err(AddUserError::UsernameExists) => Ok(()),
+            Err(AddUserError::InvalidUsername(violations)) => {
+                log::warn!(
+                    "Forwarded username {} violates the username policy: {:?}",
+                    username,
+                    violations
+                );
+                Err(Outcome::Failure(AuthenticationError::InvalidParams))
+            }
+            Err(e) => {
+                log::error!("Failed to provision forwarded user: {}", e);
+                Err(Outcome::Failure(AuthenticationError::Other))
+            }
+        };
+
+        added?;
+        find().await
+    }
+}
+
+#[rocket::async_trait]
+impl AuthenticationScheme for ProxyHeader {
+    fn name(&self) -> String {
+        format!("ProxyHeader(header={})", self.user_header)
+    }
+
+    async fn authenticate(&self, req: &rocket::Request) -> Outcome {
+        // No forwarded user, we cannot handle the request
+        let Some(username) = req.headers().get_one(&self.user_header) else {
+            return Outcome::Forward(());
+        };
+
+        if !self.trusted_proxies.is_trusted(req) {
+            log::warn!(
+                "Ignoring {} header of untrusted client {:?}",
+                self.user_header,
+                req.remote()
+            );
+            return Outcome::Forward(());
+        }
+
+        let username = username.trim();
+        if username.is_empty() {
+            log::error!("The {} header is empty", self.user_header);
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        }
+
+        let mut user = match self.find_user(username, req).await {
+            Ok(Some(user)) => user,
+            Ok(None) => {
+                log::warn!("Forwarded user {} does not exist", username);
+                return Outcome::Failure(AuthenticationError::Unauthenticated);
+            }
+            Err(outcome) => return outcome,
+        };
+
+        for groups in req.headers().get(&self.groups_header) {
+            for role in self.group_roles(groups) {
+                user.roles.add(role);
+            }
+        }
+
+        Outcome::Success(user)
+    }
+
+    async fn challenge(&self, _res: &mut rocket::Response) {
+        // Users sign in at the gateway
+    }
+}
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use rocket::{http::Status, Request};
 use yansi::Paint;
 
@@ -5,7 +7,7 @@ use crate::{config::MissingAuthPolicy, User};
 
 /// Encodes information about a way to authenticate a User.
 #[rocket::async_trait]
-pub trait AuthenticationScheme: Send + Sync + core::fmt::Debug + 'static {
+pub trait AuthenticationScheme: Send + Sync + core::fmt::Debug + private::AsAny {
     /// The name of this authentication scheme.
     fn name(&self) -> String;
 
@@ -23,6 +25,36 @@ pub trait AuthenticationScheme: Send + Sync + core::fmt::Debug + 'static {
     /// Add challenge information for the client to the response.
     /// Usually by adding a WWW-Authenticate header for this authentication scheme.
     async fn challenge(&self, res: &mut rocket::Response);
+
+    /// Add challenge information for the client to the response of a request. Defaults to
+    /// [`challenge`](Self::challenge), schemes whose challenge depends on the failed
+    /// authentication attempt override it.
+    async fn challenge_request(&self, _req: &rocket::Request<'_>, res: &mut rocket::Response<'_>) {
+        self.challenge(res).await
+    }
+}
+
+mod private {
+    use std::any::Any;
+
+    /// Gives access to the concrete type of an authentication scheme. Implemented for
+    /// every type, so schemes cannot pretend to be of another type.
+    pub trait AsAny: Any {
+        fn as_any(&self) -> &dyn Any;
+    }
+
+    impl<T: Any> AsAny for T {
+        fn as_any(&self) -> &dyn Any {
+            self
+        }
+    }
+}
+
+impl dyn AuthenticationScheme {
+    /// Whether this authentication scheme is of type `T`.
+    pub fn is<T: AuthenticationScheme>(&self) -> bool {
+        self.as_any().is::<T>()
+    }
 }
 
 /// The outcome of an authentication attempt. Success means that the attempt was
@@ -43,6 +75,7 @@ impl<T> FromAuthError for rocket::request::Outcome<T, AuthenticationError> {
         let status = match err {
             AuthenticationError::Unauthenticated => Status::Unauthorized,
             AuthenticationError::InvalidParams => Status::BadRequest,
+            AuthenticationError::TooManyRequests { .. } => Status::TooManyRequests,
             AuthenticationError::Other => Status::InternalServerError,
         };
 
@@ -68,6 +101,9 @@ pub enum AuthenticationError {
     #[error("The supplied authentication parameters are not valid")]
     InvalidParams,
 
+    #[error("Too many authentication attempts, retry after {retry_after:?}")]
+    TooManyRequests { retry_after: Duration },
+
     #[error("Some other error happened")]
     Other,
 }
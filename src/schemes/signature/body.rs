@@ -0,0 +1,150 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use rocket::{
+    data::{self, FromData, Limits},
+    fairing::{Fairing, Info, Kind},
+    http::Status,
+    serde::DeserializeOwned,
+    Data, Request,
+};
+use sha2::{Digest, Sha256};
+use subtle::ConstantTimeEq;
+
+use crate::User;
+
+/// Whether the request has a body. Set by [`BodyProbe`] for every request.
+pub(crate) struct HasBody(pub bool);
+
+/// Checks whether requests have a body, so that [`HmacSignature`](super::HmacSignature)
+/// can leave signed requests with a body to [`SignedBody`].
+pub(crate) struct BodyProbe;
+
+#[rocket::async_trait]
+impl Fairing for BodyProbe {
+    fn info(&self) -> Info {
+        Info {
+            name: "Signed body probe",
+            kind: Kind::Request,
+        }
+    }
+
+    async fn on_request(&self, req: &mut Request<'_>, data: &mut Data<'_>) {
+        let has_body = !data.peek(1).await.is_empty();
+        req.local_cache(|| HasBody(has_body));
+    }
+}
+
+/// The user and the signed `Content-Digest` header of a request with a body, set by
+/// [`HmacSignature`](super::HmacSignature) once the signature is verified.
+#[derive(Debug)]
+pub(crate) struct PendingSignedBody(pub Option<(User, String)>);
+
+/// The body of a request signed for [`HmacSignature`](super::HmacSignature). The
+/// signature covers the `Content-Digest` header, and this data guard checks that the
+/// body matches it, so the body cannot be swapped while the signature stays valid.
+///
+/// Signed requests with a body are only authenticated through this data guard, the
+/// [`User`] request guard fails for them, so routes cannot accept an unverified body.
+///
+/// Bodies are limited by the `bytes` limit.
+#[derive(Debug, Clone)]
+pub struct SignedBody {
+    user: User,
+    body: Vec<u8>,
+}
+
+impl SignedBody {
+    /// The user the request was signed for.
+    pub fn user(&self) -> &User {
+        &self.user
+    }
+
+    pub fn bytes(&self) -> &[u8] {
+        &self.body
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.body
+    }
+
+    /// Deserialize the body as JSON.
+    pub fn json<T: DeserializeOwned>(&self) -> Result<T, rocket::serde::json::serde_json::Error> {
+        rocket::serde::json::serde_json::from_slice(&self.body)
+    }
+}
+
+#[rocket::async_trait]
+impl<'r> FromData<'r> for SignedBody {
+    type Error = SignedBodyError;
+
+    async fn from_data(req: &'r Request<'_>, data: Data<'r>) -> data::Outcome<'r, Self> {
+        // The signature scheme leaves the user and content digest of signed requests with
+        // a body here instead of authenticating them
+        let status = match req.guard::<&User>().await {
+            rocket::outcome::Outcome::Failure((status, _)) => status,
+            _ => Status::Unauthorized,
+        };
+
+        let PendingSignedBody(Some((user, content_digest))) =
+            req.local_cache(|| PendingSignedBody(None))
+        else {
+            return data::Outcome::Failure((status, SignedBodyError::NotSigned));
+        };
+
+        let Some(expected) = sha256_digest(content_digest) else {
+            return data::Outcome::Failure((
+                Status::BadRequest,
+                SignedBodyError::UnsupportedDigest,
+            ));
+        };
+
+        let limit = req.limits().get("bytes").unwrap_or(Limits::BYTES);
+        let body = match data.open(limit).into_bytes().await {
+            Ok(body) if body.is_complete() => body.into_inner(),
+            Ok(_) => {
+                return data::Outcome::Failure((Status::PayloadTooLarge, SignedBodyError::TooLarge))
+            }
+            Err(e) => return data::Outcome::Failure((Status::BadRequest, SignedBodyError::Io(e))),
+        };
+
+        if !bool::from(Sha256::digest(&body).as_slice().ct_eq(&expected)) {
+            log::warn!("Body does not match the signed content digest");
+            return data::Outcome::Failure((Status::BadRequest, SignedBodyError::DigestMismatch));
+        }
+
+        data::Outcome::Success(SignedBody {
+            user: user.clone(),
+            body,
+        })
+    }
+}
+
+/// The SHA-256 digest of a `Content-Digest` header, e.g. `sha-256=:<base64>:`.
+fn sha256_digest(content_digest: &str) -> Option<Vec<u8>> {
+    content_digest.split(',').find_map(|member| {
+        let (algorithm, value) = member.trim().split_once('=')?;
+        if !algorithm.eq_ignore_ascii_case("sha-256") {
+            return None;
+        }
+
+        let value = value.strip_prefix(':')?.strip_suffix(':')?;
+        BASE64.decode(value).ok()
+    })
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SignedBodyError {
+    #[error("the request is not signed or the signature does not cover a content digest")]
+    NotSigned,
+
+    #[error("the content digest does not contain a SHA-256 digest")]
+    UnsupportedDigest,
+
+    #[error("the body does not match the content digest")]
+    DigestMismatch,
+
+    #[error("the body is too large")]
+    TooLarge,
+
+    #[error("failed to read the body")]
+    Io(#[from] std::io::Error),
+}
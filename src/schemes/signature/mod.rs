@@ -0,0 +1,5 @@
+mod body;
+mod scheme;
+
+pub use body::*;
+pub use scheme::*;
@@ -0,0 +1,280 @@
+use std::{collections::HashMap, sync::Mutex};
+
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use rocket::{
+    time::{Duration, OffsetDateTime},
+    Request,
+};
+use sha2::Sha256;
+
+use crate::{schemes::impls::prelude::*, util::parse_auth_params, SigningKeyError};
+
+use super::{BodyProbe, HasBody, PendingSignedBody};
+
+const SCHEME: &str = "HMAC-SHA256";
+
+/// Authenticates requests signed with keys issued by
+/// [`UserRepository::create_signing_key`](crate::UserRepository::create_signing_key),
+/// e.g. webhooks and calls from partner services.
+///
+/// Clients sign requests with HMAC-SHA256 using the secret of their key and send
+///
+/// ```text
+/// Authorization: HMAC-SHA256 keyId="<id>", timestamp="<unix time>", nonce="<random>",
+///     headers="content-type content-digest", signature="<base64 signature>"
+/// ```
+///
+/// The signature is computed over the following lines, joined by `\n`:
+///
+/// ```text
+/// <method>
+/// <path and query>
+/// <timestamp>
+/// <nonce>
+/// <header name>:<header value>     (one line per signed header, in the given order)
+/// ```
+///
+/// Header names are lowercase and multiple values of a header are joined by `, `. A
+/// `Content-Digest` header (RFC 9530) has to be signed when present and for every request
+/// with a body. Requests with a body are only authenticated by the
+/// [`SignedBody`](super::SignedBody) data guard, which verifies the body against the
+/// digest.
+///
+/// Signatures are only accepted within the maximum clock skew of their timestamp, and
+/// each nonce only once within that window.
+pub struct HmacSignature {
+    required_headers: Vec<String>,
+    max_skew: Duration,
+
+    /// Nonces seen for each key, until their signatures expire.
+    nonces: Mutex<HashMap<(String, String), OffsetDateTime>>,
+}
+
+impl HmacSignature {
+    pub fn new() -> Self {
+        Self {
+            required_headers: Vec::new(),
+            max_skew: Duration::minutes(5),
+            nonces: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Headers that every signature has to cover, e.g. `host` or `x-request-id`.
+    pub fn with_required_headers(mut self, headers: &[&str]) -> Self {
+        self.required_headers = headers.iter().map(|h| h.to_ascii_lowercase()).collect();
+        self
+    }
+
+    /// How far the timestamp of a signature may be from the current time.
+    pub fn with_max_skew(mut self, max_skew: Duration) -> Self {
+        self.max_skew = max_skew;
+        self
+    }
+
+    async fn authenticate_with_header(&self, header: &str, req: &Request<'_>) -> Outcome {
+        // We expect an HMAC-SHA256 scheme
+        let Some(params) = header
+            .strip_prefix(SCHEME)
+            .and_then(|p| p.strip_prefix(' '))
+        else {
+            return Outcome::Forward(());
+        };
+
+        let Some(params) = parse_auth_params(params) else {
+            log::error!("Failed to parse request signature");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let param = |name: &str| params.get(name).map(String::as_str);
+
+        let (Some(key_id), Some(timestamp), Some(nonce), Some(signature)) = (
+            param("keyid"),
+            param("timestamp"),
+            param("nonce"),
+            param("signature"),
+        ) else {
+            log::error!("Request signature is missing parameters");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let (Ok(timestamp), Ok(signature)) = (timestamp.parse::<i64>(), BASE64.decode(signature))
+        else {
+            log::error!("Request signature has an invalid timestamp or signature");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let headers = param("headers")
+            .unwrap_or_default()
+            .split_whitespace()
+            .map(str::to_ascii_lowercase)
+            .collect::<Vec<_>>();
+
+        // Without a signed digest the body could be swapped while the signature stays valid
+        let &HasBody(has_body) = req.local_cache(|| HasBody(true));
+        let signs_content_digest = headers.iter().any(|h| h == "content-digest");
+        let content_digest = req.headers().get_one("Content-Digest");
+        if self.required_headers.iter().any(|h| !headers.contains(h))
+            || ((has_body || content_digest.is_some()) && !signs_content_digest)
+        {
+            log::error!("Request signature does not cover the required headers");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        }
+
+        let Some(signing_string) = signing_string(req, timestamp, nonce, &headers) else {
+            log::error!("Request is missing signed headers");
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        let now = OffsetDateTime::now_utc();
+        let Ok(signed) = OffsetDateTime::from_unix_timestamp(timestamp) else {
+            return Outcome::Failure(AuthenticationError::InvalidParams);
+        };
+
+        if (now - signed).abs() > self.max_skew {
+            log::warn!(
+                "Request signature of key {} is outside the time window",
+                key_id
+            );
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        let users = req.user_repository().await;
+        let (mut user, signing_key) = match users.find_signing_key(key_id).await {
+            Ok(Some(found)) => found,
+            Ok(None) | Err(SigningKeyError::UnknownKey) => {
+                return Outcome::Failure(AuthenticationError::Unauthenticated)
+            }
+            Err(e) => {
+                log::error!("Failed to find signing key: {}", e);
+                return Outcome::Failure(AuthenticationError::Other);
+            }
+        };
+
+        let mut mac = Hmac::<Sha256>::new_from_slice(signing_key.secret.as_bytes())
+            .expect("HMAC accepts any key length");
+        mac.update(signing_string.as_bytes());
+        if mac.verify_slice(&signature).is_err() {
+            log::warn!("Invalid request signature for key {}", key_id);
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        // Signed requests cannot be replayed, since the nonce is remembered until the
+        // signature expires
+        if !self.record_nonce(key_id, nonce, signed + self.max_skew, now) {
+            log::warn!("Replayed request signature for key {}", key_id);
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        user.roles = Roles::from(signing_key.scopes);
+        user.claims
+            .add("signing_key", ClaimValue::String(signing_key.id));
+
+        // The body still has to match the digest, which only SignedBody can check
+        if has_body {
+            log::info!("Signed request with a body is left to the SignedBody data guard");
+            req.local_cache(|| {
+                PendingSignedBody(content_digest.map(|digest| (user, digest.to_owned())))
+            });
+            return Outcome::Failure(AuthenticationError::Unauthenticated);
+        }
+
+        Outcome::Success(user)
+    }
+
+    /// Remember a nonce of a key. Returns false if it was already used.
+    fn record_nonce(
+        &self,
+        key_id: &str,
+        nonce: &str,
+        expires: OffsetDateTime,
+        now: OffsetDateTime,
+    ) -> bool {
+        let mut nonces = self.nonces.lock().expect("Nonces poisoned");
+        nonces.retain(|_, expires| *expires > now);
+
+        nonces
+            .insert((key_id.to_owned(), nonce.to_owned()), expires)
+            .is_none()
+    }
+}
+
+/// The string a request signature is computed over, or None if a signed header is
+/// missing from the request.
+fn signing_string(
+    req: &Request<'_>,
+    timestamp: i64,
+    nonce: &str,
+    headers: &[String],
+) -> Option<String> {
+    let mut lines = vec![
+        req.method().as_str().to_owned(),
+        req.uri().to_string(),
+        timestamp.to_string(),
+        nonce.to_owned(),
+    ];
+
+    for name in headers {
+        let values = req.headers().get(name).collect::<Vec<_>>();
+        if values.is_empty() {
+            return None;
+        }
+
+        lines.push(format!("{}:{}", name, values.join(", ")));
+    }
+
+    Some(lines.join("\n"))
+}
+
+impl Default for HmacSignature {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[rocket::async_trait]
+impl AuthenticationScheme for HmacSignature {
+    fn name(&self) -> String {
+        SCHEME.to_owned()
+    }
+
+    fn setup(&mut self, rocket: rocket::Rocket<rocket::Build>) -> rocket::Rocket<rocket::Build> {
+        rocket.attach(BodyProbe)
+    }
+
+    async fn authenticate(&self, req: &rocket::Request) -> Outcome {
+        for header in req.headers().get("Authorization") {
+            match self.authenticate_with_header(header, req).await {
+                Outcome::Success(user) => return Outcome::Success(user),
+                Outcome::Failure(err) => return Outcome::Failure(err),
+                Outcome::Forward(()) => {}
+            }
+        }
+
+        // No Authorization headers, we cannot handle the request
+        Outcome::Forward(())
+    }
+
+    async fn challenge(&self, res: &mut rocket::Response) {
+        let challenge = if self.required_headers.is_empty() {
+            SCHEME.to_owned()
+        } else {
+            format!(
+                r#"{} headers="{}""#,
+                SCHEME,
+                self.required_headers.join(" ")
+            )
+        };
+
+        res.adjoin_header(rocket::http::Header::new("WWW-Authenticate", challenge));
+    }
+}
+
+impl core::fmt::Debug for HmacSignature {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("HmacSignature")
+            .field("required_headers", &self.required_headers)
+            .field("max_skew", &self.max_skew)
+            .finish()
+    }
+}
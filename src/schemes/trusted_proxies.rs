@@ -0,0 +1,90 @@
+use std::net::IpAddr;
+
+/// The addresses of reverse proxies whose headers are trusted, as single addresses or
+/// CIDR ranges, e.g. `10.0.0.0/8` or `::1`. Requests from other addresses cannot set
+/// these headers, since clients could set them to anything.
+#[derive(Debug, Clone, Default)]
+pub struct TrustedProxies {
+    ranges: Vec<(IpAddr, u8)>,
+}
+
+impl TrustedProxies {
+    /// Trust the given addresses or ranges.
+    pub fn new<S: AsRef<str>>(ranges: &[S]) -> Result<Self, InvalidIpRange> {
+        let ranges = ranges
+            .iter()
+            .map(|range| parse_range(range.as_ref()))
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self { ranges })
+    }
+
+    /// Whether a request from the given address comes from a trusted proxy.
+    pub fn contains(&self, ip: IpAddr) -> bool {
+        let ip = canonical(ip);
+
+        self.ranges
+            .iter()
+            .any(|(network, prefix)| match (network, ip) {
+                (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                    masked(u32::from(*network).into(), *prefix, 32)
+                        == masked(u32::from(ip).into(), *prefix, 32)
+                }
+                (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                    masked(u128::from(*network), *prefix, 128)
+                        == masked(u128::from(ip), *prefix, 128)
+                }
+                _ => false,
+            })
+    }
+
+    /// Whether the request was sent by a trusted proxy, judging by the address of the
+    /// connection rather than any forwarding headers.
+    pub fn is_trusted(&self, req: &rocket::Request<'_>) -> bool {
+        req.remote()
+            .is_some_and(|remote| self.contains(remote.ip()))
+    }
+}
+
+fn parse_range(range: &str) -> Result<(IpAddr, u8), InvalidIpRange> {
+    let invalid = || InvalidIpRange(range.to_owned());
+
+    let (ip, prefix) = match range.split_once('/') {
+        Some((ip, prefix)) => (ip, Some(prefix)),
+        None => (range, None),
+    };
+
+    let ip = canonical(ip.trim().parse().map_err(|_| invalid())?);
+    let max = if ip.is_ipv4() { 32 } else { 128 };
+
+    let prefix = match prefix {
+        Some(prefix) => prefix.trim().parse().map_err(|_| invalid())?,
+        None => max,
+    };
+
+    if prefix > max {
+        return Err(invalid());
+    }
+
+    Ok((ip, prefix))
+}
+
+/// IPv4 addresses mapped to IPv6, as reported by dual stack sockets, are compared as
+/// IPv4 addresses.
+fn canonical(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V6(v6) => v6.to_ipv4_mapped().map_or(ip, IpAddr::V4),
+        IpAddr::V4(_) => ip,
+    }
+}
+
+fn masked(bits: u128, prefix: u8, len: u8) -> u128 {
+    match prefix {
+        0 => 0,
+        prefix => bits >> (len - prefix),
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid IP address or range {0}")]
+pub struct InvalidIpRange(String);
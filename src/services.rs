@@ -3,8 +3,18 @@ use std::sync::Arc;
 use rocket::{Orbit, Request, Rocket};
 
 use crate::{
-    config::MissingAuthPolicy, hashers::PasswordHasher, schemes::AuthenticationSchemes,
-    stores::UserStore, UserRepository,
+    config::{LockoutOptions, MissingAuthPolicy, SignInOptions},
+    external::ExternalProviders,
+    hashers::PasswordHasher,
+    notifiers::Notifier,
+    rate_limit::{RateLimitOptions, RateLimitStore},
+    schemes::AuthenticationSchemes,
+    stores::UserStore,
+    tokens::TokenProvider,
+    two_factor::Totp,
+    validators::{PasswordValidator, UsernamePolicy},
+    webauthn::WebAuthn,
+    UserRepository,
 };
 
 #[rocket::async_trait]
@@ -19,7 +29,29 @@ pub(crate) trait InternalServices {
 
     fn password_hasher(&self) -> &Arc<dyn PasswordHasher>;
 
+    fn password_validator(&self) -> &Arc<dyn PasswordValidator>;
+
+    fn username_policy(&self) -> &Arc<UsernamePolicy>;
+
     fn missing_auth_policy(&self) -> MissingAuthPolicy;
+
+    fn lockout_options(&self) -> LockoutOptions;
+
+    fn sign_in_options(&self) -> SignInOptions;
+
+    fn rate_limit_store(&self) -> &Arc<dyn RateLimitStore>;
+
+    fn rate_limit_options(&self) -> RateLimitOptions;
+
+    fn totp(&self) -> &Arc<Totp>;
+
+    fn webauthn(&self) -> &Arc<WebAuthn>;
+
+    fn token_provider(&self) -> &Arc<TokenProvider>;
+
+    fn notifier(&self) -> Option<&Arc<dyn Notifier>>;
+
+    fn external_providers(&self) -> &Arc<ExternalProviders>;
 }
 
 #[rocket::async_trait]
@@ -31,6 +63,17 @@ impl<'r> Services for Request<'r> {
         let scope = user_store.create_request_scope(self).await;
 
         UserRepository::new(scope, password_hasher.clone())
+            .with_password_validator(self.password_validator().clone())
+            .with_username_policy(self.username_policy().clone())
+            .with_lockout_options(self.lockout_options())
+            .with_sign_in_options(self.sign_in_options())
+            .with_rate_limiting(self.rate_limit_store().clone(), self.rate_limit_options())
+            .with_totp(self.totp().clone())
+            .with_webauthn(self.webauthn().clone())
+            .with_token_provider(self.token_provider().clone())
+            .with_notifier(self.notifier().cloned())
+            .with_external_providers(self.external_providers().clone())
+            .with_client_ip(self.client_ip())
     }
 
     fn authentication_schemes(&self) -> &AuthenticationSchemes {
@@ -47,9 +90,53 @@ impl<'r> InternalServices for Request<'r> {
         self.rocket().password_hasher()
     }
 
+    fn password_validator(&self) -> &Arc<dyn PasswordValidator> {
+        self.rocket().password_validator()
+    }
+
+    fn username_policy(&self) -> &Arc<UsernamePolicy> {
+        self.rocket().username_policy()
+    }
+
     fn missing_auth_policy(&self) -> MissingAuthPolicy {
         self.rocket().missing_auth_policy()
     }
+
+    fn lockout_options(&self) -> LockoutOptions {
+        self.rocket().lockout_options()
+    }
+
+    fn sign_in_options(&self) -> SignInOptions {
+        self.rocket().sign_in_options()
+    }
+
+    fn rate_limit_store(&self) -> &Arc<dyn RateLimitStore> {
+        self.rocket().rate_limit_store()
+    }
+
+    fn rate_limit_options(&self) -> RateLimitOptions {
+        self.rocket().rate_limit_options()
+    }
+
+    fn totp(&self) -> &Arc<Totp> {
+        self.rocket().totp()
+    }
+
+    fn webauthn(&self) -> &Arc<WebAuthn> {
+        self.rocket().webauthn()
+    }
+
+    fn token_provider(&self) -> &Arc<TokenProvider> {
+        self.rocket().token_provider()
+    }
+
+    fn notifier(&self) -> Option<&Arc<dyn Notifier>> {
+        self.rocket().notifier()
+    }
+
+    fn external_providers(&self) -> &Arc<ExternalProviders> {
+        self.rocket().external_providers()
+    }
 }
 
 #[rocket::async_trait]
@@ -64,6 +151,16 @@ impl Services for Rocket<Orbit> {
             .expect("Configured UserStore does not support global scopes");
 
         UserRepository::new(scope, password_hasher.clone())
+            .with_password_validator(self.password_validator().clone())
+            .with_username_policy(self.username_policy().clone())
+            .with_lockout_options(self.lockout_options())
+            .with_sign_in_options(self.sign_in_options())
+            .with_rate_limiting(self.rate_limit_store().clone(), self.rate_limit_options())
+            .with_totp(self.totp().clone())
+            .with_webauthn(self.webauthn().clone())
+            .with_token_provider(self.token_provider().clone())
+            .with_notifier(self.notifier().cloned())
+            .with_external_providers(self.external_providers().clone())
     }
 
     fn authentication_schemes(&self) -> &AuthenticationSchemes {
@@ -83,7 +180,51 @@ impl InternalServices for Rocket<Orbit> {
         self.state().expect("Missing required PasswordHasher")
     }
 
+    fn password_validator(&self) -> &Arc<dyn PasswordValidator> {
+        self.state().expect("Missing required PasswordValidator")
+    }
+
+    fn username_policy(&self) -> &Arc<UsernamePolicy> {
+        self.state().expect("Missing required UsernamePolicy")
+    }
+
     fn missing_auth_policy(&self) -> MissingAuthPolicy {
         *self.state().expect("Missing required MissingAuthPolicy")
     }
+
+    fn lockout_options(&self) -> LockoutOptions {
+        *self.state().expect("Missing required LockoutOptions")
+    }
+
+    fn sign_in_options(&self) -> SignInOptions {
+        *self.state().expect("Missing required SignInOptions")
+    }
+
+    fn rate_limit_store(&self) -> &Arc<dyn RateLimitStore> {
+        self.state().expect("Missing required RateLimitStore")
+    }
+
+    fn rate_limit_options(&self) -> RateLimitOptions {
+        *self.state().expect("Missing required RateLimitOptions")
+    }
+
+    fn totp(&self) -> &Arc<Totp> {
+        self.state().expect("Missing required Totp")
+    }
+
+    fn webauthn(&self) -> &Arc<WebAuthn> {
+        self.state().expect("Missing required WebAuthn")
+    }
+
+    fn token_provider(&self) -> &Arc<TokenProvider> {
+        self.state().expect("Missing required TokenProvider")
+    }
+
+    fn notifier(&self) -> Option<&Arc<dyn Notifier>> {
+        self.state()
+    }
+
+    fn external_providers(&self) -> &Arc<ExternalProviders> {
+        self.state().expect("Missing required ExternalProviders")
+    }
 }
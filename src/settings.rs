@@ -0,0 +1,332 @@
+//! Settings read from the `identity` section of Rocket's configuration, e.g. from
+//! `Rocket.toml`:
+//!
+//! ```toml
+//! [default.identity]
+//! missing_auth_policy = "forward"
+//!
+//! [default.identity.jwt]
+//! secret = "a long random secret"
+//! issuer = "https://example.com"
+//! token_lifetime = 3600
+//!
+//! [default.identity.cookie]
+//! name = "session"
+//! same_site = "lax"
+//! max_age = 86400
+//!
+//! [default.identity.basic]
+//! realm = "example"
+//! charset = "UTF-8"
+//!
+//! [default.identity.argon2]
+//! memory_cost = 19456
+//! iterations = 2
+//! parallelism = 1
+//!
+//! [default.identity.tokens]
+//! secret = "another long random secret"
+//! password_reset_lifetime = 3600
+//! ```
+//!
+//! or from environment variables, e.g. `ROCKET_IDENTITY='{jwt={secret="..."}}'`.
+//! Durations are given in seconds.
+//!
+//! Settings made in code through [`ConfigBuilder`](crate::config::ConfigBuilder) take
+//! precedence: a section only adds its authentication scheme if no scheme of the same
+//! kind was added in code, and the hasher and token settings only apply if no hasher or
+//! token provider was set.
+
+use std::sync::Arc;
+
+use jsonwebtoken::{jwk::Jwk, Algorithm, EncodingKey};
+use rocket::{
+    figment::Figment,
+    http::SameSite,
+    serde::{json::serde_json, Deserialize},
+    time::Duration,
+};
+
+use crate::{
+    config::{Config, MissingAuthPolicy},
+    hashers::argon2::Argon2PasswordHasher,
+    schemes::{
+        basic::{Basic, BasicCharset},
+        cookie::{CookieOptions, CookieScheme},
+        jwt::{JwtBearer, JwtConfig, JwtConfigError, JwtKey},
+        AuthenticationScheme,
+    },
+    tokens::TokenProvider,
+};
+
+/// The key of the section in Rocket's configuration.
+pub const SETTINGS_KEY: &str = "identity";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde", deny_unknown_fields)]
+pub struct Settings {
+    pub missing_auth_policy: Option<MissingAuthPolicy>,
+    pub jwt: Option<JwtSettings>,
+    pub cookie: Option<CookieSettings>,
+    pub basic: Option<BasicSettings>,
+    pub argon2: Option<Argon2Settings>,
+    pub tokens: Option<TokenSettings>,
+}
+
+/// Adds the [`JwtBearer`] scheme. Tokens are either signed with a `secret`, or with a
+/// `private_key` in PEM format whose public key is given as a JWK in `public_key`.
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde", deny_unknown_fields)]
+pub struct JwtSettings {
+    pub secret: Option<String>,
+    pub private_key: Option<String>,
+    pub public_key: Option<String>,
+
+    /// Defaults to `HS256` with a secret and to the algorithm of the public key
+    /// otherwise.
+    pub algorithm: Option<Algorithm>,
+
+    pub key_id: Option<String>,
+    pub issuer: Option<String>,
+    pub token_lifetime: Option<i64>,
+}
+
+/// Adds the [`CookieScheme`].
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde", deny_unknown_fields)]
+pub struct CookieSettings {
+    pub name: Option<String>,
+    pub secure: Option<bool>,
+
+    /// `strict`, `lax` or `none`.
+    pub same_site: Option<String>,
+
+    pub max_age: Option<i64>,
+}
+
+/// Adds the [`Basic`] scheme.
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", deny_unknown_fields)]
+pub struct BasicSettings {
+    pub realm: String,
+
+    /// `UTF-8` (the default) or `ISO-8859-1`.
+    pub charset: Option<String>,
+}
+
+/// Parameters of the default [`Argon2PasswordHasher`].
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", deny_unknown_fields)]
+pub struct Argon2Settings {
+    /// Memory cost in KiB.
+    pub memory_cost: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Settings of the [`TokenProvider`] generating e.g. password reset tokens.
+#[derive(Debug, Default, Deserialize)]
+#[serde(crate = "rocket::serde", deny_unknown_fields)]
+pub struct TokenSettings {
+    /// The key tokens are signed with. Without one, a random key is used and tokens
+    /// become invalid when the application restarts.
+    pub secret: Option<String>,
+
+    pub email_confirmation_lifetime: Option<i64>,
+    pub password_reset_lifetime: Option<i64>,
+    pub login_link_lifetime: Option<i64>,
+}
+
+impl Settings {
+    /// Read the settings from Rocket's configuration. Missing settings are not an error.
+    pub fn from_figment(figment: &Figment) -> Result<Self, SettingsError> {
+        if !figment.contains(SETTINGS_KEY) {
+            return Ok(Self::default());
+        }
+
+        Ok(figment.extract_inner(SETTINGS_KEY).map_err(Box::new)?)
+    }
+
+    /// Complete the configuration made in code with these settings.
+    pub(crate) fn apply(self, config: &mut Config) -> Result<(), SettingsError> {
+        if config.missing_auth_policy.is_none() {
+            config.missing_auth_policy = self.missing_auth_policy;
+        }
+
+        if let (None, Some(argon2)) = (&config.password_hasher, self.argon2) {
+            let hasher = Argon2PasswordHasher::with_params(
+                argon2.memory_cost,
+                argon2.iterations,
+                argon2.parallelism,
+            )
+            .map_err(|e| SettingsError::Invalid(format!("argon2: {}", e)))?;
+
+            config.password_hasher = Some(Arc::new(hasher));
+        }
+
+        if let (None, Some(tokens)) = (&config.token_provider, self.tokens) {
+            config.token_provider = Some(Arc::new(tokens.token_provider()));
+        }
+
+        if let Some(jwt) = self.jwt {
+            if !has_scheme::<JwtBearer>(config) {
+                config
+                    .auth_schemes
+                    .push(Box::new(JwtBearer::new(jwt.jwt_config()?)));
+            }
+        }
+
+        if let Some(cookie) = self.cookie {
+            if !has_scheme::<CookieScheme>(config) {
+                config
+                    .auth_schemes
+                    .push(Box::new(CookieScheme::with_options(
+                        cookie.cookie_options()?,
+                    )));
+            }
+        }
+
+        if let Some(basic) = self.basic {
+            if !has_scheme::<Basic>(config) {
+                config.auth_schemes.push(Box::new(basic.basic()?));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl JwtSettings {
+    fn jwt_config(self) -> Result<JwtConfig, SettingsError> {
+        let mut config = match (self.secret, self.private_key, self.public_key) {
+            (Some(secret), None, None) => {
+                let secret = secret.as_bytes();
+                let algorithm = self.algorithm.unwrap_or(Algorithm::HS256);
+                let key_id = self.key_id.unwrap_or_else(|| JwtKey::hmac_key_id(secret));
+
+                JwtConfig::new(JwtKey::hmac_with_algorithm(key_id, secret, algorithm)?)?
+            }
+            (None, Some(private_key), Some(public_key)) => {
+                let mut public_key: Jwk = serde_json::from_str(&public_key)
+                    .map_err(|e| SettingsError::Invalid(format!("jwt.public_key: {}", e)))?;
+
+                if let Some(key_id) = self.key_id {
+                    public_key.common.key_id = Some(key_id);
+                }
+
+                let algorithm = self.algorithm.or(public_key.common.algorithm);
+                public_key.common.algorithm = algorithm;
+
+                let private_key = private_key.as_bytes();
+                let encoding_key = match algorithm {
+                    Some(Algorithm::ES256 | Algorithm::ES384) => {
+                        EncodingKey::from_ec_pem(private_key)
+                    }
+                    Some(Algorithm::EdDSA) => EncodingKey::from_ed_pem(private_key),
+                    _ => EncodingKey::from_rsa_pem(private_key),
+                }
+                .map_err(|e| SettingsError::Invalid(format!("jwt.private_key: {}", e)))?;
+
+                JwtConfig::asymmetric(encoding_key, public_key)?
+            }
+            _ => {
+                return Err(SettingsError::Invalid(
+                    "jwt: either secret or private_key and public_key are required".to_owned(),
+                ))
+            }
+        };
+
+        if let Some(issuer) = self.issuer {
+            config.issuer = Some(issuer);
+        }
+
+        if let Some(token_lifetime) = self.token_lifetime {
+            config.token_lifetime = Duration::seconds(token_lifetime);
+        }
+
+        Ok(config)
+    }
+}
+
+impl CookieSettings {
+    fn cookie_options(self) -> Result<CookieOptions, SettingsError> {
+        let same_site = match self.same_site.as_deref().map(str::to_lowercase).as_deref() {
+            None => None,
+            Some("strict") => Some(SameSite::Strict),
+            Some("lax") => Some(SameSite::Lax),
+            Some("none") => Some(SameSite::None),
+            Some(other) => {
+                return Err(SettingsError::Invalid(format!(
+                    "cookie.same_site: unknown value {}",
+                    other
+                )))
+            }
+        };
+
+        let defaults = CookieOptions::default();
+
+        Ok(CookieOptions {
+            name: self.name.unwrap_or(defaults.name),
+            secure: self.secure,
+            same_site,
+            max_age: self.max_age.map(Duration::seconds),
+        })
+    }
+}
+
+impl BasicSettings {
+    fn basic(self) -> Result<Basic, SettingsError> {
+        let charset = match self.charset.as_deref().map(str::to_uppercase).as_deref() {
+            None | Some("UTF-8") => BasicCharset::Utf8,
+            Some("ISO-8859-1") => BasicCharset::Iso8859_1,
+            Some(other) => {
+                return Err(SettingsError::Invalid(format!(
+                    "basic.charset: unknown value {}",
+                    other
+                )))
+            }
+        };
+
+        Ok(Basic::new(&self.realm).with_charset(charset))
+    }
+}
+
+impl TokenSettings {
+    fn token_provider(self) -> TokenProvider {
+        let mut token_provider = match self.secret {
+            Some(secret) => TokenProvider::new(secret),
+            None => TokenProvider::default(),
+        };
+
+        if let Some(lifetime) = self.email_confirmation_lifetime {
+            token_provider.email_confirmation_lifetime = Duration::seconds(lifetime);
+        }
+
+        if let Some(lifetime) = self.password_reset_lifetime {
+            token_provider.password_reset_lifetime = Duration::seconds(lifetime);
+        }
+
+        if let Some(lifetime) = self.login_link_lifetime {
+            token_provider.login_link_lifetime = Duration::seconds(lifetime);
+        }
+
+        token_provider
+    }
+}
+
+/// Whether a scheme of the type was added in code.
+fn has_scheme<T: AuthenticationScheme>(config: &Config) -> bool {
+    config.auth_schemes.iter().any(|s| s.is::<T>())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SettingsError {
+    #[error("failed to read the identity settings: {0}")]
+    Figment(#[from] Box<rocket::figment::Error>),
+
+    #[error("invalid identity setting {0}")]
+    Invalid(String),
+
+    #[error("invalid identity setting jwt: {0}")]
+    Jwt(#[from] JwtConfigError),
+}
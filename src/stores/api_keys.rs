@@ -0,0 +1,76 @@
+use std::error::Error;
+
+use rocket::time::OffsetDateTime;
+
+use crate::User;
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that persist
+/// API keys.
+#[rocket::async_trait]
+pub trait UserApiKeyStoreScope: Send + Sync {
+    /// Retrieve all API keys of a given user.
+    async fn user_api_keys(&self, user: &User) -> Result<Vec<StoredApiKey>, ApiKeyStoreError>;
+
+    /// Find an API key and the user it belongs to by its id.
+    async fn find_api_key(
+        &self,
+        id: &str,
+    ) -> Result<Option<(User, StoredApiKey)>, ApiKeyStoreError>;
+
+    /// Add an API key to a given user.
+    async fn add_api_key(
+        &mut self,
+        user: &User,
+        api_key: &StoredApiKey,
+    ) -> Result<(), ApiKeyStoreError>;
+
+    /// Replace the stored API key of a given user with the same id.
+    async fn update_api_key(
+        &mut self,
+        user: &User,
+        api_key: &StoredApiKey,
+    ) -> Result<(), ApiKeyStoreError>;
+
+    /// Remove the API key with the given id from a given user.
+    async fn remove_api_key(&mut self, user: &User, id: &str) -> Result<(), ApiKeyStoreError>;
+}
+
+/// An API key issued to a user. Only a hash of the secret part of the key is stored.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StoredApiKey {
+    /// The public part of the key, used to look it up.
+    pub id: String,
+
+    /// A name describing what the key is used for.
+    pub name: String,
+
+    /// The SHA-256 hash of the secret part of the key.
+    pub secret_hash: Vec<u8>,
+
+    /// The scopes granted to clients using the key. They become the roles of the user.
+    pub scopes: Vec<String>,
+
+    /// When the key was created.
+    pub created: OffsetDateTime,
+
+    /// The key is not accepted after this point in time.
+    pub expires: Option<OffsetDateTime>,
+
+    /// When the key was last used to authenticate.
+    pub last_used: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ApiKeyStoreError {
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("API key was not found")]
+    ApiKeyNotFound,
+
+    #[error("an API key with this id already exists")]
+    ApiKeyExists,
+
+    #[error("an error occurred while trying to access API keys")]
+    Other(#[from] Box<dyn Error>),
+}
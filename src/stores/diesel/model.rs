@@ -1,12 +1,14 @@
 use diesel::prelude::*;
 
-use crate::User;
+use crate::{stores::ExternalLogin, User};
 
 #[derive(Queryable, Selectable)]
 #[diesel(table_name = super::schema::users)]
 pub struct PersistedUser {
     pub id: i32,
     pub username: String,
+    pub email: Option<String>,
+    pub email_confirmed: bool,
 }
 
 #[derive(Insertable)]
@@ -14,6 +16,9 @@ pub struct PersistedUser {
 pub struct NewUser {
     pub username: String,
     pub password_hash: Option<Vec<u8>>,
+    pub email: Option<String>,
+    pub email_confirmed: bool,
+    pub security_stamp: String,
 }
 
 #[derive(Queryable, Selectable)]
@@ -22,8 +27,49 @@ pub struct PasswordHashSelectable {
     pub password_hash: Option<Vec<u8>>,
 }
 
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = super::schema::users)]
+pub struct LockoutSelectable {
+    pub access_failed_count: i32,
+    pub lockout_end: Option<i64>,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = super::schema::users)]
+pub struct SecurityStampSelectable {
+    pub security_stamp: String,
+}
+
+#[derive(Queryable, Selectable)]
+#[diesel(table_name = super::schema::user_logins)]
+pub struct PersistedUserLogin {
+    pub provider: String,
+    pub provider_key: String,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = super::schema::user_logins)]
+pub struct NewUserLogin {
+    pub provider: String,
+    pub provider_key: String,
+    pub user_id: i32,
+}
+
 impl From<PersistedUser> for User {
     fn from(value: PersistedUser) -> Self {
-        User::with_username(value.username)
+        User {
+            email: value.email,
+            email_confirmed: value.email_confirmed,
+            ..User::with_username(value.username)
+        }
+    }
+}
+
+impl From<PersistedUserLogin> for ExternalLogin {
+    fn from(value: PersistedUserLogin) -> Self {
+        ExternalLogin {
+            provider: value.provider,
+            provider_key: value.provider_key,
+        }
     }
 }
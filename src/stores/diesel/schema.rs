@@ -3,5 +3,22 @@ diesel::table! {
         id -> Int4,
         username -> Text,
         password_hash -> Nullable<Binary>,
+        access_failed_count -> Integer,
+        lockout_end -> Nullable<BigInt>,
+        email -> Nullable<Text>,
+        email_confirmed -> Bool,
+        security_stamp -> Text,
     }
-}
\ No newline at end of file
+}
+
+diesel::table! {
+    user_logins (provider, provider_key) {
+        provider -> Text,
+        provider_key -> Text,
+        user_id -> Int4,
+    }
+}
+
+diesel::joinable!(user_logins -> users (user_id));
+
+diesel::allow_tables_to_appear_in_same_query!(users, user_logins);
\ No newline at end of file
@@ -39,7 +39,149 @@ macro_rules! set_password_hash {
     }};
 }
 
+macro_rules! get_lockout_state {
+    ($username:expr) => {{
+        use crate::stores::diesel::model::LockoutSelectable;
+        use crate::stores::diesel::schema::users;
+
+        users::table
+            .filter(users::username.eq($username))
+            .select(LockoutSelectable::as_select())
+    }};
+}
+
+macro_rules! set_lockout_state {
+    ($username:expr, $count:expr, $end:expr) => {{
+        use crate::stores::diesel::schema::users;
+
+        diesel::update(users::table)
+            .filter(users::username.eq($username))
+            .set((
+                users::access_failed_count.eq($count),
+                users::lockout_end.eq($end),
+            ))
+    }};
+}
+
+macro_rules! get_usernames {
+    () => {{
+        use crate::stores::diesel::schema::users;
+
+        users::table.select(users::username)
+    }};
+}
+
+macro_rules! rename_user {
+    ($username:expr, $new_username:expr) => {{
+        use crate::stores::diesel::schema::users;
+
+        diesel::update(users::table)
+            .filter(users::username.eq($username))
+            .set(users::username.eq($new_username))
+    }};
+}
+
+macro_rules! set_email {
+    ($username:expr, $email:expr, $confirmed:expr) => {{
+        use crate::stores::diesel::schema::users;
+
+        diesel::update(users::table)
+            .filter(users::username.eq($username))
+            .set((
+                users::email.eq($email),
+                users::email_confirmed.eq($confirmed),
+            ))
+    }};
+}
+
+macro_rules! get_security_stamp {
+    ($username:expr) => {{
+        use crate::stores::diesel::model::SecurityStampSelectable;
+        use crate::stores::diesel::schema::users;
+
+        users::table
+            .filter(users::username.eq($username))
+            .select(SecurityStampSelectable::as_select())
+    }};
+}
+
+macro_rules! set_security_stamp {
+    ($username:expr, $security_stamp:expr) => {{
+        use crate::stores::diesel::schema::users;
+
+        diesel::update(users::table)
+            .filter(users::username.eq($username))
+            .set(users::security_stamp.eq($security_stamp))
+    }};
+}
+
+macro_rules! get_user_id {
+    ($username:expr) => {{
+        use crate::stores::diesel::schema::users;
+
+        users::table
+            .filter(users::username.eq($username))
+            .select(users::id)
+    }};
+}
+
+macro_rules! find_user_by_login {
+    ($provider:expr, $provider_key:expr) => {{
+        use crate::stores::diesel::model::PersistedUser;
+        use crate::stores::diesel::schema::{user_logins, users};
+
+        user_logins::table
+            .inner_join(users::table)
+            .filter(user_logins::provider.eq($provider))
+            .filter(user_logins::provider_key.eq($provider_key))
+            .select(PersistedUser::as_select())
+    }};
+}
+
+macro_rules! get_user_logins {
+    ($username:expr) => {{
+        use crate::stores::diesel::model::PersistedUserLogin;
+        use crate::stores::diesel::schema::{user_logins, users};
+
+        user_logins::table
+            .inner_join(users::table)
+            .filter(users::username.eq($username))
+            .select(PersistedUserLogin::as_select())
+    }};
+}
+
+macro_rules! add_user_login {
+    ($login:expr) => {{
+        use crate::stores::diesel::schema::user_logins;
+
+        diesel::insert_into(user_logins::table).values($login)
+    }};
+}
+
+macro_rules! remove_user_login {
+    ($user_id:expr, $provider:expr, $provider_key:expr) => {{
+        use crate::stores::diesel::schema::user_logins;
+
+        diesel::delete(user_logins::table)
+            .filter(user_logins::user_id.eq($user_id))
+            .filter(user_logins::provider.eq($provider))
+            .filter(user_logins::provider_key.eq($provider_key))
+    }};
+}
+
 pub(crate) use find_user_by_username;
 pub(crate) use add_user;
 pub(crate) use get_password_hash;
-pub(crate) use set_password_hash;
\ No newline at end of file
+pub(crate) use set_password_hash;
+pub(crate) use get_lockout_state;
+pub(crate) use set_lockout_state;
+pub(crate) use set_email;
+pub(crate) use get_security_stamp;
+pub(crate) use set_security_stamp;
+pub(crate) use get_usernames;
+pub(crate) use rename_user;
+pub(crate) use get_user_id;
+pub(crate) use find_user_by_login;
+pub(crate) use get_user_logins;
+pub(crate) use add_user_login;
+pub(crate) use remove_user_login;
\ No newline at end of file
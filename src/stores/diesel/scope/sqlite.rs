@@ -1,6 +1,7 @@
 use diesel::prelude::*;
+use rocket::time::OffsetDateTime;
 
-use crate::stores::diesel::model::NewUser;
+use crate::stores::diesel::model::{NewUser, NewUserLogin};
 use crate::stores::impls::prelude::*;
 
 use super::queries;
@@ -34,12 +35,26 @@ impl<T> UserStoreScope for SqliteScope<T> {
         &mut self,
         user: &User,
         password_hash: Option<&PasswordHash>,
+    ) -> Result<(), AddUserError> {
+        self.add_user_with_security_stamp(user, password_hash, "")
+            .await
+    }
+
+    /// Add a user to the store together with their initial security stamp.
+    async fn add_user_with_security_stamp(
+        &mut self,
+        user: &User,
+        password_hash: Option<&PasswordHash>,
+        security_stamp: &str,
     ) -> Result<(), AddUserError> {
         log::debug!("Adding user: {}", user.username);
 
         let new_user = NewUser {
             username: user.username.clone(),
             password_hash: password_hash.map(|h| h.clone().into_inner()),
+            email: user.email.clone(),
+            email_confirmed: user.email_confirmed,
+            security_stamp: security_stamp.to_owned(),
         };
 
         self.conn
@@ -84,4 +99,285 @@ impl<T> UserStoreScope for SqliteScope<T> {
 
         Ok(())
     }
+
+    /// Set the email address of a given user and whether it is confirmed.
+    async fn set_email(
+        &mut self,
+        user: &User,
+        email: Option<&str>,
+        confirmed: bool,
+    ) -> Result<(), EmailError> {
+        log::debug!("Setting email for user: {}", user.username);
+
+        let username = user.username.clone();
+        let email = email.map(str::to_owned);
+
+        let updated = self
+            .conn
+            .run(move |c| queries::set_email!(username, email, confirmed).execute(c))
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        if updated != 1 {
+            return Err(EmailError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve the security stamp of a given user.
+    async fn security_stamp(&self, user: &User) -> Result<String, SecurityStampError> {
+        log::debug!("Retrieving security stamp for user: {}", user.username);
+
+        let username = user.username.to_string();
+        let stamp = self
+            .conn
+            .run(|c| queries::get_security_stamp!(username).first(c).optional())
+            .await
+            .map_err(BoxableError::boxed)?
+            .ok_or(SecurityStampError::UserNotFound)?;
+
+        Ok(stamp.security_stamp)
+    }
+
+    /// Set the security stamp of a given user.
+    async fn set_security_stamp(
+        &mut self,
+        user: &User,
+        security_stamp: &str,
+    ) -> Result<(), SecurityStampError> {
+        log::debug!("Setting security stamp for user: {}", user.username);
+
+        let username = user.username.clone();
+        let security_stamp = security_stamp.to_owned();
+
+        let updated = self
+            .conn
+            .run(move |c| queries::set_security_stamp!(username, security_stamp).execute(c))
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        if updated != 1 {
+            return Err(SecurityStampError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    /// Retrieve the usernames of all users.
+    async fn usernames(&self) -> Result<Vec<String>, RenameUserError> {
+        log::debug!("Retrieving all usernames");
+
+        let usernames = self
+            .conn
+            .run(|c| queries::get_usernames!().load(c))
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        Ok(usernames)
+    }
+
+    /// Change the username of the user with the given username.
+    async fn rename_user(
+        &mut self,
+        username: &str,
+        new_username: &str,
+    ) -> Result<(), RenameUserError> {
+        log::debug!("Renaming user {} to {}", username, new_username);
+
+        let username = username.to_owned();
+        let new_username = new_username.to_owned();
+        let renamed = self
+            .conn
+            .run(move |c| queries::rename_user!(username, new_username).execute(c))
+            .await
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    _,
+                ) => RenameUserError::UsernameExists,
+                e => e.boxed().into(),
+            })?;
+
+        if renamed == 0 {
+            return Err(RenameUserError::UserNotFound);
+        }
+
+        Ok(())
+    }
+
+    fn lockout(&mut self) -> Option<&mut dyn UserLockoutStoreScope> {
+        Some(self)
+    }
+
+    fn external_logins(&mut self) -> Option<&mut dyn UserExternalLoginStoreScope> {
+        Some(self)
+    }
+}
+
+#[rocket::async_trait]
+impl<T> UserLockoutStoreScope for SqliteScope<T> {
+    /// Retrieve the failed login attempts and lockout for a given user.
+    async fn lockout_state(&self, user: &User) -> Result<LockoutState, LockoutStateError> {
+        log::debug!("Retrieving lockout state for user: {}", user.username);
+
+        let username = user.username.to_string();
+        let lockout = self
+            .conn
+            .run(|c| queries::get_lockout_state!(username).first(c).optional())
+            .await
+            .map_err(BoxableError::boxed)?
+            .ok_or(LockoutStateError::UserNotFound)?;
+
+        let locked_out_until = lockout
+            .lockout_end
+            .map(OffsetDateTime::from_unix_timestamp)
+            .transpose()
+            .map_err(BoxableError::boxed)?;
+
+        Ok(LockoutState {
+            failed_attempts: lockout.access_failed_count.max(0) as u32,
+            locked_out_until,
+        })
+    }
+
+    /// Set the failed login attempts and lockout for a given user.
+    async fn set_lockout_state(
+        &mut self,
+        user: &User,
+        state: &LockoutState,
+    ) -> Result<(), LockoutStateError> {
+        log::debug!("Setting lockout state for user: {}", user.username);
+
+        let username = user.username.clone();
+        let count = state.failed_attempts.min(i32::MAX as u32) as i32;
+        let end = state.locked_out_until.map(|t| t.unix_timestamp());
+
+        let updated = self
+            .conn
+            .run(move |c| queries::set_lockout_state!(username, count, end).execute(c))
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        if updated != 1 {
+            return Err(LockoutStateError::UserNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl<T> UserExternalLoginStoreScope for SqliteScope<T> {
+    /// Find the user an external identity is linked to.
+    async fn find_user_by_login(
+        &self,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<Option<User>, ExternalLoginStoreError> {
+        log::debug!("Finding user by {} login", provider);
+
+        let provider = provider.to_owned();
+        let provider_key = provider_key.to_owned();
+        let user = self
+            .conn
+            .run(|c| {
+                queries::find_user_by_login!(provider, provider_key)
+                    .first(c)
+                    .optional()
+            })
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        Ok(user.map(|u| u.into()))
+    }
+
+    /// Retrieve all external identities linked to a given user.
+    async fn user_logins(
+        &self,
+        user: &User,
+    ) -> Result<Vec<ExternalLogin>, ExternalLoginStoreError> {
+        log::debug!("Retrieving external logins for user: {}", user.username);
+
+        let username = user.username.to_string();
+        let logins = self
+            .conn
+            .run(|c| queries::get_user_logins!(username).load(c))
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        Ok(logins.into_iter().map(ExternalLogin::from).collect())
+    }
+
+    /// Link an external identity to a given user.
+    async fn add_login(
+        &mut self,
+        user: &User,
+        login: &ExternalLogin,
+    ) -> Result<(), ExternalLoginStoreError> {
+        log::debug!(
+            "Adding {} login for user: {}",
+            login.provider,
+            user.username
+        );
+
+        let username = user.username.clone();
+        let user_id = self
+            .conn
+            .run(|c| queries::get_user_id!(username).first::<i32>(c).optional())
+            .await
+            .map_err(BoxableError::boxed)?
+            .ok_or(ExternalLoginStoreError::UserNotFound)?;
+
+        let new_login = NewUserLogin {
+            provider: login.provider.clone(),
+            provider_key: login.provider_key.clone(),
+            user_id,
+        };
+
+        self.conn
+            .run(|c| queries::add_user_login!(new_login).execute(c))
+            .await
+            .map_err(|e| match e {
+                diesel::result::Error::DatabaseError(
+                    diesel::result::DatabaseErrorKind::UniqueViolation,
+                    _,
+                ) => ExternalLoginStoreError::LoginExists,
+                e => e.boxed().into(),
+            })?;
+
+        Ok(())
+    }
+
+    /// Unlink an external identity from a given user.
+    async fn remove_login(
+        &mut self,
+        user: &User,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<(), ExternalLoginStoreError> {
+        log::debug!("Removing {} login for user: {}", provider, user.username);
+
+        let username = user.username.clone();
+        let user_id = self
+            .conn
+            .run(|c| queries::get_user_id!(username).first::<i32>(c).optional())
+            .await
+            .map_err(BoxableError::boxed)?
+            .ok_or(ExternalLoginStoreError::UserNotFound)?;
+
+        let provider = provider.to_owned();
+        let provider_key = provider_key.to_owned();
+        let removed = self
+            .conn
+            .run(move |c| queries::remove_user_login!(user_id, provider, provider_key).execute(c))
+            .await
+            .map_err(BoxableError::boxed)?;
+
+        if removed == 0 {
+            return Err(ExternalLoginStoreError::LoginNotFound);
+        }
+
+        Ok(())
+    }
 }
@@ -0,0 +1,101 @@
+use std::error::Error;
+
+use data_encoding::HEXLOWER;
+use sha2::{Digest, Sha256};
+
+use crate::User;
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that keep the
+/// credentials HTTP Digest authentication needs. Digest authentication cannot use
+/// password hashes, since clients prove they know `H(username:realm:password)` (HA1).
+#[rocket::async_trait]
+pub trait UserDigestStoreScope: Send + Sync {
+    /// Retrieve the digest credentials of a given user for all realms.
+    async fn user_digest_credentials(
+        &self,
+        user: &User,
+    ) -> Result<Vec<DigestCredentials>, DigestStoreError>;
+
+    /// Set the digest credentials of a given user, replacing those for the same realm.
+    async fn set_digest_credentials(
+        &mut self,
+        user: &User,
+        credentials: &DigestCredentials,
+    ) -> Result<(), DigestStoreError>;
+
+    /// Remove the digest credentials of a given user for a realm.
+    async fn remove_digest_credentials(
+        &mut self,
+        user: &User,
+        realm: &str,
+    ) -> Result<(), DigestStoreError>;
+}
+
+/// The HA1 values of a user for a realm, for each supported algorithm. HA1 values are
+/// password equivalents for the realm and must be protected like passwords.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DigestCredentials {
+    pub realm: String,
+
+    /// `MD5(username:realm:password)` in lowercase hex.
+    pub ha1_md5: String,
+
+    /// `SHA-256(username:realm:password)` in lowercase hex.
+    pub ha1_sha256: String,
+}
+
+impl DigestCredentials {
+    /// Derive the credentials of the user for a realm from a password.
+    pub fn new(user: &User, realm: &str, password: &str) -> Self {
+        let a1 = format!("{}:{}:{}", user.username, realm, password);
+
+        Self {
+            realm: realm.to_owned(),
+            ha1_md5: DigestAlgorithm::Md5.hash(&a1),
+            ha1_sha256: DigestAlgorithm::Sha256.hash(&a1),
+        }
+    }
+
+    /// The HA1 value for the given algorithm.
+    pub fn ha1(&self, algorithm: DigestAlgorithm) -> &str {
+        match algorithm {
+            DigestAlgorithm::Md5 => &self.ha1_md5,
+            DigestAlgorithm::Sha256 => &self.ha1_sha256,
+        }
+    }
+}
+
+/// The hash algorithms of HTTP Digest authentication (RFC 7616).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    /// Only for clients that do not support SHA-256.
+    Md5,
+    Sha256,
+}
+
+impl DigestAlgorithm {
+    /// The name of the algorithm in challenges and responses.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Self::Md5 => "MD5",
+            Self::Sha256 => "SHA-256",
+        }
+    }
+
+    /// Hash the data, returning the hash in lowercase hex.
+    pub fn hash(&self, data: &str) -> String {
+        match self {
+            Self::Md5 => format!("{:x}", md5::compute(data.as_bytes())),
+            Self::Sha256 => HEXLOWER.encode(&Sha256::digest(data.as_bytes())),
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum DigestStoreError {
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("an error occurred while trying to access digest credentials")]
+    Other(#[from] Box<dyn Error>),
+}
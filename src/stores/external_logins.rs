@@ -0,0 +1,59 @@
+use std::error::Error;
+
+use crate::User;
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that link users
+/// to their identities at external providers.
+#[rocket::async_trait]
+pub trait UserExternalLoginStoreScope: Send + Sync {
+    /// Find the user an external identity is linked to.
+    async fn find_user_by_login(
+        &self,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<Option<User>, ExternalLoginStoreError>;
+
+    /// Retrieve all external identities linked to a given user.
+    async fn user_logins(&self, user: &User)
+        -> Result<Vec<ExternalLogin>, ExternalLoginStoreError>;
+
+    /// Link an external identity to a given user.
+    async fn add_login(
+        &mut self,
+        user: &User,
+        login: &ExternalLogin,
+    ) -> Result<(), ExternalLoginStoreError>;
+
+    /// Unlink an external identity from a given user.
+    async fn remove_login(
+        &mut self,
+        user: &User,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<(), ExternalLoginStoreError>;
+}
+
+/// An identity at an external provider linked to a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ExternalLogin {
+    /// The name of the provider.
+    pub provider: String,
+
+    /// The identifier of the user at the provider.
+    pub provider_key: String,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ExternalLoginStoreError {
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("the external login is already linked to a user")]
+    LoginExists,
+
+    #[error("the external login was not found")]
+    LoginNotFound,
+
+    #[error("an error occurred while trying to access external logins")]
+    Other(#[from] Box<dyn Error>),
+}
@@ -1,7 +1,15 @@
 pub mod prelude {
     pub use crate::{
         hashers::PasswordHash,
-        stores::{AddUserError, FindUserError, PasswordHashError, UserStore, UserStoreScope},
+        stores::{
+            AddUserError, ApiKeyStoreError, DigestCredentials, DigestStoreError, EmailError,
+            ExternalLogin, ExternalLoginStoreError, FindUserError, LockoutState, LockoutStateError,
+            Passkey, PasskeyStoreError, PasswordHashError, RenameUserError, SecurityStampError,
+            SigningKeyStoreError, StoredApiKey, StoredSigningKey, TwoFactorState,
+            TwoFactorStateError, UserApiKeyStoreScope, UserDigestStoreScope,
+            UserExternalLoginStoreScope, UserLockoutStoreScope, UserPasskeyStoreScope,
+            UserSigningKeyStoreScope, UserStore, UserStoreScope, UserTwoFactorStoreScope,
+        },
         util::BoxableError,
         User,
     };
@@ -0,0 +1,42 @@
+use std::error::Error;
+
+use rocket::time::OffsetDateTime;
+
+use crate::User;
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that persist
+/// failed login attempts and lockouts.
+#[rocket::async_trait]
+pub trait UserLockoutStoreScope: Send + Sync {
+    /// Retrieve the failed login attempts and lockout for a given user.
+    async fn lockout_state(&self, user: &User) -> Result<LockoutState, LockoutStateError>;
+
+    /// Set the failed login attempts and lockout for a given user.
+    async fn set_lockout_state(
+        &mut self,
+        user: &User,
+        state: &LockoutState,
+    ) -> Result<(), LockoutStateError>;
+}
+
+/// Failed login attempts and lockout of a user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct LockoutState {
+    /// Number of failed login attempts since the last successful login or lockout.
+    pub failed_attempts: u32,
+
+    /// The user cannot log in until this point in time.
+    pub locked_out_until: Option<OffsetDateTime>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum LockoutStateError {
+    #[error("the user store does not support lockouts")]
+    NotSupported,
+
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("an error occurred while trying to access the lockout state")]
+    Other(#[from] Box<dyn Error>),
+}
@@ -20,6 +20,16 @@ impl UserStoreScope for MemoryStoreScope {
     }
 
     async fn add_user(&mut self, user: &User, password_hash: Option<&PasswordHash>) -> Result<(), AddUserError> {
+        self.add_user_with_security_stamp(user, password_hash, "")
+            .await
+    }
+
+    async fn add_user_with_security_stamp(
+        &mut self,
+        user: &User,
+        password_hash: Option<&PasswordHash>,
+        security_stamp: &str,
+    ) -> Result<(), AddUserError> {
         let mut users = self.users.write().await;
 
         if users.contains_key(&user.username) {
@@ -31,6 +41,14 @@ impl UserStoreScope for MemoryStoreScope {
             UserEntry {
                 user: user.clone(),
                 password_hash: password_hash.cloned(),
+                lockout: LockoutState::default(),
+                security_stamp: security_stamp.to_owned(),
+                two_factor: TwoFactorState::default(),
+                passkeys: Vec::new(),
+                api_keys: Vec::new(),
+                external_logins: Vec::new(),
+                digest_credentials: Vec::new(),
+                signing_keys: Vec::new(),
             },
         );
 
@@ -58,4 +76,550 @@ impl UserStoreScope for MemoryStoreScope {
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    async fn set_email(
+        &mut self,
+        user: &User,
+        email: Option<&str>,
+        confirmed: bool,
+    ) -> Result<(), EmailError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(EmailError::UserNotFound);
+        };
+
+        entry.user.email = email.map(str::to_owned);
+        entry.user.email_confirmed = confirmed;
+
+        Ok(())
+    }
+
+    async fn security_stamp(&self, user: &User) -> Result<String, SecurityStampError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(SecurityStampError::UserNotFound);
+        };
+
+        Ok(entry.security_stamp.clone())
+    }
+
+    async fn set_security_stamp(
+        &mut self,
+        user: &User,
+        security_stamp: &str,
+    ) -> Result<(), SecurityStampError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(SecurityStampError::UserNotFound);
+        };
+
+        entry.security_stamp = security_stamp.to_owned();
+
+        Ok(())
+    }
+
+    async fn usernames(&self) -> Result<Vec<String>, RenameUserError> {
+        let users = self.users.read().await;
+
+        Ok(users.keys().cloned().collect())
+    }
+
+    async fn rename_user(
+        &mut self,
+        username: &str,
+        new_username: &str,
+    ) -> Result<(), RenameUserError> {
+        let mut users = self.users.write().await;
+
+        if users.contains_key(new_username) {
+            return Err(RenameUserError::UsernameExists);
+        }
+
+        let Some(mut entry) = users.remove(username) else {
+            return Err(RenameUserError::UserNotFound);
+        };
+
+        entry.user.username = new_username.to_owned();
+        users.insert(new_username.to_owned(), entry);
+
+        Ok(())
+    }
+
+    fn lockout(&mut self) -> Option<&mut dyn UserLockoutStoreScope> {
+        Some(self)
+    }
+
+    fn two_factor(&mut self) -> Option<&mut dyn UserTwoFactorStoreScope> {
+        Some(self)
+    }
+
+    fn passkeys(&mut self) -> Option<&mut dyn UserPasskeyStoreScope> {
+        Some(self)
+    }
+
+    fn api_keys(&mut self) -> Option<&mut dyn UserApiKeyStoreScope> {
+        Some(self)
+    }
+
+    fn external_logins(&mut self) -> Option<&mut dyn UserExternalLoginStoreScope> {
+        Some(self)
+    }
+
+    fn digest(&mut self) -> Option<&mut dyn UserDigestStoreScope> {
+        Some(self)
+    }
+
+    fn signing_keys(&mut self) -> Option<&mut dyn UserSigningKeyStoreScope> {
+        Some(self)
+    }
+}
+
+#[rocket::async_trait]
+impl UserLockoutStoreScope for MemoryStoreScope {
+    async fn lockout_state(&self, user: &User) -> Result<LockoutState, LockoutStateError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(LockoutStateError::UserNotFound);
+        };
+
+        Ok(entry.lockout.clone())
+    }
+
+    async fn set_lockout_state(
+        &mut self,
+        user: &User,
+        state: &LockoutState,
+    ) -> Result<(), LockoutStateError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(LockoutStateError::UserNotFound);
+        };
+
+        entry.lockout = state.clone();
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl UserTwoFactorStoreScope for MemoryStoreScope {
+    async fn two_factor_state(&self, user: &User) -> Result<TwoFactorState, TwoFactorStateError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(TwoFactorStateError::UserNotFound);
+        };
+
+        Ok(entry.two_factor.clone())
+    }
+
+    async fn set_two_factor_state(
+        &mut self,
+        user: &User,
+        state: &TwoFactorState,
+    ) -> Result<(), TwoFactorStateError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(TwoFactorStateError::UserNotFound);
+        };
+
+        entry.two_factor = state.clone();
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl UserPasskeyStoreScope for MemoryStoreScope {
+    async fn user_passkeys(&self, user: &User) -> Result<Vec<Passkey>, PasskeyStoreError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(PasskeyStoreError::UserNotFound);
+        };
+
+        Ok(entry.passkeys.clone())
+    }
+
+    async fn find_passkey(
+        &self,
+        credential_id: &[u8],
+    ) -> Result<Option<(User, Passkey)>, PasskeyStoreError> {
+        let users = self.users.read().await;
+
+        Ok(users.values().find_map(|entry| {
+            entry
+                .passkeys
+                .iter()
+                .find(|p| p.credential_id == credential_id)
+                .map(|p| (entry.user.clone(), p.clone()))
+        }))
+    }
+
+    async fn add_passkey(
+        &mut self,
+        user: &User,
+        passkey: &Passkey,
+    ) -> Result<(), PasskeyStoreError> {
+        let mut users = self.users.write().await;
+
+        if users.values().any(|e| {
+            e.passkeys
+                .iter()
+                .any(|p| p.credential_id == passkey.credential_id)
+        }) {
+            return Err(PasskeyStoreError::CredentialExists);
+        }
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(PasskeyStoreError::UserNotFound);
+        };
+
+        entry.passkeys.push(passkey.clone());
+
+        Ok(())
+    }
+
+    async fn update_passkey(
+        &mut self,
+        user: &User,
+        passkey: &Passkey,
+    ) -> Result<(), PasskeyStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(PasskeyStoreError::UserNotFound);
+        };
+
+        let Some(existing) = entry
+            .passkeys
+            .iter_mut()
+            .find(|p| p.credential_id == passkey.credential_id)
+        else {
+            return Err(PasskeyStoreError::PasskeyNotFound);
+        };
+
+        *existing = passkey.clone();
+
+        Ok(())
+    }
+
+    async fn remove_passkey(
+        &mut self,
+        user: &User,
+        credential_id: &[u8],
+    ) -> Result<(), PasskeyStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(PasskeyStoreError::UserNotFound);
+        };
+
+        let count = entry.passkeys.len();
+        entry.passkeys.retain(|p| p.credential_id != credential_id);
+
+        if entry.passkeys.len() == count {
+            return Err(PasskeyStoreError::PasskeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl UserApiKeyStoreScope for MemoryStoreScope {
+    async fn user_api_keys(&self, user: &User) -> Result<Vec<StoredApiKey>, ApiKeyStoreError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(ApiKeyStoreError::UserNotFound);
+        };
+
+        Ok(entry.api_keys.clone())
+    }
+
+    async fn find_api_key(
+        &self,
+        id: &str,
+    ) -> Result<Option<(User, StoredApiKey)>, ApiKeyStoreError> {
+        let users = self.users.read().await;
+
+        Ok(users.values().find_map(|entry| {
+            entry
+                .api_keys
+                .iter()
+                .find(|k| k.id == id)
+                .map(|k| (entry.user.clone(), k.clone()))
+        }))
+    }
+
+    async fn add_api_key(
+        &mut self,
+        user: &User,
+        api_key: &StoredApiKey,
+    ) -> Result<(), ApiKeyStoreError> {
+        let mut users = self.users.write().await;
+
+        if users
+            .values()
+            .any(|e| e.api_keys.iter().any(|k| k.id == api_key.id))
+        {
+            return Err(ApiKeyStoreError::ApiKeyExists);
+        }
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(ApiKeyStoreError::UserNotFound);
+        };
+
+        entry.api_keys.push(api_key.clone());
+
+        Ok(())
+    }
+
+    async fn update_api_key(
+        &mut self,
+        user: &User,
+        api_key: &StoredApiKey,
+    ) -> Result<(), ApiKeyStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(ApiKeyStoreError::UserNotFound);
+        };
+
+        let Some(existing) = entry.api_keys.iter_mut().find(|k| k.id == api_key.id) else {
+            return Err(ApiKeyStoreError::ApiKeyNotFound);
+        };
+
+        *existing = api_key.clone();
+
+        Ok(())
+    }
+
+    async fn remove_api_key(&mut self, user: &User, id: &str) -> Result<(), ApiKeyStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(ApiKeyStoreError::UserNotFound);
+        };
+
+        let count = entry.api_keys.len();
+        entry.api_keys.retain(|k| k.id != id);
+
+        if entry.api_keys.len() == count {
+            return Err(ApiKeyStoreError::ApiKeyNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl UserExternalLoginStoreScope for MemoryStoreScope {
+    async fn find_user_by_login(
+        &self,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<Option<User>, ExternalLoginStoreError> {
+        let users = self.users.read().await;
+
+        Ok(users
+            .values()
+            .find(|entry| {
+                entry
+                    .external_logins
+                    .iter()
+                    .any(|l| l.provider == provider && l.provider_key == provider_key)
+            })
+            .map(|entry| entry.user.clone()))
+    }
+
+    async fn user_logins(
+        &self,
+        user: &User,
+    ) -> Result<Vec<ExternalLogin>, ExternalLoginStoreError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(ExternalLoginStoreError::UserNotFound);
+        };
+
+        Ok(entry.external_logins.clone())
+    }
+
+    async fn add_login(
+        &mut self,
+        user: &User,
+        login: &ExternalLogin,
+    ) -> Result<(), ExternalLoginStoreError> {
+        let mut users = self.users.write().await;
+
+        if users.values().any(|e| e.external_logins.contains(login)) {
+            return Err(ExternalLoginStoreError::LoginExists);
+        }
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(ExternalLoginStoreError::UserNotFound);
+        };
+
+        entry.external_logins.push(login.clone());
+
+        Ok(())
+    }
+
+    async fn remove_login(
+        &mut self,
+        user: &User,
+        provider: &str,
+        provider_key: &str,
+    ) -> Result<(), ExternalLoginStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(ExternalLoginStoreError::UserNotFound);
+        };
+
+        let count = entry.external_logins.len();
+        entry
+            .external_logins
+            .retain(|l| l.provider != provider || l.provider_key != provider_key);
+
+        if entry.external_logins.len() == count {
+            return Err(ExternalLoginStoreError::LoginNotFound);
+        }
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl UserDigestStoreScope for MemoryStoreScope {
+    async fn user_digest_credentials(
+        &self,
+        user: &User,
+    ) -> Result<Vec<DigestCredentials>, DigestStoreError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(DigestStoreError::UserNotFound);
+        };
+
+        Ok(entry.digest_credentials.clone())
+    }
+
+    async fn set_digest_credentials(
+        &mut self,
+        user: &User,
+        credentials: &DigestCredentials,
+    ) -> Result<(), DigestStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(DigestStoreError::UserNotFound);
+        };
+
+        entry
+            .digest_credentials
+            .retain(|c| c.realm != credentials.realm);
+        entry.digest_credentials.push(credentials.clone());
+
+        Ok(())
+    }
+
+    async fn remove_digest_credentials(
+        &mut self,
+        user: &User,
+        realm: &str,
+    ) -> Result<(), DigestStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(DigestStoreError::UserNotFound);
+        };
+
+        entry.digest_credentials.retain(|c| c.realm != realm);
+
+        Ok(())
+    }
+}
+
+#[rocket::async_trait]
+impl UserSigningKeyStoreScope for MemoryStoreScope {
+    async fn user_signing_keys(
+        &self,
+        user: &User,
+    ) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError> {
+        let users = self.users.read().await;
+
+        let Some(entry) = users.get(&user.username) else {
+            return Err(SigningKeyStoreError::UserNotFound);
+        };
+
+        Ok(entry.signing_keys.clone())
+    }
+
+    async fn find_signing_key(
+        &self,
+        id: &str,
+    ) -> Result<Option<(User, StoredSigningKey)>, SigningKeyStoreError> {
+        let users = self.users.read().await;
+
+        Ok(users.values().find_map(|entry| {
+            entry
+                .signing_keys
+                .iter()
+                .find(|k| k.id == id)
+                .map(|k| (entry.user.clone(), k.clone()))
+        }))
+    }
+
+    async fn add_signing_key(
+        &mut self,
+        user: &User,
+        signing_key: &StoredSigningKey,
+    ) -> Result<(), SigningKeyStoreError> {
+        let mut users = self.users.write().await;
+
+        if users
+            .values()
+            .any(|e| e.signing_keys.iter().any(|k| k.id == signing_key.id))
+        {
+            return Err(SigningKeyStoreError::SigningKeyExists);
+        }
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(SigningKeyStoreError::UserNotFound);
+        };
+
+        entry.signing_keys.push(signing_key.clone());
+
+        Ok(())
+    }
+
+    async fn remove_signing_key(
+        &mut self,
+        user: &User,
+        id: &str,
+    ) -> Result<(), SigningKeyStoreError> {
+        let mut users = self.users.write().await;
+
+        let Some(entry) = users.get_mut(&user.username) else {
+            return Err(SigningKeyStoreError::UserNotFound);
+        };
+
+        let count = entry.signing_keys.len();
+        entry.signing_keys.retain(|k| k.id != id);
+
+        if entry.signing_keys.len() == count {
+            return Err(SigningKeyStoreError::SigningKeyNotFound);
+        }
+
+        Ok(())
+    }
+}
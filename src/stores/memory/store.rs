@@ -16,6 +16,14 @@ pub struct MemoryStore {
 pub(crate) struct UserEntry {
     pub user: User,
     pub password_hash: Option<PasswordHash>,
+    pub lockout: LockoutState,
+    pub security_stamp: String,
+    pub two_factor: TwoFactorState,
+    pub passkeys: Vec<Passkey>,
+    pub api_keys: Vec<StoredApiKey>,
+    pub external_logins: Vec<ExternalLogin>,
+    pub digest_credentials: Vec<DigestCredentials>,
+    pub signing_keys: Vec<StoredSigningKey>,
 }
 
 impl MemoryStore {
@@ -1,10 +1,24 @@
+mod api_keys;
+mod digest;
+mod external_logins;
+mod lockout;
+mod passkeys;
 mod scope;
+mod signing_keys;
 mod store;
+mod two_factor;
 
 pub mod diesel;
 pub mod memory;
 
 pub mod impls;
 
+pub use api_keys::*;
+pub use digest::*;
+pub use external_logins::*;
+pub use lockout::*;
+pub use passkeys::*;
 pub use scope::*;
+pub use signing_keys::*;
 pub use store::*;
+pub use two_factor::*;
@@ -0,0 +1,66 @@
+use std::error::Error;
+
+use crate::User;
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that persist
+/// WebAuthn credentials.
+#[rocket::async_trait]
+pub trait UserPasskeyStoreScope: Send + Sync {
+    /// Retrieve all passkeys of a given user.
+    async fn user_passkeys(&self, user: &User) -> Result<Vec<Passkey>, PasskeyStoreError>;
+
+    /// Find a passkey and the user it belongs to by its credential id.
+    async fn find_passkey(
+        &self,
+        credential_id: &[u8],
+    ) -> Result<Option<(User, Passkey)>, PasskeyStoreError>;
+
+    /// Add a passkey to a given user.
+    async fn add_passkey(
+        &mut self,
+        user: &User,
+        passkey: &Passkey,
+    ) -> Result<(), PasskeyStoreError>;
+
+    /// Replace the stored passkey of a given user with the same credential id.
+    async fn update_passkey(
+        &mut self,
+        user: &User,
+        passkey: &Passkey,
+    ) -> Result<(), PasskeyStoreError>;
+
+    /// Remove the passkey with the given credential id from a given user.
+    async fn remove_passkey(
+        &mut self,
+        user: &User,
+        credential_id: &[u8],
+    ) -> Result<(), PasskeyStoreError>;
+}
+
+/// A WebAuthn credential registered for a user.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Passkey {
+    /// The id the authenticator assigned to the credential.
+    pub credential_id: Vec<u8>,
+
+    /// The SEC1 encoded P-256 public key of the credential.
+    pub public_key: Vec<u8>,
+
+    /// The last signature counter reported by the authenticator.
+    pub sign_count: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum PasskeyStoreError {
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("passkey was not found")]
+    PasskeyNotFound,
+
+    #[error("a passkey with this credential id already exists")]
+    CredentialExists,
+
+    #[error("an error occurred while trying to access passkeys")]
+    Other(#[from] Box<dyn Error>),
+}
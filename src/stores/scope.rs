@@ -2,6 +2,11 @@ use std::error::Error;
 
 use crate::{hashers::PasswordHash, User};
 
+use super::{
+    UserApiKeyStoreScope, UserDigestStoreScope, UserExternalLoginStoreScope, UserLockoutStoreScope,
+    UserPasskeyStoreScope, UserSigningKeyStoreScope, UserTwoFactorStoreScope,
+};
+
 /// Trait for an object that persists users.
 #[rocket::async_trait]
 pub trait UserStoreScope: Send + Sync + 'static {
@@ -15,6 +20,18 @@ pub trait UserStoreScope: Send + Sync + 'static {
         password_hash: Option<&PasswordHash>,
     ) -> Result<(), AddUserError>;
 
+    /// Add a user to the store together with their initial security stamp. Stores that
+    /// support security stamps should write both at once, the default ignores the
+    /// security stamp.
+    async fn add_user_with_security_stamp(
+        &mut self,
+        user: &User,
+        password_hash: Option<&PasswordHash>,
+        _security_stamp: &str,
+    ) -> Result<(), AddUserError> {
+        self.add_user(user, password_hash).await
+    }
+
     /// Retrieve the password hash for a given user.
     async fn password_hash(&self, user: &User) -> Result<Option<PasswordHash>, PasswordHashError>;
 
@@ -24,6 +41,80 @@ pub trait UserStoreScope: Send + Sync + 'static {
         user: &User,
         password_hash: &PasswordHash,
     ) -> Result<(), PasswordHashError>;
+
+    /// Retrieve the usernames of all users, e.g. to migrate them to a new username policy.
+    async fn usernames(&self) -> Result<Vec<String>, RenameUserError> {
+        Err(RenameUserError::NotSupported)
+    }
+
+    /// Change the username of the user with the given username.
+    async fn rename_user(
+        &mut self,
+        _username: &str,
+        _new_username: &str,
+    ) -> Result<(), RenameUserError> {
+        Err(RenameUserError::NotSupported)
+    }
+
+    /// Access failed login attempts and lockouts if the store supports them.
+    fn lockout(&mut self) -> Option<&mut dyn UserLockoutStoreScope> {
+        None
+    }
+
+    /// Set the email address of a given user and whether it is confirmed.
+    async fn set_email(
+        &mut self,
+        _user: &User,
+        _email: Option<&str>,
+        _confirmed: bool,
+    ) -> Result<(), EmailError> {
+        Err(EmailError::NotSupported)
+    }
+
+    /// Retrieve the security stamp of a given user. The security stamp changes whenever
+    /// the credentials of the user change.
+    async fn security_stamp(&self, _user: &User) -> Result<String, SecurityStampError> {
+        Err(SecurityStampError::NotSupported)
+    }
+
+    /// Set the security stamp of a given user.
+    async fn set_security_stamp(
+        &mut self,
+        _user: &User,
+        _security_stamp: &str,
+    ) -> Result<(), SecurityStampError> {
+        Err(SecurityStampError::NotSupported)
+    }
+
+    /// Access two-factor authentication data if the store supports it.
+    fn two_factor(&mut self) -> Option<&mut dyn UserTwoFactorStoreScope> {
+        None
+    }
+
+    /// Access WebAuthn credentials if the store supports them.
+    fn passkeys(&mut self) -> Option<&mut dyn UserPasskeyStoreScope> {
+        None
+    }
+
+    /// Access API keys if the store supports them.
+    fn api_keys(&mut self) -> Option<&mut dyn UserApiKeyStoreScope> {
+        None
+    }
+
+    /// Access links to external identities if the store supports them.
+    fn external_logins(&mut self) -> Option<&mut dyn UserExternalLoginStoreScope> {
+        None
+    }
+
+    /// Access HTTP Digest credentials if the store supports them.
+    fn digest(&mut self) -> Option<&mut dyn UserDigestStoreScope> {
+        None
+    }
+
+    /// Access request signing keys if the store supports them.
+    fn signing_keys(&mut self) -> Option<&mut dyn UserSigningKeyStoreScope> {
+        None
+    }
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -48,4 +139,43 @@ pub enum PasswordHashError {
 
     #[error("an error occurred while trying to hash the password")]
     Other(#[from] Box<dyn Error>),
-}
\ No newline at end of file
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum RenameUserError {
+    #[error("the user store does not support renaming users")]
+    NotSupported,
+
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("a user with the new username already exists")]
+    UsernameExists,
+
+    #[error("an error occurred while trying to rename a user")]
+    Other(#[from] Box<dyn Error>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum EmailError {
+    #[error("the user store does not support email addresses")]
+    NotSupported,
+
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("an error occurred while trying to set the email address")]
+    Other(#[from] Box<dyn Error>),
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SecurityStampError {
+    #[error("the user store does not support security stamps")]
+    NotSupported,
+
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("an error occurred while trying to access the security stamp")]
+    Other(#[from] Box<dyn Error>),
+}
@@ -0,0 +1,83 @@
+use std::error::Error;
+
+use rocket::time::OffsetDateTime;
+
+use crate::User;
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that persist
+/// keys for signing requests.
+#[rocket::async_trait]
+pub trait UserSigningKeyStoreScope: Send + Sync {
+    /// Retrieve all signing keys of a given user.
+    async fn user_signing_keys(
+        &self,
+        user: &User,
+    ) -> Result<Vec<StoredSigningKey>, SigningKeyStoreError>;
+
+    /// Find a signing key and the user it belongs to by its id.
+    async fn find_signing_key(
+        &self,
+        id: &str,
+    ) -> Result<Option<(User, StoredSigningKey)>, SigningKeyStoreError>;
+
+    /// Add a signing key to a given user.
+    async fn add_signing_key(
+        &mut self,
+        user: &User,
+        signing_key: &StoredSigningKey,
+    ) -> Result<(), SigningKeyStoreError>;
+
+    /// Remove the signing key with the given id from a given user.
+    async fn remove_signing_key(
+        &mut self,
+        user: &User,
+        id: &str,
+    ) -> Result<(), SigningKeyStoreError>;
+}
+
+/// A shared secret a client signs requests with. Unlike API keys the secret itself has
+/// to be stored, since signatures are verified by computing them again.
+#[derive(Clone, PartialEq, Eq)]
+pub struct StoredSigningKey {
+    /// The public id of the key, sent along with signatures.
+    pub id: String,
+
+    /// A name describing the client using the key.
+    pub name: String,
+
+    /// The HMAC secret.
+    pub secret: String,
+
+    /// The scopes granted to clients using the key. They become the roles of the user.
+    pub scopes: Vec<String>,
+
+    /// When the key was created.
+    pub created: OffsetDateTime,
+}
+
+impl core::fmt::Debug for StoredSigningKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("StoredSigningKey")
+            .field("id", &self.id)
+            .field("name", &self.name)
+            .field("secret", &"hidden")
+            .field("scopes", &self.scopes)
+            .field("created", &self.created)
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum SigningKeyStoreError {
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("signing key was not found")]
+    SigningKeyNotFound,
+
+    #[error("a signing key with this id already exists")]
+    SigningKeyExists,
+
+    #[error("an error occurred while trying to access signing keys")]
+    Other(#[from] Box<dyn Error>),
+}
@@ -0,0 +1,43 @@
+use std::error::Error;
+
+use crate::{hashers::PasswordHash, User};
+
+/// Extension of [`UserStoreScope`](super::UserStoreScope) for stores that persist
+/// two-factor authentication data.
+#[rocket::async_trait]
+pub trait UserTwoFactorStoreScope: Send + Sync {
+    /// Retrieve the two-factor state for a given user.
+    async fn two_factor_state(&self, user: &User) -> Result<TwoFactorState, TwoFactorStateError>;
+
+    /// Set the two-factor state for a given user.
+    async fn set_two_factor_state(
+        &mut self,
+        user: &User,
+        state: &TwoFactorState,
+    ) -> Result<(), TwoFactorStateError>;
+}
+
+/// Two-factor authentication data of a user.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TwoFactorState {
+    /// Whether the user has to provide a second factor to log in.
+    pub enabled: bool,
+
+    /// The shared TOTP secret.
+    pub totp_secret: Option<Vec<u8>>,
+
+    /// The last TOTP time step that was used, to prevent replay of codes.
+    pub last_totp_step: Option<u64>,
+
+    /// Hashes of the remaining single-use recovery codes.
+    pub recovery_codes: Vec<PasswordHash>,
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TwoFactorStateError {
+    #[error("user was not found")]
+    UserNotFound,
+
+    #[error("an error occurred while trying to access the two-factor state")]
+    Other(#[from] Box<dyn Error>),
+}
@@ -0,0 +1,224 @@
+use data_encoding::BASE64URL_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use rocket::time::{Duration, OffsetDateTime};
+use sha2::Sha256;
+
+use crate::User;
+
+/// Generates and verifies the tokens sent to users, e.g. in email confirmation links.
+///
+/// A token is signed over its purpose, the user and the user's security stamp. Since
+/// the security stamp changes whenever the credentials of the user change, tokens do
+/// not outlive them.
+#[derive(Clone)]
+pub struct TokenProvider {
+    key: Vec<u8>,
+
+    /// How long an email confirmation token is valid.
+    pub email_confirmation_lifetime: Duration,
+
+    /// How long a password reset token is valid.
+    pub password_reset_lifetime: Duration,
+
+    /// How long a login link is valid.
+    pub login_link_lifetime: Duration,
+}
+
+impl TokenProvider {
+    /// Create a token provider signing tokens with the given key. Tokens can be
+    /// verified by any instance using the same key.
+    pub fn new(key: impl Into<Vec<u8>>) -> Self {
+        Self {
+            key: key.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Generate a new random security stamp.
+    pub fn generate_security_stamp(&self) -> String {
+        let mut stamp = [0; 20];
+        OsRng.fill_bytes(&mut stamp);
+
+        BASE64URL_NOPAD.encode(&stamp)
+    }
+
+    /// Generate a token for the given purpose. The data is covered by the signature,
+    /// but not contained in the token.
+    pub fn generate(
+        &self,
+        purpose: &str,
+        user: &User,
+        security_stamp: &str,
+        data: &str,
+        lifetime: Duration,
+    ) -> String {
+        let expires = (OffsetDateTime::now_utc() + lifetime).unix_timestamp();
+
+        let mut token = expires.to_be_bytes().to_vec();
+        token.extend_from_slice(&self.sign(purpose, user, security_stamp, data, expires));
+
+        BASE64URL_NOPAD.encode(&token)
+    }
+
+    /// Verify a token generated for the given purpose, user, security stamp and data.
+    pub fn verify(
+        &self,
+        token: &str,
+        purpose: &str,
+        user: &User,
+        security_stamp: &str,
+        data: &str,
+    ) -> Result<(), TokenError> {
+        let token = BASE64URL_NOPAD
+            .decode(token.trim().as_bytes())
+            .map_err(|_| TokenError::Invalid)?;
+
+        if token.len() <= 8 {
+            return Err(TokenError::Invalid);
+        }
+
+        let (expires, signature) = token.split_at(8);
+        let expires = i64::from_be_bytes(expires.try_into().expect("Length was checked"));
+
+        self.mac(purpose, user, security_stamp, data, expires)
+            .verify_slice(signature)
+            .map_err(|_| TokenError::Invalid)?;
+
+        // Only reported for tokens with a valid signature
+        if OffsetDateTime::now_utc().unix_timestamp() >= expires {
+            return Err(TokenError::Expired);
+        }
+
+        Ok(())
+    }
+
+    fn sign(
+        &self,
+        purpose: &str,
+        user: &User,
+        security_stamp: &str,
+        data: &str,
+        expires: i64,
+    ) -> Vec<u8> {
+        self.mac(purpose, user, security_stamp, data, expires)
+            .finalize()
+            .into_bytes()
+            .to_vec()
+    }
+
+    fn mac(
+        &self,
+        purpose: &str,
+        user: &User,
+        security_stamp: &str,
+        data: &str,
+        expires: i64,
+    ) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.key).expect("HMAC accepts keys of any length");
+
+        // Length prefixes keep the fields from running into each other
+        for field in [purpose, &user.username, security_stamp, data] {
+            mac.update(&(field.len() as u64).to_be_bytes());
+            mac.update(field.as_bytes());
+        }
+        mac.update(&expires.to_be_bytes());
+
+        mac
+    }
+}
+
+impl Default for TokenProvider {
+    /// A token provider with a random key. Tokens become invalid when the application
+    /// restarts, so a fixed key should be configured in production.
+    fn default() -> Self {
+        let mut key = vec![0; 32];
+        OsRng.fill_bytes(&mut key);
+
+        Self {
+            key,
+            email_confirmation_lifetime: Duration::days(1),
+            password_reset_lifetime: Duration::hours(1),
+            login_link_lifetime: Duration::minutes(15),
+        }
+    }
+}
+
+impl core::fmt::Debug for TokenProvider {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_struct("TokenProvider")
+            .field("key", &"hidden")
+            .field(
+                "email_confirmation_lifetime",
+                &self.email_confirmation_lifetime,
+            )
+            .field("password_reset_lifetime", &self.password_reset_lifetime)
+            .field("login_link_lifetime", &self.login_link_lifetime)
+            .finish()
+    }
+}
+
+#[derive(Debug, thiserror::Error, PartialEq, Eq)]
+pub enum TokenError {
+    #[error("the token is invalid")]
+    Invalid,
+
+    #[error("the token has expired")]
+    Expired,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn token_is_bound_to_purpose_user_and_stamp() {
+        let provider = TokenProvider::new("key");
+        let user = User::with_username("user1");
+        let token = provider.generate("confirm", &user, "stamp", "a@b", Duration::hours(1));
+
+        assert_eq!(
+            provider.verify(&token, "confirm", &user, "stamp", "a@b"),
+            Ok(())
+        );
+        assert_eq!(
+            provider.verify(&token, "reset", &user, "stamp", "a@b"),
+            Err(TokenError::Invalid)
+        );
+        assert_eq!(
+            provider.verify(
+                &token,
+                "confirm",
+                &User::with_username("user2"),
+                "stamp",
+                "a@b"
+            ),
+            Err(TokenError::Invalid)
+        );
+        assert_eq!(
+            provider.verify(&token, "confirm", &user, "other", "a@b"),
+            Err(TokenError::Invalid)
+        );
+        assert_eq!(
+            provider.verify(&token, "confirm", &user, "stamp", "c@d"),
+            Err(TokenError::Invalid)
+        );
+        assert_eq!(
+            TokenProvider::new("other").verify(&token, "confirm", &user, "stamp", "a@b"),
+            Err(TokenError::Invalid)
+        );
+    }
+
+    #[test]
+    fn expired_token_is_rejected() {
+        let provider = TokenProvider::new("key");
+        let user = User::with_username("user1");
+        let token = provider.generate("confirm", &user, "stamp", "", Duration::seconds(-1));
+
+        assert_eq!(
+            provider.verify(&token, "confirm", &user, "stamp", ""),
+            Err(TokenError::Expired)
+        );
+    }
+}
@@ -0,0 +1,5 @@
+mod recovery;
+mod totp;
+
+pub use recovery::*;
+pub use totp::*;
@@ -0,0 +1,45 @@
+use rand::{rngs::OsRng, Rng};
+
+/// Characters used in recovery codes. Excludes characters that are easily confused
+/// such as `0`/`o` and `1`/`l`.
+const ALPHABET: &[u8] = b"23456789abcdefghjkmnpqrstuvwxyz";
+
+/// Number of characters on each side of the dash of a recovery code.
+const GROUP_LENGTH: usize = 5;
+
+/// Generate a batch of random recovery codes of the form `xxxxx-xxxxx`.
+pub fn generate_recovery_codes(count: usize) -> Vec<String> {
+    (0..count).map(|_| generate_recovery_code()).collect()
+}
+
+fn generate_recovery_code() -> String {
+    let group = || {
+        (0..GROUP_LENGTH)
+            .map(|_| ALPHABET[OsRng.gen_range(0..ALPHABET.len())] as char)
+            .collect::<String>()
+    };
+
+    format!("{}-{}", group(), group())
+}
+
+/// Normalize a recovery code as entered by a user, ignoring case, whitespace and dashes.
+pub fn normalize_recovery_code(code: &str) -> String {
+    code.chars()
+        .filter(|c| !c.is_whitespace() && *c != '-')
+        .collect::<String>()
+        .to_lowercase()
+}
+
+#[cfg(test)]
+mod test {
+    use super::{generate_recovery_codes, normalize_recovery_code};
+
+    #[test]
+    fn test_generate() {
+        let codes = generate_recovery_codes(10);
+
+        assert_eq!(codes.len(), 10);
+        assert!(codes.iter().all(|c| c.len() == 11 && &c[5..6] == "-"));
+        assert_eq!(normalize_recovery_code(" AB3DE-fgh45 "), "ab3defgh45");
+    }
+}
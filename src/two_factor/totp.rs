@@ -0,0 +1,290 @@
+use data_encoding::BASE32_NOPAD;
+use hmac::{Hmac, Mac};
+use rand::{rngs::OsRng, RngCore};
+use rocket::time::OffsetDateTime;
+use subtle::ConstantTimeEq;
+
+use crate::util::percent_encode;
+
+/// Generates and verifies time-based one-time passwords as specified in RFC 6238.
+#[derive(Debug, Clone)]
+pub struct Totp {
+    /// The issuer shown in authenticator apps, usually the name of the application.
+    pub issuer: String,
+
+    /// Number of digits of a code, from 6 to 8.
+    digits: u32,
+
+    /// Seconds a code is valid for.
+    pub period: u64,
+
+    /// Number of periods before and after the current one that are also accepted,
+    /// to allow for clock drift.
+    pub skew: u64,
+
+    /// The HMAC algorithm used to derive codes.
+    pub algorithm: TotpAlgorithm,
+
+    /// Length of generated secrets in bytes.
+    pub secret_length: usize,
+}
+
+/// HMAC algorithms supported for TOTP.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TotpAlgorithm {
+    Sha1,
+    Sha256,
+    Sha512,
+}
+
+impl TotpAlgorithm {
+    fn name(&self) -> &'static str {
+        match self {
+            Self::Sha1 => "SHA1",
+            Self::Sha256 => "SHA256",
+            Self::Sha512 => "SHA512",
+        }
+    }
+}
+
+/// A shared TOTP secret.
+#[derive(Clone, PartialEq, Eq)]
+pub struct TotpSecret(Vec<u8>);
+
+impl TotpSecret {
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// The secret in the base32 encoding used by authenticator apps.
+    pub fn to_base32(&self) -> String {
+        BASE32_NOPAD.encode(&self.0)
+    }
+
+    /// Parse a base32 encoded secret. Padding and whitespace are ignored.
+    pub fn from_base32(encoded: &str) -> Option<Self> {
+        let encoded = encoded
+            .chars()
+            .filter(|c| !c.is_whitespace() && *c != '=')
+            .collect::<String>()
+            .to_ascii_uppercase();
+
+        BASE32_NOPAD.decode(encoded.as_bytes()).ok().map(Self)
+    }
+}
+
+impl From<Vec<u8>> for TotpSecret {
+    fn from(bytes: Vec<u8>) -> Self {
+        Self(bytes)
+    }
+}
+
+impl core::fmt::Debug for TotpSecret {
+    fn fmt(&self, f: &mut core::fmt::Formatter<'_>) -> core::fmt::Result {
+        f.debug_tuple("TotpSecret").field(&"hidden").finish()
+    }
+}
+
+impl Totp {
+    pub fn new(issuer: impl Into<String>) -> Self {
+        Self {
+            issuer: issuer.into(),
+            ..Self::default()
+        }
+    }
+
+    /// Use codes with the given number of digits. RFC 4226 allows 6 to 8 digits.
+    pub fn with_digits(mut self, digits: u32) -> Result<Self, TotpError> {
+        if !(6..=8).contains(&digits) {
+            return Err(TotpError::InvalidDigits(digits));
+        }
+
+        self.digits = digits;
+        Ok(self)
+    }
+
+    /// Number of digits of a code.
+    pub fn digits(&self) -> u32 {
+        self.digits
+    }
+
+    /// Generate a new random secret.
+    pub fn generate_secret(&self) -> TotpSecret {
+        let mut secret = vec![0; self.secret_length];
+        OsRng.fill_bytes(&mut secret);
+
+        TotpSecret(secret)
+    }
+
+    /// The time step containing the given point in time.
+    pub fn step_at(&self, time: OffsetDateTime) -> u64 {
+        (time.unix_timestamp().max(0) as u64) / self.period.max(1)
+    }
+
+    /// The code for the given time step.
+    pub fn code_at_step(&self, secret: &TotpSecret, step: u64) -> String {
+        let digest = match self.algorithm {
+            TotpAlgorithm::Sha1 => hmac::<Hmac<sha1::Sha1>>(secret, step),
+            TotpAlgorithm::Sha256 => hmac::<Hmac<sha2::Sha256>>(secret, step),
+            TotpAlgorithm::Sha512 => hmac::<Hmac<sha2::Sha512>>(secret, step),
+        };
+
+        // Dynamic truncation as specified in RFC 4226 section 5.3
+        let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+        let binary = u32::from_be_bytes([
+            digest[offset] & 0x7f,
+            digest[offset + 1],
+            digest[offset + 2],
+            digest[offset + 3],
+        ]);
+
+        let code = binary as u64 % 10u64.pow(self.digits);
+        format!("{:0width$}", code, width = self.digits as usize)
+    }
+
+    /// The code for the given point in time.
+    pub fn code_at(&self, secret: &TotpSecret, time: OffsetDateTime) -> String {
+        self.code_at_step(secret, self.step_at(time))
+    }
+
+    /// Verify a code at the given point in time. Steps up to and including
+    /// `last_used_step` are rejected so that a code cannot be replayed. Returns the
+    /// matched step, which should be stored as the new `last_used_step`.
+    pub fn verify(
+        &self,
+        secret: &TotpSecret,
+        code: &str,
+        time: OffsetDateTime,
+        last_used_step: Option<u64>,
+    ) -> Option<u64> {
+        let code = code.trim();
+        let current = self.step_at(time);
+
+        let first = current.saturating_sub(self.skew);
+        let first = match last_used_step {
+            Some(last) => first.max(last.saturating_add(1)),
+            None => first,
+        };
+
+        (first..=current.saturating_add(self.skew)).find(|step| {
+            let expected = self.code_at_step(secret, *step);
+            bool::from(expected.as_bytes().ct_eq(code.as_bytes()))
+        })
+    }
+
+    /// The `otpauth://` URI to provision an authenticator app, usually shown as a QR code.
+    pub fn provisioning_uri(&self, secret: &TotpSecret, account_name: &str) -> String {
+        format!(
+            "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm={}&digits={}&period={}",
+            percent_encode(&self.issuer),
+            percent_encode(account_name),
+            secret.to_base32(),
+            percent_encode(&self.issuer),
+            self.algorithm.name(),
+            self.digits,
+            self.period,
+        )
+    }
+}
+
+impl Default for Totp {
+    fn default() -> Self {
+        Self {
+            issuer: "rocket-identity".to_owned(),
+            digits: 6,
+            period: 30,
+            skew: 1,
+            algorithm: TotpAlgorithm::Sha1,
+            secret_length: 20,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TotpError {
+    #[error("TOTP codes must have 6 to 8 digits, not {0}")]
+    InvalidDigits(u32),
+}
+
+fn hmac<M: Mac + hmac::digest::KeyInit>(secret: &TotpSecret, step: u64) -> Vec<u8> {
+    let mut mac = <M as hmac::digest::KeyInit>::new_from_slice(secret.as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(&step.to_be_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+#[cfg(test)]
+mod test {
+    use rocket::time::OffsetDateTime;
+
+    use super::{Totp, TotpAlgorithm, TotpSecret};
+
+    fn rfc_totp(algorithm: TotpAlgorithm) -> Totp {
+        Totp {
+            algorithm,
+            ..Totp::default()
+        }
+        .with_digits(8)
+        .unwrap()
+    }
+
+    fn time(timestamp: i64) -> OffsetDateTime {
+        OffsetDateTime::from_unix_timestamp(timestamp).unwrap()
+    }
+
+    #[test]
+    fn test_rfc6238_vectors() {
+        let sha1 = TotpSecret::from(b"12345678901234567890".to_vec());
+        let sha256 = TotpSecret::from(b"12345678901234567890123456789012".to_vec());
+        let sha512 = TotpSecret::from(
+            b"1234567890123456789012345678901234567890123456789012345678901234".to_vec(),
+        );
+
+        let totp = rfc_totp(TotpAlgorithm::Sha1);
+        assert_eq!(totp.code_at(&sha1, time(59)), "94287082");
+        assert_eq!(totp.code_at(&sha1, time(1111111109)), "07081804");
+        assert_eq!(totp.code_at(&sha1, time(20000000000)), "65353130");
+
+        let totp = rfc_totp(TotpAlgorithm::Sha256);
+        assert_eq!(totp.code_at(&sha256, time(59)), "46119246");
+
+        let totp = rfc_totp(TotpAlgorithm::Sha512);
+        assert_eq!(totp.code_at(&sha512, time(59)), "90693936");
+    }
+
+    #[test]
+    fn test_verify_window_and_replay() {
+        let totp = Totp::default();
+        let secret = totp.generate_secret();
+        let now = time(1_700_000_000);
+
+        let previous = totp.code_at(&secret, time(1_700_000_000 - 30));
+        let step = totp.verify(&secret, &previous, now, None);
+        assert_eq!(step, Some(totp.step_at(now) - 1));
+
+        // The same code cannot be used twice
+        assert_eq!(totp.verify(&secret, &previous, now, step), None);
+
+        let outdated = totp.code_at(&secret, time(1_700_000_000 - 90));
+        assert_eq!(totp.verify(&secret, &outdated, now, None), None);
+    }
+
+    #[test]
+    fn test_provisioning_uri() {
+        let totp = Totp::new("My App");
+        let secret = TotpSecret::from(b"12345678901234567890".to_vec());
+
+        assert_eq!(
+            totp.provisioning_uri(&secret, "alice@example.com"),
+            "otpauth://totp/My%20App:alice%40example.com?secret=GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ&issuer=My%20App&algorithm=SHA1&digits=6&period=30"
+        );
+        assert_eq!(
+            TotpSecret::from_base32("gezd gnbv gy3t qojq gezd gnbv gy3t qojq"),
+            Some(secret)
+        );
+    }
+}
@@ -1,3 +1,5 @@
+use std::collections::HashMap;
+
 pub type Result<T> = std::result::Result<T, BoxError>;
 
 pub type BoxError = Box<dyn std::error::Error>;
@@ -12,3 +14,76 @@ pub trait BoxableError: std::error::Error {
 }
 
 impl<T: std::error::Error> BoxableError for T {}
+
+/// Percent-encode everything except unreserved characters (RFC 3986 section 2.3).
+pub(crate) fn percent_encode(value: &str) -> String {
+    value
+        .bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (b as char).to_string()
+            }
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// The credentials of an `Authorization` header with the given scheme. The scheme
+/// token is case insensitive.
+pub(crate) fn auth_credentials<'a>(header: &'a str, scheme: &str) -> Option<&'a str> {
+    let header = header.trim();
+    let (token, credentials) = header
+        .split_once(|c: char| c.is_ascii_whitespace())
+        .unwrap_or((header, ""));
+
+    token
+        .eq_ignore_ascii_case(scheme)
+        .then(|| credentials.trim())
+}
+
+/// Parse the comma separated `name=value` parameters of an `Authorization` header.
+/// Values may be quoted strings with backslash escapes. Names are lowercased.
+pub(crate) fn parse_auth_params(params: &str) -> Option<HashMap<String, String>> {
+    let mut result = HashMap::new();
+    let mut rest = params.trim_start();
+
+    while !rest.is_empty() {
+        let (name, after_name) = rest.split_once('=')?;
+        let name = name.trim().to_ascii_lowercase();
+        let after_name = after_name.trim_start();
+
+        let (value, after_value) = match after_name.strip_prefix('"') {
+            Some(quoted) => {
+                let mut value = String::new();
+                let mut chars = quoted.char_indices();
+
+                let end = loop {
+                    match chars.next()? {
+                        (i, '"') => break i + 1,
+                        (_, '\\') => value.push(chars.next()?.1),
+                        (_, c) => value.push(c),
+                    }
+                };
+
+                (value, &quoted[end..])
+            }
+            None => {
+                let end = after_name.find(',').unwrap_or(after_name.len());
+                (after_name[..end].trim().to_owned(), &after_name[end..])
+            }
+        };
+
+        if name.is_empty() || result.insert(name, value).is_some() {
+            return None;
+        }
+
+        let after_value = after_value.trim_start();
+        rest = match after_value.strip_prefix(',') {
+            Some(next) => next.trim_start(),
+            None if after_value.is_empty() => after_value,
+            None => return None,
+        };
+    }
+
+    Some(result)
+}
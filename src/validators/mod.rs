@@ -0,0 +1,7 @@
+mod password;
+mod policy;
+mod username;
+
+pub use password::*;
+pub use policy::*;
+pub use username::*;
@@ -0,0 +1,35 @@
+use crate::User;
+
+/// Validates passwords before they are hashed and stored.
+pub trait PasswordValidator: Send + Sync + core::fmt::Debug + 'static {
+    /// Check the password of the given user. Returns all violated rules on failure.
+    fn validate(&self, user: &User, password: &str) -> Result<(), Vec<PasswordViolation>>;
+}
+
+/// A single rule a password did not satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum PasswordViolation {
+    #[error("password must be at least {0} characters long")]
+    TooShort(usize),
+
+    #[error("password must be at most {0} characters long")]
+    TooLong(usize),
+
+    #[error("password must contain a lowercase letter")]
+    MissingLowercase,
+
+    #[error("password must contain an uppercase letter")]
+    MissingUppercase,
+
+    #[error("password must contain a digit")]
+    MissingDigit,
+
+    #[error("password must contain a character that is neither a letter nor a digit")]
+    MissingSymbol,
+
+    #[error("password must not contain the username")]
+    ContainsUsername,
+
+    #[error("password is too common")]
+    Common,
+}
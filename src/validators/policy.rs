@@ -0,0 +1,194 @@
+use std::{collections::HashSet, sync::OnceLock};
+
+use crate::{
+    validators::{PasswordValidator, PasswordViolation},
+    User,
+};
+
+/// Embedded list of frequently used passwords, one per line in lowercase.
+const COMMON_PASSWORDS: &str = include_str!("common_passwords.txt");
+
+/// A configurable set of built-in password rules.
+///
+/// The default policy only rejects empty and excessively long passwords. Use
+/// [`PasswordPolicy::recommended`] for a stricter starting point.
+#[derive(Debug, Clone)]
+pub struct PasswordPolicy {
+    /// Minimum number of characters.
+    pub min_length: usize,
+
+    /// Maximum number of characters. Protects the hasher from oversized input.
+    pub max_length: usize,
+
+    /// Require at least one lowercase letter.
+    pub require_lowercase: bool,
+
+    /// Require at least one uppercase letter.
+    pub require_uppercase: bool,
+
+    /// Require at least one digit.
+    pub require_digit: bool,
+
+    /// Require at least one character that is neither a letter nor a digit.
+    pub require_symbol: bool,
+
+    /// Reject passwords containing the username (case insensitive).
+    pub reject_username: bool,
+
+    /// Reject passwords found in the embedded list of common passwords.
+    pub reject_common: bool,
+}
+
+impl PasswordPolicy {
+    pub fn new() -> Self {
+        Self {
+            min_length: 1,
+            max_length: 1024,
+            require_lowercase: false,
+            require_uppercase: false,
+            require_digit: false,
+            require_symbol: false,
+            reject_username: false,
+            reject_common: false,
+        }
+    }
+
+    /// A policy following current guidance: long passwords that are neither common
+    /// nor derived from the username, without composition rules.
+    pub fn recommended() -> Self {
+        Self {
+            min_length: 8,
+            reject_username: true,
+            reject_common: true,
+            ..Self::new()
+        }
+    }
+
+    fn is_common(password: &str) -> bool {
+        static LIST: OnceLock<HashSet<&'static str>> = OnceLock::new();
+
+        let list = LIST.get_or_init(|| {
+            COMMON_PASSWORDS
+                .lines()
+                .map(str::trim)
+                .filter(|l| !l.is_empty())
+                .collect()
+        });
+
+        list.contains(password.to_lowercase().as_str())
+    }
+}
+
+impl Default for PasswordPolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PasswordValidator for PasswordPolicy {
+    fn validate(&self, user: &User, password: &str) -> Result<(), Vec<PasswordViolation>> {
+        let mut violations = Vec::new();
+        let length = password.chars().count();
+
+        if length < self.min_length {
+            violations.push(PasswordViolation::TooShort(self.min_length));
+        }
+
+        if length > self.max_length {
+            violations.push(PasswordViolation::TooLong(self.max_length));
+        }
+
+        if self.require_lowercase && !password.chars().any(char::is_lowercase) {
+            violations.push(PasswordViolation::MissingLowercase);
+        }
+
+        if self.require_uppercase && !password.chars().any(char::is_uppercase) {
+            violations.push(PasswordViolation::MissingUppercase);
+        }
+
+        if self.require_digit && !password.chars().any(|c| c.is_ascii_digit()) {
+            violations.push(PasswordViolation::MissingDigit);
+        }
+
+        if self.require_symbol && password.chars().all(char::is_alphanumeric) {
+            violations.push(PasswordViolation::MissingSymbol);
+        }
+
+        if self.reject_username
+            && !user.username.is_empty()
+            && password
+                .to_lowercase()
+                .contains(&user.username.to_lowercase())
+        {
+            violations.push(PasswordViolation::ContainsUsername);
+        }
+
+        if self.reject_common && Self::is_common(password) {
+            violations.push(PasswordViolation::Common);
+        }
+
+        if violations.is_empty() {
+            Ok(())
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        validators::{PasswordPolicy, PasswordValidator, PasswordViolation},
+        User,
+    };
+
+    #[test]
+    fn test_default_rejects_empty() {
+        let policy = PasswordPolicy::default();
+        let user = User::with_username("user1");
+
+        assert_eq!(
+            policy.validate(&user, ""),
+            Err(vec![PasswordViolation::TooShort(1)])
+        );
+        assert_eq!(policy.validate(&user, "pass1"), Ok(()));
+    }
+
+    #[test]
+    fn test_recommended() {
+        let policy = PasswordPolicy::recommended();
+        let user = User::with_username("alice");
+
+        assert_eq!(
+            policy.validate(&user, "password"),
+            Err(vec![PasswordViolation::Common])
+        );
+        assert_eq!(
+            policy.validate(&user, "my-name-is-Alice"),
+            Err(vec![PasswordViolation::ContainsUsername])
+        );
+        assert_eq!(policy.validate(&user, "correct horse battery"), Ok(()));
+    }
+
+    #[test]
+    fn test_character_classes() {
+        let policy = PasswordPolicy {
+            require_lowercase: true,
+            require_uppercase: true,
+            require_digit: true,
+            require_symbol: true,
+            ..PasswordPolicy::new()
+        };
+        let user = User::with_username("user1");
+
+        assert_eq!(
+            policy.validate(&user, "abc"),
+            Err(vec![
+                PasswordViolation::MissingUppercase,
+                PasswordViolation::MissingDigit,
+                PasswordViolation::MissingSymbol,
+            ])
+        );
+        assert_eq!(policy.validate(&user, "aB3$"), Ok(()));
+    }
+}
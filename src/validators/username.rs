@@ -0,0 +1,201 @@
+use std::collections::HashSet;
+
+use unicode_normalization::UnicodeNormalization;
+
+/// Rules for normalizing and validating usernames.
+///
+/// Usernames are validated when users are added, and normalized before they are
+/// stored and before they are looked up, so that e.g. `"Alice"` and `"alice "` refer
+/// to the same user. Users stored before the policy applied can be migrated with
+/// [`UserRepository::normalize_usernames`](crate::UserRepository::normalize_usernames).
+#[derive(Debug, Clone)]
+pub struct UsernamePolicy {
+    /// Remove leading and trailing whitespace.
+    pub trim_whitespace: bool,
+
+    /// Apply Unicode NFKC normalization.
+    pub unicode_normalization: bool,
+
+    /// Fold usernames to lowercase so that lookups are case insensitive.
+    pub case_insensitive: bool,
+
+    /// The set of allowed characters. If `None`, all characters except control
+    /// characters are allowed. Usernames with colons cannot be registered in any case,
+    /// since Basic authentication separates the username from the password at the
+    /// first colon.
+    pub allowed_characters: Option<HashSet<char>>,
+
+    /// Minimum number of characters after normalization.
+    pub min_length: usize,
+
+    /// Maximum number of characters after normalization.
+    pub max_length: usize,
+
+    /// Usernames that cannot be registered. Compared after normalization.
+    pub reserved_names: HashSet<String>,
+}
+
+impl UsernamePolicy {
+    pub fn new() -> Self {
+        Self {
+            trim_whitespace: true,
+            unicode_normalization: true,
+            case_insensitive: true,
+            allowed_characters: None,
+            min_length: 1,
+            max_length: 256,
+            reserved_names: HashSet::new(),
+        }
+    }
+
+    /// Restrict usernames to ASCII letters, digits and `-._@+`.
+    pub fn with_ascii_characters(mut self) -> Self {
+        self.allowed_characters = Some(
+            ('a'..='z')
+                .chain('A'..='Z')
+                .chain('0'..='9')
+                .chain("-._@+".chars())
+                .collect(),
+        );
+        self
+    }
+
+    /// Add names that cannot be registered.
+    pub fn with_reserved_names<I, S>(mut self, names: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let names = names
+            .into_iter()
+            .map(|n| self.normalize(n.as_ref()))
+            .collect::<Vec<_>>();
+        self.reserved_names.extend(names);
+        self
+    }
+
+    /// Normalize a username without validating it.
+    pub fn normalize(&self, username: &str) -> String {
+        let username = if self.trim_whitespace {
+            username.trim()
+        } else {
+            username
+        };
+
+        let username = if self.unicode_normalization {
+            username.nfkc().collect::<String>()
+        } else {
+            username.to_owned()
+        };
+
+        if self.case_insensitive {
+            username.to_lowercase()
+        } else {
+            username
+        }
+    }
+
+    /// Normalize and validate a username. Returns the normalized username on success
+    /// and all violated rules on failure.
+    pub fn validate(&self, username: &str) -> Result<String, Vec<UsernameViolation>> {
+        let username = self.normalize(username);
+        let mut violations = Vec::new();
+        let length = username.chars().count();
+
+        if length < self.min_length {
+            violations.push(UsernameViolation::TooShort(self.min_length));
+        }
+
+        if length > self.max_length {
+            violations.push(UsernameViolation::TooLong(self.max_length));
+        }
+
+        let invalid = username.chars().find(|c| match &self.allowed_characters {
+            Some(allowed) => !allowed.contains(c),
+            None => c.is_control(),
+        });
+
+        if let Some(c) = invalid {
+            violations.push(UsernameViolation::InvalidCharacter(c));
+        }
+
+        if self.reserved_names.contains(&username) {
+            violations.push(UsernameViolation::Reserved);
+        }
+
+        if violations.is_empty() {
+            Ok(username)
+        } else {
+            Err(violations)
+        }
+    }
+}
+
+impl Default for UsernamePolicy {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// A single rule a username did not satisfy.
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum UsernameViolation {
+    #[error("username must be at least {0} characters long")]
+    TooShort(usize),
+
+    #[error("username must be at most {0} characters long")]
+    TooLong(usize),
+
+    #[error("username contains the invalid character {0:?}")]
+    InvalidCharacter(char),
+
+    #[error("username is reserved")]
+    Reserved,
+}
+
+#[cfg(test)]
+mod test {
+    use super::{UsernamePolicy, UsernameViolation};
+
+    #[test]
+    fn test_normalization() {
+        let policy = UsernamePolicy::default();
+
+        assert_eq!(policy.normalize("Alice"), "alice");
+        assert_eq!(policy.normalize("alice "), "alice");
+        assert_eq!(policy.normalize("ＡＬＩＣＥ"), "alice");
+    }
+
+    #[test]
+    fn test_validation() {
+        let policy = UsernamePolicy::default()
+            .with_ascii_characters()
+            .with_reserved_names(["Admin"]);
+
+        assert_eq!(policy.validate(" Bob "), Ok("bob".to_owned()));
+        assert_eq!(
+            policy.validate("  "),
+            Err(vec![UsernameViolation::TooShort(1)])
+        );
+        assert_eq!(
+            policy.validate("b o b"),
+            Err(vec![UsernameViolation::InvalidCharacter(' ')])
+        );
+        assert_eq!(
+            policy.validate("ADMIN"),
+            Err(vec![UsernameViolation::Reserved])
+        );
+    }
+
+    #[test]
+    fn test_colons_follow_the_allowed_characters() {
+        let mut policy = UsernamePolicy::default();
+        assert_eq!(policy.validate("bob:smith"), Ok("bob:smith".to_owned()));
+
+        policy.allowed_characters = Some(['b', 'o'].into_iter().collect());
+        assert_eq!(
+            policy.validate("bob:"),
+            Err(vec![UsernameViolation::InvalidCharacter(':')])
+        );
+    }
+}
@@ -0,0 +1,121 @@
+use ciborium::value::Value;
+use p256::{elliptic_curve::sec1::EncodedPoint, NistP256};
+
+use super::WebAuthnError;
+
+/// User present.
+pub(crate) const FLAG_UP: u8 = 0x01;
+/// User verified.
+pub(crate) const FLAG_UV: u8 = 0x04;
+/// Attested credential data included.
+pub(crate) const FLAG_AT: u8 = 0x40;
+
+/// COSE algorithm identifier of ECDSA with P-256 and SHA-256.
+pub(crate) const COSE_ALG_ES256: i64 = -7;
+
+/// The authenticator data structure (WebAuthn section 6.1).
+#[derive(Debug)]
+pub(crate) struct AuthenticatorData {
+    pub rp_id_hash: [u8; 32],
+    pub flags: u8,
+    pub sign_count: u32,
+    pub attested_credential: Option<AttestedCredential>,
+}
+
+#[derive(Debug)]
+pub(crate) struct AttestedCredential {
+    pub credential_id: Vec<u8>,
+    /// The SEC1 encoded P-256 public key.
+    pub public_key: Vec<u8>,
+}
+
+impl AuthenticatorData {
+    pub fn parse(data: &[u8]) -> Result<Self, WebAuthnError> {
+        if data.len() < 37 {
+            return Err(WebAuthnError::Malformed("authenticator data is too short"));
+        }
+
+        let mut rp_id_hash = [0; 32];
+        rp_id_hash.copy_from_slice(&data[..32]);
+        let flags = data[32];
+        let sign_count = u32::from_be_bytes([data[33], data[34], data[35], data[36]]);
+
+        let attested_credential = if flags & FLAG_AT != 0 {
+            Some(parse_attested_credential(&data[37..])?)
+        } else {
+            None
+        };
+
+        Ok(Self {
+            rp_id_hash,
+            flags,
+            sign_count,
+            attested_credential,
+        })
+    }
+}
+
+fn parse_attested_credential(data: &[u8]) -> Result<AttestedCredential, WebAuthnError> {
+    // 16 bytes AAGUID followed by the length of the credential id
+    if data.len() < 18 {
+        return Err(WebAuthnError::Malformed(
+            "attested credential data is too short",
+        ));
+    }
+
+    let id_length = u16::from_be_bytes([data[16], data[17]]) as usize;
+    let Some(credential_id) = data.get(18..18 + id_length) else {
+        return Err(WebAuthnError::Malformed("credential id is truncated"));
+    };
+
+    // The public key is followed by extensions if the ED flag is set, so only
+    // the first CBOR item is read
+    let mut rest = &data[18 + id_length..];
+    let key: Value = ciborium::de::from_reader(&mut rest)
+        .map_err(|_| WebAuthnError::Malformed("credential public key is not valid CBOR"))?;
+
+    Ok(AttestedCredential {
+        credential_id: credential_id.to_vec(),
+        public_key: parse_cose_key(&key)?,
+    })
+}
+
+/// Convert a COSE_Key (RFC 8152 section 7) to a SEC1 encoded point. Only ES256 keys
+/// are supported.
+fn parse_cose_key(key: &Value) -> Result<Vec<u8>, WebAuthnError> {
+    let Value::Map(entries) = key else {
+        return Err(WebAuthnError::Malformed(
+            "credential public key is not a map",
+        ));
+    };
+
+    let get = |label: i64| {
+        entries.iter().find_map(|(k, v)| match k {
+            Value::Integer(i) if i128::from(*i) == label as i128 => Some(v),
+            _ => None,
+        })
+    };
+    let int = |label: i64| match get(label) {
+        Some(Value::Integer(i)) => Some(i128::from(*i)),
+        _ => None,
+    };
+    let bytes = |label: i64| match get(label) {
+        Some(Value::Bytes(b)) if b.len() == 32 => Some(b.as_slice()),
+        _ => None,
+    };
+
+    // kty: EC2, alg: ES256, crv: P-256
+    if int(1) != Some(2) || int(3) != Some(COSE_ALG_ES256 as i128) || int(-1) != Some(1) {
+        return Err(WebAuthnError::UnsupportedAlgorithm);
+    }
+
+    let (Some(x), Some(y)) = (bytes(-2), bytes(-3)) else {
+        return Err(WebAuthnError::Malformed(
+            "credential public key is missing coordinates",
+        ));
+    };
+
+    let point = EncodedPoint::<NistP256>::from_affine_coordinates(x.into(), y.into(), false);
+
+    Ok(point.as_bytes().to_vec())
+}
@@ -0,0 +1,6 @@
+mod authenticator_data;
+mod relying_party;
+mod types;
+
+pub use relying_party::*;
+pub use types::*;
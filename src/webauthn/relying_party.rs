@@ -0,0 +1,390 @@
+use ciborium::value::Value;
+use data_encoding::BASE64URL_NOPAD;
+use p256::ecdsa::{signature::Verifier, Signature, VerifyingKey};
+use rand::{rngs::OsRng, RngCore};
+use rocket::{
+    serde::{json::serde_json, Deserialize},
+    time::{Duration, OffsetDateTime},
+};
+use sha2::{Digest, Sha256};
+
+use crate::stores::Passkey;
+
+use super::{
+    authenticator_data::{AuthenticatorData, COSE_ALG_ES256, FLAG_UP, FLAG_UV},
+    AssertionCredential, AuthenticatorSelection, Ceremony, CreationOptions, CredentialDescriptor,
+    CredentialParameters, PasskeyChallenge, RegistrationCredential, RelyingPartyEntity,
+    RequestOptions, UserEntity,
+};
+
+/// The relying party of WebAuthn ceremonies. Creates challenges and verifies the
+/// responses of authenticators.
+///
+/// Only ES256 credentials and the `none` attestation format are supported.
+#[derive(Debug, Clone)]
+pub struct WebAuthn {
+    /// The domain credentials are scoped to, e.g. `example.com`.
+    pub rp_id: String,
+
+    /// The name of the application shown by authenticators.
+    pub rp_name: String,
+
+    /// The origin pages performing ceremonies are served from, e.g.
+    /// `https://example.com`.
+    pub origin: String,
+
+    /// How long the user has to complete a ceremony.
+    pub timeout: Duration,
+
+    /// Whether authenticators have to verify the user, e.g. with a PIN or biometrics.
+    pub require_user_verification: bool,
+}
+
+/// The parts of the client data (WebAuthn section 5.8.1) that are verified.
+#[derive(Debug, Deserialize)]
+#[serde(crate = "rocket::serde", rename_all = "camelCase")]
+struct ClientData {
+    #[serde(rename = "type")]
+    kind: String,
+    challenge: String,
+    origin: String,
+    #[serde(default)]
+    cross_origin: bool,
+}
+
+impl WebAuthn {
+    pub fn new(
+        rp_id: impl Into<String>,
+        rp_name: impl Into<String>,
+        origin: impl Into<String>,
+    ) -> Self {
+        Self {
+            rp_id: rp_id.into(),
+            rp_name: rp_name.into(),
+            origin: origin.into(),
+            ..Self::default()
+        }
+    }
+
+    /// The user handle of a user. It is derived from the username so that it does not
+    /// have to be stored.
+    pub fn user_handle(&self, username: &str) -> Vec<u8> {
+        Sha256::digest(username.as_bytes()).to_vec()
+    }
+
+    /// Start registering a new passkey for a user. Existing passkeys are excluded so
+    /// that an authenticator is not registered twice.
+    pub fn start_registration(
+        &self,
+        username: &str,
+        existing: &[Passkey],
+    ) -> (CreationOptions, PasskeyChallenge) {
+        let challenge = self.create_challenge(Ceremony::Registration, Some(username));
+
+        let options = CreationOptions {
+            rp: RelyingPartyEntity {
+                id: self.rp_id.clone(),
+                name: self.rp_name.clone(),
+            },
+            user: UserEntity {
+                id: BASE64URL_NOPAD.encode(&self.user_handle(username)),
+                name: username.to_owned(),
+                display_name: username.to_owned(),
+            },
+            challenge: challenge.challenge.clone(),
+            pub_key_cred_params: vec![CredentialParameters {
+                kind: "public-key".to_owned(),
+                alg: COSE_ALG_ES256,
+            }],
+            timeout: self.timeout_millis(),
+            exclude_credentials: descriptors(existing),
+            authenticator_selection: AuthenticatorSelection {
+                resident_key: "preferred".to_owned(),
+                user_verification: self.user_verification().to_owned(),
+            },
+            attestation: "none".to_owned(),
+        };
+
+        (options, challenge)
+    }
+
+    /// Verify the response of the authenticator to a registration challenge and return
+    /// the new passkey.
+    pub fn finish_registration(
+        &self,
+        challenge: &PasskeyChallenge,
+        credential: &RegistrationCredential,
+    ) -> Result<Passkey, WebAuthnError> {
+        self.verify_client_data(
+            challenge,
+            Ceremony::Registration,
+            &credential.response.client_data_json,
+        )?;
+
+        let attestation_object = decode(&credential.response.attestation_object)?;
+        let Value::Map(attestation) = ciborium::de::from_reader(attestation_object.as_slice())
+            .map_err(|_| WebAuthnError::Malformed("attestation object is not valid CBOR"))?
+        else {
+            return Err(WebAuthnError::Malformed("attestation object is not a map"));
+        };
+
+        let get = |name: &str| {
+            attestation
+                .iter()
+                .find(|(k, _)| k.as_text() == Some(name))
+                .map(|(_, v)| v)
+        };
+
+        match get("fmt").and_then(Value::as_text) {
+            Some("none") => {}
+            Some(format) => return Err(WebAuthnError::UnsupportedAttestation(format.to_owned())),
+            None => return Err(WebAuthnError::Malformed("attestation format is missing")),
+        }
+
+        let Some(Value::Bytes(auth_data)) = get("authData") else {
+            return Err(WebAuthnError::Malformed("authenticator data is missing"));
+        };
+
+        let auth_data = AuthenticatorData::parse(auth_data)?;
+        self.verify_authenticator_data(&auth_data)?;
+
+        let Some(attested) = auth_data.attested_credential else {
+            return Err(WebAuthnError::Malformed(
+                "attested credential data is missing",
+            ));
+        };
+
+        if attested.credential_id != decode(&credential.raw_id)? {
+            return Err(WebAuthnError::Malformed("credential id does not match"));
+        }
+
+        Ok(Passkey {
+            credential_id: attested.credential_id,
+            public_key: attested.public_key,
+            sign_count: auth_data.sign_count,
+        })
+    }
+
+    /// Start signing in with a passkey. If a username is given, only passkeys of that
+    /// user are accepted, otherwise the authenticator chooses a discoverable credential.
+    pub fn start_authentication(
+        &self,
+        username: Option<&str>,
+        allowed: &[Passkey],
+    ) -> (RequestOptions, PasskeyChallenge) {
+        let challenge = self.create_challenge(Ceremony::Authentication, username);
+
+        let options = RequestOptions {
+            challenge: challenge.challenge.clone(),
+            timeout: self.timeout_millis(),
+            rp_id: self.rp_id.clone(),
+            allow_credentials: descriptors(allowed),
+            user_verification: self.user_verification().to_owned(),
+        };
+
+        (options, challenge)
+    }
+
+    /// Verify the response of the authenticator to an authentication challenge, using
+    /// the stored passkey of the user. Returns the new signature counter, which should
+    /// be stored to detect cloned authenticators.
+    pub fn verify_assertion(
+        &self,
+        challenge: &PasskeyChallenge,
+        username: &str,
+        passkey: &Passkey,
+        credential: &AssertionCredential,
+    ) -> Result<u32, WebAuthnError> {
+        let client_data_json = self.verify_client_data(
+            challenge,
+            Ceremony::Authentication,
+            &credential.response.client_data_json,
+        )?;
+
+        if challenge
+            .username
+            .as_ref()
+            .is_some_and(|expected| expected != username)
+        {
+            return Err(WebAuthnError::UserMismatch);
+        }
+
+        if let Some(user_handle) = &credential.response.user_handle {
+            if decode(user_handle)? != self.user_handle(username) {
+                return Err(WebAuthnError::UserMismatch);
+            }
+        }
+
+        let auth_data_bytes = decode(&credential.response.authenticator_data)?;
+        let auth_data = AuthenticatorData::parse(&auth_data_bytes)?;
+        self.verify_authenticator_data(&auth_data)?;
+
+        // The signature covers the authenticator data and the hash of the client data
+        let mut message = auth_data_bytes;
+        message.extend_from_slice(&Sha256::digest(&client_data_json));
+
+        let key = VerifyingKey::from_sec1_bytes(&passkey.public_key)
+            .map_err(|_| WebAuthnError::Malformed("stored public key is invalid"))?;
+        let signature = Signature::from_der(&decode(&credential.response.signature)?)
+            .map_err(|_| WebAuthnError::InvalidSignature)?;
+
+        key.verify(&message, &signature)
+            .map_err(|_| WebAuthnError::InvalidSignature)?;
+
+        // Authenticators that do not implement a counter always report zero
+        if (auth_data.sign_count != 0 || passkey.sign_count != 0)
+            && auth_data.sign_count <= passkey.sign_count
+        {
+            return Err(WebAuthnError::SignCountRegression);
+        }
+
+        Ok(auth_data.sign_count)
+    }
+
+    fn create_challenge(&self, ceremony: Ceremony, username: Option<&str>) -> PasskeyChallenge {
+        let mut challenge = [0; 32];
+        OsRng.fill_bytes(&mut challenge);
+
+        PasskeyChallenge {
+            ceremony,
+            challenge: BASE64URL_NOPAD.encode(&challenge),
+            username: username.map(str::to_owned),
+            expires: (OffsetDateTime::now_utc() + self.timeout).unix_timestamp(),
+        }
+    }
+
+    /// Verify the client data and return its raw bytes.
+    fn verify_client_data(
+        &self,
+        challenge: &PasskeyChallenge,
+        ceremony: Ceremony,
+        client_data_json: &str,
+    ) -> Result<Vec<u8>, WebAuthnError> {
+        if challenge.ceremony != ceremony {
+            return Err(WebAuthnError::ChallengeMismatch);
+        }
+
+        if OffsetDateTime::now_utc().unix_timestamp() >= challenge.expires {
+            return Err(WebAuthnError::ChallengeExpired);
+        }
+
+        let client_data_json = decode(client_data_json)?;
+        let client_data: ClientData = serde_json::from_slice(&client_data_json)
+            .map_err(|_| WebAuthnError::Malformed("client data is not valid JSON"))?;
+
+        let expected_type = match ceremony {
+            Ceremony::Registration => "webauthn.create",
+            Ceremony::Authentication => "webauthn.get",
+        };
+
+        if client_data.kind != expected_type {
+            return Err(WebAuthnError::Malformed("client data has the wrong type"));
+        }
+
+        if decode(&client_data.challenge)? != decode(&challenge.challenge)? {
+            return Err(WebAuthnError::ChallengeMismatch);
+        }
+
+        if client_data.origin != self.origin || client_data.cross_origin {
+            return Err(WebAuthnError::OriginMismatch(client_data.origin));
+        }
+
+        Ok(client_data_json)
+    }
+
+    fn verify_authenticator_data(&self, data: &AuthenticatorData) -> Result<(), WebAuthnError> {
+        if data.rp_id_hash[..] != Sha256::digest(self.rp_id.as_bytes())[..] {
+            return Err(WebAuthnError::RelyingPartyMismatch);
+        }
+
+        if data.flags & FLAG_UP == 0 {
+            return Err(WebAuthnError::UserNotPresent);
+        }
+
+        if self.require_user_verification && data.flags & FLAG_UV == 0 {
+            return Err(WebAuthnError::UserNotVerified);
+        }
+
+        Ok(())
+    }
+
+    fn user_verification(&self) -> &'static str {
+        if self.require_user_verification {
+            "required"
+        } else {
+            "preferred"
+        }
+    }
+
+    fn timeout_millis(&self) -> u64 {
+        self.timeout.whole_milliseconds().max(0) as u64
+    }
+}
+
+impl Default for WebAuthn {
+    fn default() -> Self {
+        Self {
+            rp_id: "localhost".to_owned(),
+            rp_name: "rocket-identity".to_owned(),
+            origin: "http://localhost:8000".to_owned(),
+            timeout: Duration::minutes(5),
+            require_user_verification: false,
+        }
+    }
+}
+
+/// Decode base64url, with or without padding.
+pub(crate) fn decode(value: &str) -> Result<Vec<u8>, WebAuthnError> {
+    BASE64URL_NOPAD
+        .decode(value.trim_end_matches('=').as_bytes())
+        .map_err(|_| WebAuthnError::Malformed("value is not valid base64url"))
+}
+
+fn descriptors(passkeys: &[Passkey]) -> Vec<CredentialDescriptor> {
+    passkeys
+        .iter()
+        .map(|passkey| CredentialDescriptor {
+            kind: "public-key".to_owned(),
+            id: BASE64URL_NOPAD.encode(&passkey.credential_id),
+        })
+        .collect()
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum WebAuthnError {
+    #[error("malformed response: {0}")]
+    Malformed(&'static str),
+
+    #[error("the response does not belong to the challenge")]
+    ChallengeMismatch,
+
+    #[error("the challenge has expired")]
+    ChallengeExpired,
+
+    #[error("unexpected origin {0}")]
+    OriginMismatch(String),
+
+    #[error("the credential is scoped to a different relying party")]
+    RelyingPartyMismatch,
+
+    #[error("the credential belongs to a different user")]
+    UserMismatch,
+
+    #[error("the user was not present")]
+    UserNotPresent,
+
+    #[error("the user was not verified")]
+    UserNotVerified,
+
+    #[error("unsupported attestation format {0}")]
+    UnsupportedAttestation(String),
+
+    #[error("only ES256 credentials are supported")]
+    UnsupportedAlgorithm,
+
+    #[error("the signature is invalid")]
+    InvalidSignature,
+
+    #[error("the signature counter did not increase, the authenticator may be cloned")]
+    SignCountRegression,
+}
@@ -0,0 +1,108 @@
+use rocket::{
+    get,
+    http::{Header, Status},
+    local::asynchronous::Client,
+    routes,
+    time::{Duration, OffsetDateTime},
+    Build, Rocket,
+};
+use rocket_identity::{
+    schemes::api_key::ApiKey, stores::memory::MemoryStore, Identity, Services, User,
+};
+
+#[get("/authenticated")]
+fn handler(user: &User) -> String {
+    let mut roles = user.roles.iter().collect::<Vec<_>>();
+    roles.sort();
+
+    format!("{} {}", user.username, roles.join(","))
+}
+
+fn setup() -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(ApiKey::default().with_query_param("api_key"))
+        .build();
+
+    rocket::build()
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config))
+}
+
+#[rocket::async_test]
+async fn api_key_authenticates_with_its_scopes() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("service1");
+    users.add_user(&user, None).await.unwrap();
+
+    let (key, api_key) = users
+        .create_api_key(&user, "deploy", &["read", "write"], None)
+        .await
+        .expect("Could not create API key");
+    assert!(key.starts_with("rik_"));
+    assert_eq!(api_key.last_used, None);
+
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("X-API-Key", key.clone()))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.into_string().await.unwrap(), "service1 read,write");
+
+    let res = client
+        .get(format!("/authenticated?api_key={}", key))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let api_keys = users.api_keys(&user).await.unwrap();
+    assert_eq!(api_keys.len(), 1);
+    assert!(api_keys[0].last_used.is_some());
+
+    // A wrong secret for an existing id is rejected
+    let (prefix, _) = key.rsplit_once('_').unwrap();
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("X-API-Key", format!("{}_wrong", prefix)))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    users.revoke_api_key(&user, &api_key.id).await.unwrap();
+
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("X-API-Key", key))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn expired_api_key_is_rejected() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("service1");
+    users.add_user(&user, None).await.unwrap();
+
+    let expires = OffsetDateTime::now_utc() - Duration::seconds(1);
+    let (key, _) = users
+        .create_api_key(&user, "old", &[], Some(expires))
+        .await
+        .unwrap();
+
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("X-API-Key", key))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
@@ -0,0 +1,219 @@
+use base64::{engine::general_purpose, Engine};
+use rocket::{
+    figment::{providers::Format, providers::Toml, Figment},
+    get,
+    http::{Header, Status},
+    local::asynchronous::Client,
+    routes, Build, Rocket,
+};
+use rocket_identity::{
+    schemes::basic::{Basic, BasicCharset},
+    stores::memory::MemoryStore,
+    validators::UsernameViolation,
+    AddUserError, Identity, Services, User,
+};
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup(rocket: Rocket<Build>, scheme: Option<Basic>) -> Rocket<Build> {
+    let mut config = Identity::config();
+    config.with_user_store(MemoryStore::new());
+    if let Some(scheme) = scheme {
+        config.add_scheme(scheme);
+    }
+
+    rocket
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config.build()))
+}
+
+async fn setup_client(rocket: Rocket<Build>) -> Client {
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    users
+        .add_user(&User::with_username("user1"), Some("pa:ss:1"))
+        .await
+        .unwrap();
+    users
+        .add_user(&User::with_username("jörg"), Some("pässword"))
+        .await
+        .unwrap();
+
+    client
+}
+
+async fn authenticate(client: &Client, authorization: &str) -> (Status, String) {
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("Authorization", authorization.to_owned()))
+        .dispatch()
+        .await;
+
+    (res.status(), res.into_string().await.unwrap_or_default())
+}
+
+fn encode(credentials: &[u8]) -> String {
+    general_purpose::STANDARD.encode(credentials)
+}
+
+/// ISO-8859-1 encoding of characters below U+0100.
+fn latin1(credentials: &str) -> Vec<u8> {
+    credentials.chars().map(|c| c as u8).collect()
+}
+
+#[rocket::async_test]
+async fn scheme_token_is_case_insensitive_and_whitespace_is_ignored() {
+    let client = setup_client(setup(rocket::build(), Some(Basic::new("Server")))).await;
+    let credentials = encode(b"user1:pa:ss:1");
+
+    for authorization in [
+        format!("Basic {}", credentials),
+        format!("basic {}", credentials),
+        format!("BASIC {}", credentials),
+        format!("bAsIc {}", credentials),
+        format!("Basic    {}", credentials),
+        format!("Basic\t{}", credentials),
+        format!("  Basic {}  ", credentials),
+    ] {
+        assert_eq!(
+            authenticate(&client, &authorization).await,
+            (Status::Ok, "user1".to_owned()),
+            "{:?}",
+            authorization
+        );
+    }
+
+    // Other schemes are not mistaken for Basic
+    for authorization in [
+        format!("Basicx {}", credentials),
+        format!("Bearer {}", credentials),
+    ] {
+        assert_eq!(
+            authenticate(&client, &authorization).await.0,
+            Status::Unauthorized
+        );
+    }
+}
+
+#[rocket::async_test]
+async fn passwords_can_contain_colons() {
+    let client = setup_client(setup(rocket::build(), Some(Basic::new("Server")))).await;
+
+    let authorization = format!("Basic {}", encode(b"user1:pa:ss:1"));
+    assert_eq!(authenticate(&client, &authorization).await.0, Status::Ok);
+
+    // Only the first colon separates the username
+    let authorization = format!("Basic {}", encode(b"user1:pa:ss"));
+    assert_eq!(
+        authenticate(&client, &authorization).await.0,
+        Status::Unauthorized
+    );
+
+    let authorization = format!("Basic {}", encode(b"user1"));
+    assert_eq!(
+        authenticate(&client, &authorization).await.0,
+        Status::Unauthorized
+    );
+}
+
+#[rocket::async_test]
+async fn usernames_with_colons_cannot_be_registered() {
+    let client = setup_client(setup(rocket::build(), Some(Basic::new("Server")))).await;
+    let users = client.rocket().user_repository().await;
+
+    let result = users
+        .add_user(&User::with_username("user:2"), Some("password"))
+        .await;
+    assert!(matches!(
+        result,
+        Err(AddUserError::InvalidUsername(violations))
+            if violations == vec![UsernameViolation::InvalidCharacter(':')]
+    ));
+}
+
+#[rocket::async_test]
+async fn malformed_credentials_are_challenged() {
+    let client = setup_client(setup(rocket::build(), Some(Basic::new("Server")))).await;
+
+    // Padding is optional
+    let authorization = format!("Basic {}", encode(b"user1:pa:ss:1").trim_end_matches('='));
+    assert_eq!(authenticate(&client, &authorization).await.0, Status::Ok);
+
+    for authorization in ["Basic !!!", "Basic dXNlcjE6c", "Basic"] {
+        let res = client
+            .get("/authenticated")
+            .header(Header::new("Authorization", authorization))
+            .dispatch()
+            .await;
+
+        assert_eq!(res.status(), Status::Unauthorized, "{:?}", authorization);
+        assert_eq!(
+            res.headers().get_one("WWW-Authenticate"),
+            Some(r#"Basic realm="Server", charset="UTF-8""#)
+        );
+    }
+}
+
+#[rocket::async_test]
+async fn utf8_credentials_are_required_by_default() {
+    let client = setup_client(setup(rocket::build(), Some(Basic::new("Server")))).await;
+
+    let authorization = format!("Basic {}", encode("jörg:pässword".as_bytes()));
+    assert_eq!(
+        authenticate(&client, &authorization).await,
+        (Status::Ok, "jörg".to_owned())
+    );
+
+    let authorization = format!("Basic {}", encode(&latin1("jörg:pässword")));
+    assert_eq!(
+        authenticate(&client, &authorization).await.0,
+        Status::Unauthorized
+    );
+}
+
+#[rocket::async_test]
+async fn iso_8859_1_credentials_can_be_accepted() {
+    let scheme = Basic::new("Server").with_charset(BasicCharset::Iso8859_1);
+    let client = setup_client(setup(rocket::build(), Some(scheme))).await;
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(
+        res.headers().get_one("WWW-Authenticate"),
+        Some(r#"Basic realm="Server""#)
+    );
+
+    for credentials in [latin1("jörg:pässword"), "jörg:pässword".as_bytes().to_vec()] {
+        let authorization = format!("Basic {}", encode(&credentials));
+        assert_eq!(
+            authenticate(&client, &authorization).await,
+            (Status::Ok, "jörg".to_owned())
+        );
+    }
+}
+
+#[rocket::async_test]
+async fn charset_can_be_configured_in_settings() {
+    let figment = Figment::from(rocket::Config::default()).merge(Toml::string(
+        r#"
+        [identity.basic]
+        realm = "Server"
+        charset = "iso-8859-1"
+        "#,
+    ));
+    let client = setup_client(setup(rocket::custom(figment), None)).await;
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(
+        res.headers().get_one("WWW-Authenticate"),
+        Some(r#"Basic realm="Server""#)
+    );
+
+    let authorization = format!("Basic {}", encode(&latin1("jörg:pässword")));
+    assert_eq!(authenticate(&client, &authorization).await.0, Status::Ok);
+}
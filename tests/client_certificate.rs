@@ -0,0 +1,193 @@
+#![cfg(feature = "mtls")]
+
+use std::net::SocketAddr;
+
+use rcgen::{Certificate, CertificateParams, DnType, SanType};
+use rocket::{
+    get,
+    http::{Header, Status},
+    local::asynchronous::Client,
+    routes,
+};
+use rocket_identity::{
+    schemes::{
+        client_certificate::{
+            CertificateField, CertificateIdentity, ClientCertificate, FieldMapper,
+        },
+        TrustedProxies,
+    },
+    stores::memory::MemoryStore,
+    Identity, Roles, Services, User,
+};
+
+const HEADER: &str = "X-Client-Cert";
+
+#[get("/authenticated")]
+fn handler(user: &User) -> String {
+    let mut roles = user.roles.iter().collect::<Vec<_>>();
+    roles.sort();
+
+    format!("{} {}", user.username, roles.join(","))
+}
+
+async fn setup(scheme: ClientCertificate) -> Client {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(scheme)
+        .build();
+
+    let rocket = rocket::build()
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config));
+
+    Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client")
+}
+
+fn proxied(mapper: FieldMapper) -> ClientCertificate {
+    let trusted_proxies = TrustedProxies::new(&["10.0.0.0/8", "::1"]).unwrap();
+
+    ClientCertificate::new(mapper).with_proxy_header(HEADER, trusted_proxies)
+}
+
+/// A certificate as nginx forwards it with `$ssl_client_escaped_cert`.
+fn escaped_certificate(common_name: &str, alt_names: Vec<SanType>) -> String {
+    let mut params = CertificateParams::default();
+    params
+        .distinguished_name
+        .push(DnType::CommonName, common_name);
+    params.subject_alt_names = alt_names;
+
+    let pem = Certificate::from_params(params)
+        .unwrap()
+        .serialize_pem()
+        .unwrap();
+
+    pem.replace('\n', "%0A")
+        .replace(' ', "%20")
+        .replace('+', "%2B")
+}
+
+async fn authenticate(client: &Client, remote: &str, certificate: String) -> (Status, String) {
+    let res = client
+        .get("/authenticated")
+        .remote(remote.parse::<SocketAddr>().unwrap())
+        .header(Header::new(HEADER, certificate))
+        .dispatch()
+        .await;
+
+    (res.status(), res.into_string().await.unwrap_or_default())
+}
+
+#[rocket::async_test]
+async fn forwarded_certificates_authenticate() {
+    let client = setup(proxied(FieldMapper::new(CertificateField::CommonName))).await;
+
+    let certificate = escaped_certificate("billing", vec![]);
+    assert_eq!(
+        authenticate(&client, "10.1.2.3:4000", certificate.clone()).await,
+        (Status::Ok, "billing ".to_owned())
+    );
+    assert_eq!(
+        authenticate(&client, "[::ffff:10.1.2.3]:4000", certificate.clone())
+            .await
+            .0,
+        Status::Ok
+    );
+
+    // Clients cannot set the header themselves
+    assert_eq!(
+        authenticate(&client, "192.168.1.5:4000", certificate)
+            .await
+            .0,
+        Status::Unauthorized
+    );
+
+    assert_eq!(
+        authenticate(&client, "[::1]:4000", "not a certificate".to_owned())
+            .await
+            .0,
+        Status::BadRequest
+    );
+
+    let res = client
+        .get("/authenticated")
+        .remote("10.1.2.3:4000".parse().unwrap())
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn certificates_map_to_existing_users() {
+    let client = setup(proxied(
+        FieldMapper::new(CertificateField::DnsName).existing_users(),
+    ))
+    .await;
+
+    let users = client.rocket().user_repository().await;
+    let mut user = User::with_username("billing.example.com");
+    user.roles = Roles::from(vec!["service"]);
+    users.add_user(&user, None).await.unwrap();
+
+    let certificate = escaped_certificate(
+        "Billing",
+        vec![SanType::DnsName("billing.example.com".to_owned())],
+    );
+    assert_eq!(
+        authenticate(&client, "10.0.0.1:4000", certificate).await,
+        (Status::Ok, "billing.example.com service".to_owned())
+    );
+
+    let certificate = escaped_certificate(
+        "Other",
+        vec![SanType::DnsName("other.example.com".to_owned())],
+    );
+    assert_eq!(
+        authenticate(&client, "10.0.0.1:4000", certificate).await.0,
+        Status::Unauthorized
+    );
+}
+
+#[rocket::async_test]
+async fn custom_mappers_see_the_certificate_identity() {
+    let mapper = |identity: &CertificateIdentity| {
+        let spiffe_id = identity.uris.first()?;
+        let service = spiffe_id.strip_prefix("spiffe://example.com/")?;
+
+        Some(User::with_username(service))
+    };
+    let trusted_proxies = TrustedProxies::new(&["127.0.0.1"]).unwrap();
+    let client =
+        setup(ClientCertificate::new(mapper).with_proxy_header(HEADER, trusted_proxies)).await;
+
+    let certificate = escaped_certificate(
+        "ignored",
+        vec![SanType::URI("spiffe://example.com/payments".to_owned())],
+    );
+    assert_eq!(
+        authenticate(&client, "127.0.0.1:4000", certificate).await,
+        (Status::Ok, "payments ".to_owned())
+    );
+
+    let certificate = escaped_certificate(
+        "ignored",
+        vec![SanType::URI("spiffe://other.org/payments".to_owned())],
+    );
+    assert_eq!(
+        authenticate(&client, "127.0.0.1:4000", certificate).await.0,
+        Status::Unauthorized
+    );
+}
+
+#[test]
+fn invalid_proxy_ranges_are_rejected() {
+    assert!(TrustedProxies::new(&["10.0.0.0/33"]).is_err());
+    assert!(TrustedProxies::new(&["example.com"]).is_err());
+
+    let trusted_proxies = TrustedProxies::new(&["10.0.0.0/8", "fd00::/8"]).unwrap();
+    assert!(trusted_proxies.contains("10.255.0.1".parse().unwrap()));
+    assert!(trusted_proxies.contains("fd12::1".parse().unwrap()));
+    assert!(!trusted_proxies.contains("11.0.0.1".parse().unwrap()));
+}
@@ -0,0 +1,110 @@
+use rocket::{
+    get,
+    http::Status,
+    local::asynchronous::Client,
+    post, routes,
+    time::{Duration, OffsetDateTime},
+    Build, Rocket,
+};
+use rocket_identity::{
+    schemes::cookie::{CookieScheme, CookieSession},
+    stores::memory::MemoryStore,
+    two_factor::Totp,
+    Identity, LoginError, Services, User, UserRepository,
+};
+
+#[post("/login?<username>&<password>")]
+async fn login(
+    username: &str,
+    password: &str,
+    users: &UserRepository,
+    session: CookieSession<'_>,
+) -> &'static str {
+    match users.authenticate(username, password).await {
+        Ok(user) => {
+            session.sign_in(&user);
+            "signed in"
+        }
+        Err(LoginError::TwoFactorRequired { user }) => {
+            session.begin_two_factor(&user);
+            "two-factor required"
+        }
+        Err(_) => "failed",
+    }
+}
+
+#[post("/login/two-factor?<code>")]
+async fn two_factor(code: &str, users: &UserRepository, session: CookieSession<'_>) -> Status {
+    match session.complete_two_factor(users, code).await {
+        Ok(_) => Status::Ok,
+        Err(_) => Status::Unauthorized,
+    }
+}
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup() -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(CookieScheme::default())
+        .build();
+
+    rocket::build()
+        .mount("/", routes![login, two_factor, handler])
+        .attach(Identity::fairing(config))
+}
+
+#[rocket::async_test]
+async fn pending_two_factor_session_is_not_signed_in() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+    let totp = Totp::default();
+
+    let user = User::with_username("user1");
+    users.add_user(&user, Some("pass1")).await.unwrap();
+    let secret = users.create_totp_secret(&user).await.unwrap();
+    let now = OffsetDateTime::now_utc();
+    users
+        .enable_two_factor(&user, &totp.code_at(&secret, now))
+        .await
+        .unwrap();
+
+    let res = client
+        .post("/login?username=user1&password=pass1")
+        .dispatch()
+        .await;
+    assert_eq!(res.into_string().await.unwrap(), "two-factor required");
+
+    // The pending sign-in is not accepted as a login
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    let res = client
+        .post("/login/two-factor?code=000000x")
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    let code = totp.code_at(&secret, now + Duration::seconds(30));
+    let res = client
+        .post(format!("/login/two-factor?code={}", code))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.into_string().await.unwrap(), "user1");
+
+    // The pending sign-in was consumed
+    let res = client
+        .post(format!("/login/two-factor?code={}", code))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
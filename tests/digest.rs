@@ -0,0 +1,358 @@
+use rocket::{
+    get,
+    http::{Header, Status},
+    local::asynchronous::Client,
+    routes,
+    time::Duration,
+    Build, Rocket,
+};
+use rocket_identity::{
+    schemes::digest::Digest,
+    stores::{memory::MemoryStore, DigestAlgorithm},
+    Identity, Services, User,
+};
+
+const REALM: &str = "devices@example.com";
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup(scheme: Digest) -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(scheme)
+        .build();
+
+    rocket::build()
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config))
+}
+
+async fn setup_client(scheme: Digest) -> Client {
+    let client = Client::tracked(setup(scheme))
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("alice");
+    users.add_user(&user, Some("password1")).await.unwrap();
+    users
+        .set_digest_password(&user, REALM, "password1")
+        .await
+        .unwrap();
+
+    client
+}
+
+/// The parameters of a challenge the client has to echo.
+struct Challenge {
+    nonce: String,
+    opaque: String,
+}
+
+async fn challenges(client: &Client) -> Vec<String> {
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    res.headers()
+        .get("WWW-Authenticate")
+        .map(str::to_owned)
+        .collect()
+}
+
+fn challenge_param(challenge: &str, name: &str) -> String {
+    let start = challenge.find(&format!("{}=\"", name)).unwrap() + name.len() + 2;
+    let end = challenge[start..].find('"').unwrap();
+
+    challenge[start..start + end].to_owned()
+}
+
+async fn fetch_challenge(client: &Client) -> Challenge {
+    let challenges = challenges(client).await;
+
+    Challenge {
+        nonce: challenge_param(&challenges[0], "nonce"),
+        opaque: challenge_param(&challenges[0], "opaque"),
+    }
+}
+
+/// Answer a challenge like a client would.
+fn response(
+    challenge: &Challenge,
+    algorithm: DigestAlgorithm,
+    password: &str,
+    uri: &str,
+    nc: u32,
+) -> String {
+    let ha1 = algorithm.hash(&format!("alice:{}:{}", REALM, password));
+    let ha2 = algorithm.hash(&format!("GET:{}", uri));
+    let nc = format!("{:08x}", nc);
+    let cnonce = "0a4f113b";
+    let response = algorithm.hash(&format!(
+        "{}:{}:{}:{}:auth:{}",
+        ha1, challenge.nonce, nc, cnonce, ha2
+    ));
+
+    format!(
+        r#"Digest username="alice", realm="{}", uri="{}", algorithm={}, nonce="{}", nc={}, cnonce="{}", qop=auth, response="{}", opaque="{}""#,
+        REALM,
+        uri,
+        algorithm.name(),
+        challenge.nonce,
+        nc,
+        cnonce,
+        response,
+        challenge.opaque
+    )
+}
+
+async fn authenticate(client: &Client, authorization: String) -> Status {
+    client
+        .get("/authenticated")
+        .header(Header::new("Authorization", authorization))
+        .dispatch()
+        .await
+        .status()
+}
+
+#[rocket::async_test]
+async fn digest_responses_authenticate() {
+    let client = setup_client(Digest::new(REALM)).await;
+
+    let challenges = challenges(&client).await;
+    assert_eq!(challenges.len(), 2);
+    assert!(challenges[0].starts_with(&format!(
+        r#"Digest realm="{}", qop="auth", algorithm=SHA-256, nonce=""#,
+        REALM
+    )));
+    assert!(challenges[1].contains("algorithm=MD5"));
+
+    let challenge = fetch_challenge(&client).await;
+    for (algorithm, nc) in [(DigestAlgorithm::Sha256, 1), (DigestAlgorithm::Md5, 2)] {
+        let authorization = response(&challenge, algorithm, "password1", "/authenticated", nc);
+        let res = client
+            .get("/authenticated")
+            .header(Header::new("Authorization", authorization))
+            .dispatch()
+            .await;
+        assert_eq!(res.status(), Status::Ok);
+        assert_eq!(res.into_string().await.unwrap(), "alice");
+    }
+
+    // Responses cannot be replayed
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        2,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::Unauthorized
+    );
+
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "wrong",
+        "/authenticated",
+        3,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::Unauthorized
+    );
+
+    // Responses have to be for the requested URI
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/other",
+        4,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::BadRequest
+    );
+}
+
+#[rocket::async_test]
+async fn digest_credentials_follow_password_changes() {
+    let client = setup_client(Digest::new(REALM)).await;
+    let users = client.rocket().user_repository().await;
+    let user = users.find_by_username("alice").await.unwrap().unwrap();
+
+    users
+        .change_password(&user, "password1", "password2")
+        .await
+        .unwrap();
+
+    let challenge = fetch_challenge(&client).await;
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::Unauthorized
+    );
+
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password2",
+        "/authenticated",
+        2,
+    );
+    assert_eq!(authenticate(&client, authorization).await, Status::Ok);
+
+    // Without digest credentials users cannot sign in with digest authentication
+    users.remove_digest_password(&user, REALM).await.unwrap();
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password2",
+        "/authenticated",
+        3,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::Unauthorized
+    );
+}
+
+#[rocket::async_test]
+async fn only_valid_nonces_and_offered_algorithms_are_accepted() {
+    let client = setup_client(
+        Digest::new(REALM)
+            .with_algorithms(&[DigestAlgorithm::Sha256])
+            .with_nonce_lifetime(Duration::ZERO),
+    )
+    .await;
+
+    assert_eq!(challenges(&client).await.len(), 1);
+
+    // Expired nonces
+    let challenge = fetch_challenge(&client).await;
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::Unauthorized
+    );
+
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Md5,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::BadRequest
+    );
+
+    let client = setup_client(Digest::new(REALM)).await;
+
+    // Nonces that were not issued by the scheme
+    let forged = Challenge {
+        nonce: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_owned(),
+        ..fetch_challenge(&client).await
+    };
+    let authorization = response(
+        &forged,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    assert_eq!(
+        authenticate(&client, authorization).await,
+        Status::Unauthorized
+    );
+
+    assert_eq!(
+        authenticate(&client, r#"Digest username="alice, realm="#.to_owned()).await,
+        Status::BadRequest
+    );
+}
+
+#[rocket::async_test]
+async fn expired_nonces_are_challenged_as_stale() {
+    let client = setup_client(Digest::new(REALM).with_nonce_lifetime(Duration::ZERO)).await;
+
+    let challenge = fetch_challenge(&client).await;
+    assert!(!challenges(&client).await[0].contains("stale"));
+
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("Authorization", authorization))
+        .dispatch()
+        .await;
+
+    assert_eq!(res.status(), Status::Unauthorized);
+    for challenge in res.headers().get("WWW-Authenticate") {
+        assert!(challenge.ends_with(", stale=true"));
+    }
+
+    // Nonces that were not issued by the scheme are not stale
+    let forged = Challenge {
+        nonce: "AAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAAA".to_owned(),
+        ..challenge
+    };
+    let authorization = response(
+        &forged,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("Authorization", authorization))
+        .dispatch()
+        .await;
+
+    assert_eq!(res.status(), Status::Unauthorized);
+    assert!(res
+        .headers()
+        .get("WWW-Authenticate")
+        .all(|challenge| !challenge.contains("stale")));
+}
+
+#[rocket::async_test]
+async fn digest_scheme_token_is_case_insensitive() {
+    let client = setup_client(Digest::new(REALM)).await;
+
+    let challenge = fetch_challenge(&client).await;
+    let authorization = response(
+        &challenge,
+        DigestAlgorithm::Sha256,
+        "password1",
+        "/authenticated",
+        1,
+    );
+    let authorization = authorization.replacen("Digest ", "digest  ", 1);
+
+    assert_eq!(authenticate(&client, authorization).await, Status::Ok);
+}
@@ -0,0 +1,95 @@
+use rocket::local::asynchronous::Client;
+use rocket_identity::{
+    config::SignInOptions, stores::memory::MemoryStore, tokens::TokenError, EmailConfirmationError,
+    Identity, LoginError, Services, User,
+};
+
+#[rocket::async_test]
+async fn confirmation_token_confirms_current_email() {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User {
+        email: Some("user1@example.com".to_owned()),
+        ..User::with_username("user1")
+    };
+    users
+        .add_user(&user, Some("pass1"))
+        .await
+        .expect("Could not add user");
+
+    let token = users
+        .generate_email_confirmation_token(&user)
+        .await
+        .expect("Could not generate token");
+
+    assert!(matches!(
+        users.confirm_email(&user, "invalid").await,
+        Err(EmailConfirmationError::InvalidToken(TokenError::Invalid))
+    ));
+
+    users
+        .confirm_email(&user, &token)
+        .await
+        .expect("Could not confirm email");
+
+    let user = users.find_by_username("user1").await.unwrap().unwrap();
+    assert!(user.email_confirmed);
+
+    // Changing the address requires a new confirmation and invalidates old tokens
+    users
+        .set_email(&user, Some("user1@example.org"))
+        .await
+        .expect("Could not change email");
+
+    let user = users.find_by_username("user1").await.unwrap().unwrap();
+    assert_eq!(user.email.as_deref(), Some("user1@example.org"));
+    assert!(!user.email_confirmed);
+
+    assert!(matches!(
+        users.confirm_email(&user, &token).await,
+        Err(EmailConfirmationError::InvalidToken(TokenError::Invalid))
+    ));
+}
+
+#[rocket::async_test]
+async fn unconfirmed_users_cannot_log_in_if_required() {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_sign_in_options(SignInOptions {
+            require_confirmed_email: true,
+        })
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User {
+        email: Some("user1@example.com".to_owned()),
+        ..User::with_username("user1")
+    };
+    users.add_user(&user, Some("pass1")).await.unwrap();
+
+    assert!(matches!(
+        users.authenticate("user1", "pass1").await,
+        Err(LoginError::EmailNotConfirmed)
+    ));
+
+    let token = users
+        .generate_email_confirmation_token(&user)
+        .await
+        .unwrap();
+    users.confirm_email(&user, &token).await.unwrap();
+
+    assert!(users.authenticate("user1", "pass1").await.is_ok());
+}
@@ -0,0 +1,249 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use rocket::{
+    get,
+    http::{Header, Status},
+    local::asynchronous::Client,
+    routes,
+    serde::json::{serde_json, Value},
+    time::{Duration, OffsetDateTime},
+    Build, Rocket,
+};
+use rocket_identity::{
+    external::{HttpClient, HttpClientError, HttpResponse},
+    schemes::introspection::Introspection,
+    stores::memory::MemoryStore,
+    Identity, User,
+};
+
+const ENDPOINT: &str = "https://auth.example.com/introspect";
+
+/// Answers introspection requests for the tokens it knows, like an authorization
+/// server would.
+#[derive(Debug, Clone, Default)]
+struct StubServer {
+    tokens: Arc<Mutex<HashMap<String, Value>>>,
+    requests: Arc<Mutex<usize>>,
+}
+
+impl StubServer {
+    fn add_token(&self, token: &str, response: Value) {
+        self.tokens
+            .lock()
+            .unwrap()
+            .insert(token.to_owned(), response);
+    }
+
+    fn requests(&self) -> usize {
+        *self.requests.lock().unwrap()
+    }
+}
+
+#[rocket::async_trait]
+impl HttpClient for StubServer {
+    async fn get(&self, _url: &str) -> Result<HttpResponse, HttpClientError> {
+        unimplemented!()
+    }
+
+    async fn post_form(
+        &self,
+        url: &str,
+        form: &[(&str, &str)],
+    ) -> Result<HttpResponse, HttpClientError> {
+        assert_eq!(url, ENDPOINT);
+        *self.requests.lock().unwrap() += 1;
+
+        let param = |name: &str| {
+            form.iter()
+                .find(|(n, _)| *n == name)
+                .map(|(_, v)| *v)
+                .unwrap_or_default()
+        };
+
+        if param("client_id") != "api" || param("client_secret") != "secret" {
+            return Ok(HttpResponse::new(401, "{}"));
+        }
+
+        let response = self
+            .tokens
+            .lock()
+            .unwrap()
+            .get(param("token"))
+            .cloned()
+            .unwrap_or(serde_json::json!({ "active": false }));
+
+        Ok(HttpResponse::new(200, response.to_string()))
+    }
+}
+
+#[get("/authenticated")]
+fn handler(user: &User) -> String {
+    let mut roles = user.roles.iter().collect::<Vec<_>>();
+    roles.sort();
+
+    let tenant = user
+        .claims
+        .get("tenant")
+        .and_then(|tenant| tenant.as_str())
+        .unwrap_or_default();
+
+    format!("{} {} {}", user.username, roles.join(","), tenant)
+}
+
+fn setup(server: &StubServer, client_secret: &str) -> Rocket<Build> {
+    setup_with(
+        Introspection::new(ENDPOINT, server.clone()).with_client_credentials("api", client_secret),
+    )
+}
+
+fn setup_with(introspection: Introspection) -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(introspection)
+        .build();
+
+    rocket::build()
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config))
+}
+
+async fn authenticate(client: &Client, token: &str) -> (Status, String) {
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .dispatch()
+        .await;
+
+    (res.status(), res.into_string().await.unwrap_or_default())
+}
+
+fn expires_in(duration: Duration) -> i64 {
+    (OffsetDateTime::now_utc() + duration).unix_timestamp()
+}
+
+#[rocket::async_test]
+async fn active_tokens_are_mapped_and_cached() {
+    let server = StubServer::default();
+    server.add_token(
+        "token1",
+        serde_json::json!({
+            "active": true,
+            "sub": "alice",
+            "scope": "read write",
+            "tenant": "example",
+            "exp": expires_in(Duration::hours(1)),
+        }),
+    );
+    server.add_token(
+        "token2",
+        serde_json::json!({
+            "active": true,
+            "username": "service",
+        }),
+    );
+
+    let client = Client::tracked(setup(&server, "secret"))
+        .await
+        .expect("Failed to acquire Client");
+
+    let (status, body) = authenticate(&client, "token1").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body, "alice read,write example");
+    assert_eq!(server.requests(), 1);
+
+    // Active tokens are not introspected again until they expire
+    let (status, _) = authenticate(&client, "token1").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(server.requests(), 1);
+
+    // Tokens without an expiry are introspected on every request
+    let (status, body) = authenticate(&client, "token2").await;
+    assert_eq!(status, Status::Ok);
+    assert_eq!(body, "service  ");
+    authenticate(&client, "token2").await;
+    assert_eq!(server.requests(), 3);
+}
+
+#[rocket::async_test]
+async fn tokens_are_cached_for_at_most_the_max_cache_duration() {
+    let server = StubServer::default();
+    server.add_token(
+        "token1",
+        serde_json::json!({
+            "active": true,
+            "sub": "alice",
+            "exp": expires_in(Duration::hours(1)),
+        }),
+    );
+
+    let client = Client::tracked(setup_with(
+        Introspection::new(ENDPOINT, server.clone())
+            .with_client_credentials("api", "secret")
+            .with_max_cache_duration(Duration::ZERO),
+    ))
+    .await
+    .expect("Failed to acquire Client");
+
+    let (status, _) = authenticate(&client, "token1").await;
+    assert_eq!(status, Status::Ok);
+
+    // The token is introspected again, e.g. to notice that it was revoked
+    server.add_token("token1", serde_json::json!({ "active": false }));
+    let (status, _) = authenticate(&client, "token1").await;
+    assert_eq!(status, Status::Unauthorized);
+    assert_eq!(server.requests(), 2);
+}
+
+#[rocket::async_test]
+async fn inactive_tokens_are_rejected() {
+    let server = StubServer::default();
+    server.add_token(
+        "expired",
+        serde_json::json!({
+            "active": true,
+            "sub": "alice",
+            "exp": expires_in(-Duration::minutes(1)),
+        }),
+    );
+    server.add_token("anonymous", serde_json::json!({ "active": true }));
+
+    let client = Client::tracked(setup(&server, "secret"))
+        .await
+        .expect("Failed to acquire Client");
+
+    let (status, _) = authenticate(&client, "unknown").await;
+    assert_eq!(status, Status::Unauthorized);
+
+    // Inactive results are not cached
+    authenticate(&client, "unknown").await;
+    assert_eq!(server.requests(), 2);
+
+    let (status, _) = authenticate(&client, "expired").await;
+    assert_eq!(status, Status::Unauthorized);
+
+    let (status, _) = authenticate(&client, "anonymous").await;
+    assert_eq!(status, Status::BadRequest);
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+    assert_eq!(res.headers().get_one("WWW-Authenticate"), Some("Bearer"));
+}
+
+#[rocket::async_test]
+async fn failed_introspection_is_an_error() {
+    let server = StubServer::default();
+    server.add_token(
+        "token1",
+        serde_json::json!({ "active": true, "sub": "alice" }),
+    );
+
+    let client = Client::tracked(setup(&server, "wrong"))
+        .await
+        .expect("Failed to acquire Client");
+
+    let (status, _) = authenticate(&client, "token1").await;
+    assert_eq!(status, Status::InternalServerError);
+}
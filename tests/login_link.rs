@@ -0,0 +1,118 @@
+use rocket::{
+    get, http::Status, local::asynchronous::Client, post, routes, time::OffsetDateTime, Build,
+    Rocket,
+};
+use rocket_identity::{
+    notifiers::{memory::MemoryNotifier, Notification},
+    schemes::cookie::{CookieScheme, CookieSession},
+    stores::memory::MemoryStore,
+    two_factor::Totp,
+    Identity, LoginError, Services, User, UserRepository,
+};
+
+#[post("/login/link?<username>&<token>")]
+async fn login_link(
+    username: &str,
+    token: &str,
+    users: &UserRepository,
+    session: CookieSession<'_>,
+) -> &'static str {
+    match session
+        .sign_in_with_login_token(users, username, token)
+        .await
+    {
+        Ok(_) => "signed in",
+        Err(LoginError::TwoFactorRequired { .. }) => "two-factor required",
+        Err(_) => "failed",
+    }
+}
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup(notifier: MemoryNotifier) -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_notifier(notifier)
+        .add_scheme(CookieScheme::default())
+        .build();
+
+    rocket::build()
+        .mount("/", routes![login_link, handler])
+        .attach(Identity::fairing(config))
+}
+
+#[rocket::async_test]
+async fn login_link_signs_in_once() {
+    let notifier = MemoryNotifier::new();
+    let client = Client::tracked(setup(notifier.clone()))
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    // Users without a password can only log in with a link
+    let user = User::with_username("user1");
+    users.add_user(&user, None).await.unwrap();
+
+    users
+        .send_login_link(&user)
+        .await
+        .expect("Could not send login link");
+
+    let Some(Notification::LoginLink { token, .. }) = notifier.last() else {
+        panic!("Expected a login link notification");
+    };
+
+    let res = client
+        .post("/login/link?username=user1&token=invalid")
+        .dispatch()
+        .await;
+    assert_eq!(res.into_string().await.unwrap(), "failed");
+
+    let res = client
+        .post(format!("/login/link?username=user1&token={}", token))
+        .dispatch()
+        .await;
+    assert_eq!(res.into_string().await.unwrap(), "signed in");
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.into_string().await.unwrap(), "user1");
+
+    // The token was consumed
+    assert!(matches!(
+        users.authenticate_with_login_token("user1", &token).await,
+        Err(LoginError::InvalidLoginToken)
+    ));
+}
+
+#[rocket::async_test]
+async fn login_link_requires_second_factor() {
+    let client = Client::tracked(setup(MemoryNotifier::new()))
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("user1");
+    users.add_user(&user, None).await.unwrap();
+    let secret = users.create_totp_secret(&user).await.unwrap();
+    users
+        .enable_two_factor(
+            &user,
+            &Totp::default().code_at(&secret, OffsetDateTime::now_utc()),
+        )
+        .await
+        .unwrap();
+
+    let token = users.generate_login_token(&user).await.unwrap();
+    let res = client
+        .post(format!("/login/link?username=user1&token={}", token))
+        .dispatch()
+        .await;
+    assert_eq!(res.into_string().await.unwrap(), "two-factor required");
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
@@ -0,0 +1,439 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use data_encoding::BASE64URL_NOPAD;
+use rocket::{
+    get,
+    http::{ContentType, Header, Status},
+    local::asynchronous::{Client, LocalResponse},
+    post, routes,
+    serde::json::{serde_json, Value},
+    Build, Rocket,
+};
+use rocket_identity::{
+    oauth::{self, memory::MemoryOAuthStore, AuthorizationServer, GrantType, OAuthClient},
+    schemes::{
+        cookie::{CookieScheme, CookieSession},
+        jwt::{JwtBearer, JwtConfig},
+    },
+    stores::memory::MemoryStore,
+    Identity, Services, User, UserRepository,
+};
+use sha2::{Digest, Sha256};
+
+const REDIRECT_URI: &str = "https://app.example.com/callback";
+
+#[post("/login?<username>&<password>")]
+async fn login(
+    username: &str,
+    password: &str,
+    users: &UserRepository,
+    session: CookieSession<'_>,
+) -> Status {
+    match users.authenticate(username, password).await {
+        Ok(user) => {
+            session.sign_in(&user);
+            Status::Ok
+        }
+        Err(_) => Status::Unauthorized,
+    }
+}
+
+#[get("/authenticated")]
+fn handler(user: &User) -> String {
+    let mut roles = user.roles.iter().collect::<Vec<_>>();
+    roles.sort();
+
+    format!("{} {}", user.username, roles.join(","))
+}
+
+fn setup(server: AuthorizationServer) -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_authorization_server(server)
+        .add_scheme(JwtBearer::new(JwtConfig::hmac(b"secret")))
+        .add_scheme(CookieScheme::default())
+        .build();
+
+    rocket::build()
+        .mount("/", routes![login, handler])
+        .mount("/oauth", oauth::routes())
+        .attach(Identity::fairing(config))
+}
+
+async fn setup_client(server: &AuthorizationServer) -> Client {
+    let client = Client::tracked(setup(server.clone()))
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    users
+        .add_user(&User::with_username("alice"), Some("password1"))
+        .await
+        .unwrap();
+
+    client
+}
+
+async fn token_request<'c>(
+    client: &'c Client,
+    credentials: Option<(&str, &str)>,
+    params: &[(&str, &str)],
+) -> LocalResponse<'c> {
+    let body = params
+        .iter()
+        .map(|(name, value)| format!("{}={}", name, value))
+        .collect::<Vec<_>>()
+        .join("&");
+
+    let mut req = client
+        .post("/oauth/token")
+        .header(ContentType::Form)
+        .body(body);
+
+    if let Some((client_id, secret)) = credentials {
+        let credentials = BASE64.encode(format!("{}:{}", client_id, secret));
+        req = req.header(Header::new(
+            "Authorization",
+            format!("Basic {}", credentials),
+        ));
+    }
+
+    req.dispatch().await
+}
+
+async fn json(res: LocalResponse<'_>) -> Value {
+    serde_json::from_str(&res.into_string().await.unwrap()).unwrap()
+}
+
+async fn authenticated_with(client: &Client, access_token: &Value) -> String {
+    let res = client
+        .get("/authenticated")
+        .header(Header::new(
+            "Authorization",
+            format!("Bearer {}", access_token.as_str().unwrap()),
+        ))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    res.into_string().await.unwrap()
+}
+
+#[rocket::async_test]
+async fn password_grant_issues_rotating_refresh_tokens() {
+    let server = AuthorizationServer::new(MemoryOAuthStore::new());
+    let secret = server
+        .register_confidential_client(
+            OAuthClient::new("legacy")
+                .with_grant_types(&[GrantType::Password, GrantType::RefreshToken])
+                .with_scopes(&["read", "write"]),
+        )
+        .await
+        .unwrap();
+    let client = setup_client(&server).await;
+
+    let res = token_request(
+        &client,
+        Some(("legacy", &secret)),
+        &[
+            ("grant_type", "password"),
+            ("username", "alice"),
+            ("password", "password1"),
+            ("scope", "read"),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.headers().get_one("Cache-Control"), Some("no-store"));
+
+    let body = json(res).await;
+    assert_eq!(body["token_type"], "Bearer");
+    assert_eq!(body["scope"], "read");
+    assert_eq!(body["expires_in"], 3600);
+    assert_eq!(
+        authenticated_with(&client, &body["access_token"]).await,
+        "alice "
+    );
+
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_owned();
+    let refresh = [
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token.as_str()),
+    ];
+
+    let res = token_request(&client, Some(("legacy", &secret)), &refresh).await;
+    assert_eq!(res.status(), Status::Ok);
+    let body = json(res).await;
+    assert_eq!(body["scope"], "read");
+    assert_ne!(body["refresh_token"].as_str().unwrap(), refresh_token);
+
+    // Refresh tokens are rotated
+    let res = token_request(&client, Some(("legacy", &secret)), &refresh).await;
+    assert_eq!(res.status(), Status::BadRequest);
+    assert_eq!(json(res).await["error"], "invalid_grant");
+
+    // Changing the password revokes outstanding refresh tokens
+    let refresh_token = body["refresh_token"].as_str().unwrap().to_owned();
+    let users = client.rocket().user_repository().await;
+    let user = users.find_by_username("alice").await.unwrap().unwrap();
+    users
+        .change_password(&user, "password1", "password2")
+        .await
+        .unwrap();
+
+    let res = token_request(
+        &client,
+        Some(("legacy", &secret)),
+        &[
+            ("grant_type", "refresh_token"),
+            ("refresh_token", refresh_token.as_str()),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), Status::BadRequest);
+
+    // Wrong passwords and grants the client may not use are rejected
+    let res = token_request(
+        &client,
+        Some(("legacy", &secret)),
+        &[
+            ("grant_type", "password"),
+            ("username", "alice"),
+            ("password", "password1"),
+        ],
+    )
+    .await;
+    assert_eq!(json(res).await["error"], "invalid_grant");
+
+    let res = token_request(
+        &client,
+        Some(("legacy", &secret)),
+        &[("grant_type", "client_credentials")],
+    )
+    .await;
+    assert_eq!(json(res).await["error"], "unauthorized_client");
+}
+
+#[rocket::async_test]
+async fn client_credentials_grant_authenticates_the_client() {
+    let server = AuthorizationServer::new(MemoryOAuthStore::new());
+    let secret = server
+        .register_confidential_client(
+            OAuthClient::new("service")
+                .with_grant_types(&[GrantType::ClientCredentials])
+                .with_scopes(&["read", "write"]),
+        )
+        .await
+        .unwrap();
+    let client = setup_client(&server).await;
+
+    // Credentials may also be sent in the request body
+    let res = token_request(
+        &client,
+        None,
+        &[
+            ("grant_type", "client_credentials"),
+            ("client_id", "service"),
+            ("client_secret", &secret),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let body = json(res).await;
+    assert_eq!(body["scope"], "read write");
+    assert!(body.get("refresh_token").is_none());
+    assert_eq!(
+        authenticated_with(&client, &body["access_token"]).await,
+        "client:service read,write"
+    );
+
+    let res = token_request(
+        &client,
+        Some(("service", &secret)),
+        &[("grant_type", "client_credentials"), ("scope", "admin")],
+    )
+    .await;
+    assert_eq!(res.status(), Status::BadRequest);
+    assert_eq!(json(res).await["error"], "invalid_scope");
+
+    let res = token_request(
+        &client,
+        Some(("service", "wrong")),
+        &[("grant_type", "client_credentials")],
+    )
+    .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+    assert_eq!(json(res).await["error"], "invalid_client");
+
+    let res = token_request(&client, None, &[("grant_type", "client_credentials")]).await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    // The scheme token is case insensitive
+    let credentials = BASE64.encode(format!("service:{}", secret));
+    let res = client
+        .post("/oauth/token")
+        .header(ContentType::Form)
+        .header(Header::new(
+            "Authorization",
+            format!("basic  {} ", credentials),
+        ))
+        .body("grant_type=client_credentials")
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn clients_named_like_users_cannot_act_on_their_own_behalf() {
+    let server = AuthorizationServer::new(MemoryOAuthStore::new());
+    let secret = server
+        .register_confidential_client(
+            OAuthClient::new("alice").with_grant_types(&[GrantType::ClientCredentials]),
+        )
+        .await
+        .unwrap();
+    let client = setup_client(&server).await;
+
+    let res = token_request(
+        &client,
+        Some(("alice", &secret)),
+        &[("grant_type", "client_credentials")],
+    )
+    .await;
+    assert_eq!(res.status(), Status::BadRequest);
+    assert_eq!(json(res).await["error"], "unauthorized_client");
+}
+
+#[rocket::async_test]
+async fn grant_types_can_be_disabled_for_all_clients() {
+    let mut server = AuthorizationServer::new(MemoryOAuthStore::new());
+    server.grant_types = vec![GrantType::AuthorizationCode, GrantType::RefreshToken];
+    let secret = server
+        .register_confidential_client(
+            OAuthClient::new("legacy").with_grant_types(&[GrantType::Password]),
+        )
+        .await
+        .unwrap();
+    let client = setup_client(&server).await;
+
+    let res = token_request(
+        &client,
+        Some(("legacy", &secret)),
+        &[
+            ("grant_type", "password"),
+            ("username", "alice"),
+            ("password", "password1"),
+        ],
+    )
+    .await;
+    assert_eq!(res.status(), Status::BadRequest);
+    assert_eq!(json(res).await["error"], "unsupported_grant_type");
+}
+
+#[rocket::async_test]
+async fn authorization_code_grant_requires_pkce() {
+    let server = AuthorizationServer::new(MemoryOAuthStore::new());
+    server
+        .register_client(
+            OAuthClient::new("spa")
+                .with_redirect_uris(&[REDIRECT_URI])
+                .with_scopes(&["read"]),
+        )
+        .await
+        .unwrap();
+    let client = setup_client(&server).await;
+
+    let code_verifier = "verifier-with-enough-entropy-0123456789";
+    let code_challenge = BASE64URL_NOPAD.encode(&Sha256::digest(code_verifier));
+    let authorize = format!(
+        "/oauth/authorize?response_type=code&client_id=spa&redirect_uri={}&state=state1&code_challenge={}&code_challenge_method=S256",
+        REDIRECT_URI, code_challenge
+    );
+
+    // The user has to be signed in
+    let res = client.get(&authorize).dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    let res = client
+        .post("/login?username=alice&password=password1")
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let authorize_code = || async {
+        let res = client.get(&authorize).dispatch().await;
+        assert_eq!(res.status(), Status::SeeOther);
+
+        let location = res.headers().get_one("Location").unwrap().to_owned();
+        let query = location
+            .strip_prefix(&format!("{}?", REDIRECT_URI))
+            .unwrap()
+            .to_owned();
+        let (code, state) = query.split_once('&').unwrap();
+        assert_eq!(state, "state=state1");
+
+        code.strip_prefix("code=").unwrap().to_owned()
+    };
+
+    // A wrong code verifier consumes the code
+    let code = authorize_code().await;
+    let redeem = |code: String, code_verifier: &'static str| {
+        let client = &client;
+        async move {
+            token_request(
+                client,
+                None,
+                &[
+                    ("grant_type", "authorization_code"),
+                    ("client_id", "spa"),
+                    ("code", &code),
+                    ("redirect_uri", REDIRECT_URI),
+                    ("code_verifier", code_verifier),
+                ],
+            )
+            .await
+        }
+    };
+
+    let res = redeem(code.clone(), "wrong-verifier").await;
+    assert_eq!(json(res).await["error"], "invalid_grant");
+    let res = redeem(code, code_verifier).await;
+    assert_eq!(json(res).await["error"], "invalid_grant");
+
+    let code = authorize_code().await;
+    let res = redeem(code.clone(), code_verifier).await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let body = json(res).await;
+    assert_eq!(body["scope"], "read");
+    assert!(body["refresh_token"].is_string());
+    assert_eq!(
+        authenticated_with(&client, &body["access_token"]).await,
+        "alice "
+    );
+
+    // Codes can only be redeemed once
+    let res = redeem(code, code_verifier).await;
+    assert_eq!(json(res).await["error"], "invalid_grant");
+
+    // Public clients have to send a code challenge
+    let res = client
+        .get(format!(
+            "/oauth/authorize?response_type=code&client_id=spa&state=state2&redirect_uri={}",
+            REDIRECT_URI
+        ))
+        .dispatch()
+        .await;
+    assert_eq!(
+        res.headers().get_one("Location"),
+        Some(format!("{}?error=invalid_request&state=state2", REDIRECT_URI).as_str())
+    );
+
+    // Unregistered redirect URIs are not redirected to
+    let res = client
+        .get("/oauth/authorize?response_type=code&client_id=spa&redirect_uri=https://evil.example.com")
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::BadRequest);
+    assert_eq!(json(res).await["error"], "invalid_request");
+}
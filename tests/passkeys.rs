@@ -0,0 +1,316 @@
+use ciborium::value::Value;
+use data_encoding::BASE64URL_NOPAD;
+use p256::ecdsa::{signature::Signer, Signature, SigningKey};
+use rand::rngs::OsRng;
+use rocket::{
+    get,
+    http::{ContentType, Status},
+    local::asynchronous::Client,
+    post, routes,
+    serde::json::{serde_json, Json},
+    time::OffsetDateTime,
+    Build, Rocket,
+};
+use rocket_identity::{
+    schemes::cookie::{CookieScheme, CookieSession},
+    stores::memory::MemoryStore,
+    two_factor::Totp,
+    webauthn::{
+        AssertionCredential, AssertionResponse, AttestationResponse, CreationOptions,
+        RegistrationCredential, RequestOptions, WebAuthnError,
+    },
+    Identity, LoginError, PasskeyError, Services, User, UserRepository,
+};
+use sha2::{Digest, Sha256};
+
+const ORIGIN: &str = "http://localhost:8000";
+
+/// A software authenticator holding a single ES256 credential.
+struct Authenticator {
+    credential_id: Vec<u8>,
+    key: SigningKey,
+    sign_count: u32,
+}
+
+impl Authenticator {
+    fn new() -> Self {
+        Self {
+            credential_id: b"software-credential".to_vec(),
+            key: SigningKey::random(&mut OsRng),
+            sign_count: 0,
+        }
+    }
+
+    fn create(&mut self, options: &CreationOptions) -> RegistrationCredential {
+        let point = self.key.verifying_key().to_encoded_point(false);
+        let cose_key = Value::Map(vec![
+            (1.into(), 2.into()),
+            (3.into(), (-7).into()),
+            ((-1).into(), 1.into()),
+            ((-2).into(), Value::Bytes(point.x().unwrap().to_vec())),
+            ((-3).into(), Value::Bytes(point.y().unwrap().to_vec())),
+        ]);
+
+        let mut auth_data = self.authenticator_data(&options.rp.id, 0x41);
+        auth_data.extend_from_slice(&[0; 16]);
+        auth_data.extend_from_slice(&(self.credential_id.len() as u16).to_be_bytes());
+        auth_data.extend_from_slice(&self.credential_id);
+        ciborium::ser::into_writer(&cose_key, &mut auth_data).unwrap();
+
+        let attestation = Value::Map(vec![
+            ("fmt".into(), "none".into()),
+            ("attStmt".into(), Value::Map(vec![])),
+            ("authData".into(), Value::Bytes(auth_data)),
+        ]);
+        let mut attestation_object = Vec::new();
+        ciborium::ser::into_writer(&attestation, &mut attestation_object).unwrap();
+
+        RegistrationCredential {
+            id: BASE64URL_NOPAD.encode(&self.credential_id),
+            raw_id: BASE64URL_NOPAD.encode(&self.credential_id),
+            kind: "public-key".to_owned(),
+            response: AttestationResponse {
+                client_data_json: client_data("webauthn.create", &options.challenge),
+                attestation_object: BASE64URL_NOPAD.encode(&attestation_object),
+            },
+        }
+    }
+
+    fn get(&mut self, options: &RequestOptions) -> AssertionCredential {
+        self.sign_count += 1;
+
+        let auth_data = self.authenticator_data(&options.rp_id, 0x01);
+        let client_data_json = client_data("webauthn.get", &options.challenge);
+
+        let mut message = auth_data.clone();
+        message.extend_from_slice(&Sha256::digest(
+            BASE64URL_NOPAD.decode(client_data_json.as_bytes()).unwrap(),
+        ));
+        let signature: Signature = self.key.sign(&message);
+
+        AssertionCredential {
+            id: BASE64URL_NOPAD.encode(&self.credential_id),
+            raw_id: BASE64URL_NOPAD.encode(&self.credential_id),
+            kind: "public-key".to_owned(),
+            response: AssertionResponse {
+                client_data_json,
+                authenticator_data: BASE64URL_NOPAD.encode(&auth_data),
+                signature: BASE64URL_NOPAD.encode(signature.to_der().as_bytes()),
+                user_handle: None,
+            },
+        }
+    }
+
+    fn authenticator_data(&self, rp_id: &str, flags: u8) -> Vec<u8> {
+        let mut data = Sha256::digest(rp_id.as_bytes()).to_vec();
+        data.push(flags);
+        data.extend_from_slice(&self.sign_count.to_be_bytes());
+        data
+    }
+}
+
+fn client_data(kind: &str, challenge: &str) -> String {
+    let json = serde_json::json!({
+        "type": kind,
+        "challenge": challenge,
+        "origin": ORIGIN,
+    });
+
+    BASE64URL_NOPAD.encode(json.to_string().as_bytes())
+}
+
+#[post("/passkey/begin")]
+async fn begin(users: &UserRepository, session: CookieSession<'_>) -> Json<RequestOptions> {
+    Json(session.begin_passkey_sign_in(users).await.unwrap())
+}
+
+#[post("/passkey/complete", data = "<credential>")]
+async fn complete(
+    credential: Json<AssertionCredential>,
+    users: &UserRepository,
+    session: CookieSession<'_>,
+) -> Status {
+    match session.complete_passkey_sign_in(users, &credential).await {
+        Ok(_) => Status::Ok,
+        Err(_) => Status::Unauthorized,
+    }
+}
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup() -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(CookieScheme::default())
+        .build();
+
+    rocket::build()
+        .mount("/", routes![begin, complete, handler])
+        .attach(Identity::fairing(config))
+}
+
+async fn register(users: &UserRepository, user: &User, authenticator: &mut Authenticator) {
+    let (options, challenge) = users.start_passkey_registration(user).await.unwrap();
+    let credential = authenticator.create(&options);
+
+    users
+        .finish_passkey_registration(user, &challenge, &credential)
+        .await
+        .expect("Could not register passkey");
+}
+
+#[rocket::async_test]
+async fn registered_passkey_authenticates_user() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+    let mut authenticator = Authenticator::new();
+
+    let user = User::with_username("user1");
+    users.add_user(&user, None).await.unwrap();
+    register(&users, &user, &mut authenticator).await;
+
+    let passkeys = users.passkeys(&user).await.unwrap();
+    assert_eq!(passkeys.len(), 1);
+    assert_eq!(passkeys[0].credential_id, authenticator.credential_id);
+
+    // The same authenticator cannot be registered twice
+    let (options, _) = users.start_passkey_registration(&user).await.unwrap();
+    assert_eq!(options.exclude_credentials.len(), 1);
+
+    let (options, challenge) = users.start_passkey_authentication(None).await.unwrap();
+    let credential = authenticator.get(&options);
+    let signed_in = users
+        .finish_passkey_authentication(&challenge, &credential)
+        .await
+        .expect("Could not verify assertion");
+    assert_eq!(signed_in.username, "user1");
+    assert_eq!(users.passkeys(&user).await.unwrap()[0].sign_count, 1);
+
+    // Replaying the assertion is rejected by the signature counter
+    assert!(matches!(
+        users
+            .finish_passkey_authentication(&challenge, &credential)
+            .await,
+        Err(PasskeyError::WebAuthn(WebAuthnError::SignCountRegression))
+    ));
+
+    // An assertion for a different challenge is rejected
+    let (options, _) = users.start_passkey_authentication(None).await.unwrap();
+    let (_, other_challenge) = users.start_passkey_authentication(None).await.unwrap();
+    let credential = authenticator.get(&options);
+    assert!(matches!(
+        users
+            .finish_passkey_authentication(&other_challenge, &credential)
+            .await,
+        Err(PasskeyError::WebAuthn(WebAuthnError::ChallengeMismatch))
+    ));
+}
+
+#[rocket::async_test]
+async fn assertion_from_unknown_key_is_rejected() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+    let mut authenticator = Authenticator::new();
+
+    let user = User::with_username("user1");
+    users.add_user(&user, None).await.unwrap();
+    register(&users, &user, &mut authenticator).await;
+
+    // Same credential id, but a different private key
+    let mut impostor = Authenticator::new();
+    impostor.sign_count = 10;
+
+    let (options, challenge) = users.start_passkey_authentication(None).await.unwrap();
+    let credential = impostor.get(&options);
+    assert!(matches!(
+        users
+            .finish_passkey_authentication(&challenge, &credential)
+            .await,
+        Err(PasskeyError::WebAuthn(WebAuthnError::InvalidSignature))
+    ));
+}
+
+#[rocket::async_test]
+async fn passkey_sign_in_applies_login_requirements() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+    let mut authenticator = Authenticator::new();
+
+    let user = User::with_username("user1");
+    users.add_user(&user, None).await.unwrap();
+    register(&users, &user, &mut authenticator).await;
+
+    let secret = users.create_totp_secret(&user).await.unwrap();
+    users
+        .enable_two_factor(
+            &user,
+            &Totp::default().code_at(&secret, OffsetDateTime::now_utc()),
+        )
+        .await
+        .unwrap();
+
+    let (options, challenge) = users.start_passkey_authentication(None).await.unwrap();
+    let credential = authenticator.get(&options);
+    assert!(matches!(
+        users
+            .finish_passkey_authentication(&challenge, &credential)
+            .await,
+        Err(PasskeyError::Login(LoginError::TwoFactorRequired { .. }))
+    ));
+}
+
+#[rocket::async_test]
+async fn passkey_sign_in_creates_cookie_session() {
+    let client = Client::tracked(setup())
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+    let mut authenticator = Authenticator::new();
+
+    let user = User::with_username("user1");
+    users.add_user(&user, None).await.unwrap();
+    register(&users, &user, &mut authenticator).await;
+
+    let options: RequestOptions = client
+        .post("/passkey/begin")
+        .dispatch()
+        .await
+        .into_json()
+        .await
+        .unwrap();
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+
+    let credential = authenticator.get(&options);
+    let res = client
+        .post("/passkey/complete")
+        .header(ContentType::JSON)
+        .body(serde_json::to_string(&credential).unwrap())
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.into_string().await.unwrap(), "user1");
+
+    // The challenge was consumed
+    let credential = authenticator.get(&options);
+    let res = client
+        .post("/passkey/complete")
+        .header(ContentType::JSON)
+        .body(serde_json::to_string(&credential).unwrap())
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
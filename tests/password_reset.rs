@@ -0,0 +1,94 @@
+use rocket::local::asynchronous::Client;
+use rocket_identity::{
+    notifiers::{memory::MemoryNotifier, Notification},
+    stores::memory::MemoryStore,
+    tokens::TokenError,
+    Identity, PasswordResetError, Services, User,
+};
+
+#[rocket::async_test]
+async fn reset_token_is_single_use() {
+    let notifier = MemoryNotifier::new();
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_notifier(notifier.clone())
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("user1");
+    users
+        .add_user(&user, Some("password1"))
+        .await
+        .expect("Could not add user");
+
+    users
+        .send_password_reset(&user)
+        .await
+        .expect("Could not send password reset");
+
+    let Some(Notification::PasswordReset {
+        user: recipient,
+        token,
+    }) = notifier.last()
+    else {
+        panic!("Expected a password reset notification");
+    };
+    assert_eq!(recipient.username, "user1");
+
+    assert!(matches!(
+        users.reset_password(&user, "invalid", "password2").await,
+        Err(PasswordResetError::InvalidToken(TokenError::Invalid))
+    ));
+
+    users
+        .reset_password(&user, &token, "password2")
+        .await
+        .expect("Could not reset password");
+
+    assert!(users.authenticate("user1", "password1").await.is_err());
+    assert!(users.authenticate("user1", "password2").await.is_ok());
+
+    assert!(matches!(
+        users.reset_password(&user, &token, "password3").await,
+        Err(PasswordResetError::InvalidToken(TokenError::Invalid))
+    ));
+}
+
+#[rocket::async_test]
+async fn changing_password_invalidates_reset_token() {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("user1");
+    users.add_user(&user, Some("password1")).await.unwrap();
+
+    let token = users.generate_password_reset_token(&user).await.unwrap();
+
+    users
+        .change_password(&user, "password1", "password2")
+        .await
+        .unwrap();
+
+    assert!(matches!(
+        users.reset_password(&user, &token, "password3").await,
+        Err(PasswordResetError::InvalidToken(TokenError::Invalid))
+    ));
+
+    // Without a notifier, tokens can still be generated but not sent
+    assert!(matches!(
+        users.send_password_reset(&user).await,
+        Err(PasswordResetError::Notifier(_))
+    ));
+}
@@ -0,0 +1,139 @@
+use std::net::SocketAddr;
+
+use rocket::{
+    get,
+    http::{Header, Status},
+    local::asynchronous::Client,
+    routes,
+};
+use rocket_identity::{
+    schemes::{proxy_header::ProxyHeader, TrustedProxies},
+    stores::memory::MemoryStore,
+    Identity, Roles, Services, User,
+};
+
+const GATEWAY: &str = "10.0.0.2:4180";
+
+#[get("/authenticated")]
+fn handler(user: &User) -> String {
+    let mut roles = user.roles.iter().collect::<Vec<_>>();
+    roles.sort();
+
+    format!("{} {}", user.username, roles.join(","))
+}
+
+async fn setup(configure: impl FnOnce(ProxyHeader) -> ProxyHeader) -> Client {
+    let trusted_proxies = TrustedProxies::new(&["10.0.0.0/24"]).unwrap();
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(configure(ProxyHeader::new(trusted_proxies)))
+        .build();
+
+    let rocket = rocket::build()
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config));
+
+    Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client")
+}
+
+async fn authenticate(
+    client: &Client,
+    remote: &str,
+    user: &str,
+    groups: Option<&str>,
+) -> (Status, String) {
+    let mut req = client
+        .get("/authenticated")
+        .remote(remote.parse::<SocketAddr>().unwrap())
+        .header(Header::new("X-Forwarded-User", user.to_owned()));
+    if let Some(groups) = groups {
+        req = req.header(Header::new("X-Forwarded-Groups", groups.to_owned()));
+    }
+
+    let res = req.dispatch().await;
+
+    (res.status(), res.into_string().await.unwrap_or_default())
+}
+
+#[rocket::async_test]
+async fn forwarded_users_authenticate_from_trusted_proxies() {
+    let client = setup(|scheme| scheme).await;
+
+    assert_eq!(
+        authenticate(&client, GATEWAY, "alice", Some("admins, developers")).await,
+        (Status::Ok, "alice admins,developers".to_owned())
+    );
+
+    // Clients cannot set the headers themselves
+    assert_eq!(
+        authenticate(&client, "10.0.1.2:4180", "alice", Some("admins"))
+            .await
+            .0,
+        Status::Unauthorized
+    );
+
+    assert_eq!(
+        authenticate(&client, GATEWAY, " ", None).await.0,
+        Status::BadRequest
+    );
+
+    // Users are not stored by default
+    let users = client.rocket().user_repository().await;
+    assert!(users.find_by_username("alice").await.unwrap().is_none());
+}
+
+#[rocket::async_test]
+async fn groups_map_to_roles() {
+    let client = setup(|scheme| {
+        scheme
+            .with_groups_header("X-Forwarded-Groups")
+            .map_group("admins", "admin")
+            .map_group("admins", "editor")
+            .map_group("writers", "editor")
+    })
+    .await;
+
+    assert_eq!(
+        authenticate(&client, GATEWAY, "alice", Some("admins,writers,guests")).await,
+        (Status::Ok, "alice admin,editor".to_owned())
+    );
+    assert_eq!(
+        authenticate(&client, GATEWAY, "bob", Some("guests")).await,
+        (Status::Ok, "bob ".to_owned())
+    );
+}
+
+#[rocket::async_test]
+async fn users_can_be_required_or_provisioned() {
+    let client = setup(ProxyHeader::existing_users).await;
+    let users = client.rocket().user_repository().await;
+
+    let mut user = User::with_username("alice");
+    user.roles = Roles::from(vec!["billing"]);
+    users.add_user(&user, None).await.unwrap();
+
+    assert_eq!(
+        authenticate(&client, GATEWAY, "alice", Some("admins")).await,
+        (Status::Ok, "alice admins,billing".to_owned())
+    );
+    assert_eq!(
+        authenticate(&client, GATEWAY, "bob", None).await.0,
+        Status::Unauthorized
+    );
+
+    let client = setup(ProxyHeader::auto_provision).await;
+    let users = client.rocket().user_repository().await;
+
+    assert_eq!(
+        authenticate(&client, GATEWAY, "bob", Some("developers")).await,
+        (Status::Ok, "bob developers".to_owned())
+    );
+    assert!(users.find_by_username("bob").await.unwrap().is_some());
+
+    assert_eq!(
+        authenticate(&client, GATEWAY, "bob", None).await,
+        (Status::Ok, "bob ".to_owned())
+    );
+}
@@ -0,0 +1,72 @@
+use std::time::Duration;
+
+use rocket::{
+    fairing::AdHoc,
+    get,
+    http::{Header, Status},
+    local::blocking::Client,
+    routes, Build, Rocket,
+};
+use rocket_identity::{
+    rate_limit::{RateLimitOptions, TokenBucket},
+    schemes::basic::Basic,
+    stores::memory::MemoryStore,
+    {Identity, Services, User},
+};
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup() -> Rocket<Build> {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_rate_limit_options(RateLimitOptions {
+            per_ip: None,
+            per_username: Some(TokenBucket::new(2, Duration::from_secs(60))),
+        })
+        .add_scheme(Basic::new("Server"))
+        .build();
+
+    rocket::build()
+        .mount("/", routes![handler])
+        .attach(Identity::fairing(config))
+        .attach(AdHoc::on_liftoff("User setup", |r| Box::pin(initialize(r))))
+}
+
+async fn initialize(rocket: &Rocket<rocket::Orbit>) {
+    let users = rocket.user_repository().await;
+
+    users
+        .add_user(&User::with_username("user1"), Some("pass1"))
+        .await
+        .expect("Could not add user");
+}
+
+#[test]
+fn throttled_request_fails_with_retry_after() {
+    let rocket = setup();
+    let client = Client::tracked(rocket).expect("Failed to acquire Client");
+
+    for _ in 0..2 {
+        let mut req = client.get("/authenticated");
+        req.add_header(Header::new("Authorization", "Basic dXNlcjE6cGFzczE=")); // user1:pass1
+        assert_eq!(req.dispatch().status(), Status::Ok);
+    }
+
+    let mut req = client.get("/authenticated");
+    req.add_header(Header::new("Authorization", "Basic dXNlcjE6cGFzczE=")); // user1:pass1
+    let res = req.dispatch();
+
+    assert_eq!(res.status(), Status::TooManyRequests);
+
+    // The bucket refills a little while the previous logins are hashed
+    let retry_after = res
+        .headers()
+        .get_one("Retry-After")
+        .and_then(|r| r.parse::<u64>().ok())
+        .expect("Missing Retry-After");
+    assert!((1..=60).contains(&retry_after), "{}", retry_after);
+    assert!(!res.headers().contains("WWW-Authenticate"));
+}
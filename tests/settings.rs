@@ -0,0 +1,237 @@
+use rocket::{
+    error::ErrorKind,
+    figment::{
+        providers::{Format, Toml},
+        Figment,
+    },
+    get,
+    http::{Header, SameSite, Status},
+    local::asynchronous::Client,
+    post, routes,
+    time::Duration,
+    Build, Rocket,
+};
+use rocket_identity::{
+    config::{ConfigBuilder, MissingAuthPolicy},
+    schemes::{
+        cookie::{CookieScheme, CookieSession},
+        jwt::JwtTokenProvider,
+        AuthenticationScheme, Outcome,
+    },
+    stores::memory::MemoryStore,
+    Identity, Services, User, UserRepository,
+};
+
+/// A scheme that merely looks like the basic scheme.
+#[derive(Debug)]
+struct LookalikeScheme;
+
+#[rocket::async_trait]
+impl AuthenticationScheme for LookalikeScheme {
+    fn name(&self) -> String {
+        "Basic(realm=lookalike)".to_owned()
+    }
+
+    async fn authenticate(&self, _req: &rocket::Request) -> Outcome {
+        Outcome::Forward(())
+    }
+
+    async fn challenge(&self, _res: &mut rocket::Response) {}
+}
+
+const SETTINGS: &str = r#"
+[identity]
+missing_auth_policy = "forward"
+
+[identity.jwt]
+secret = "jwt secret"
+issuer = "https://example.com"
+token_lifetime = 3600
+
+[identity.cookie]
+name = "session"
+same_site = "lax"
+max_age = 86400
+
+[identity.basic]
+realm = "example"
+
+[identity.argon2]
+memory_cost = 1024
+iterations = 1
+parallelism = 1
+"#;
+
+#[post("/login?<username>&<password>")]
+async fn login(
+    username: &str,
+    password: &str,
+    users: &UserRepository,
+    session: CookieSession<'_>,
+) -> Status {
+    match users.authenticate(username, password).await {
+        Ok(user) => {
+            session.sign_in(&user);
+            Status::Ok
+        }
+        Err(_) => Status::Unauthorized,
+    }
+}
+
+#[get("/token")]
+fn token(tokens: JwtTokenProvider<'_>) -> String {
+    let user = User::with_username("user1");
+
+    tokens.create_token(&user).unwrap().as_str().to_owned()
+}
+
+#[get("/authenticated")]
+fn handler(user: &User) -> &str {
+    user.username.as_str()
+}
+
+fn setup(settings: &str, config: &mut ConfigBuilder) -> Rocket<Build> {
+    let figment = Figment::from(rocket::Config::default()).merge(Toml::string(settings));
+    let config = config.with_user_store(MemoryStore::new()).build();
+
+    rocket::custom(figment)
+        .mount("/", routes![login, token, handler])
+        .attach(Identity::fairing(config))
+}
+
+fn scheme_names(client: &Client) -> Vec<String> {
+    client
+        .rocket()
+        .authentication_schemes()
+        .iter()
+        .map(|s| s.name())
+        .collect()
+}
+
+#[rocket::async_test]
+async fn settings_configure_identity() {
+    let client = Client::tracked(setup(SETTINGS, &mut Identity::config()))
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    assert_eq!(
+        scheme_names(&client),
+        ["JwtBearer", "Cookie(session)", "Basic(realm=example)"]
+    );
+
+    // Unauthenticated requests are forwarded
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::NotFound);
+
+    users
+        .add_user(&User::with_username("user1"), Some("password1"))
+        .await
+        .unwrap();
+
+    // Sessions use the configured cookie
+    let res = client
+        .post("/login?username=user1&password=password1")
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    let cookie = res.cookies().get("session").unwrap();
+    assert_eq!(cookie.same_site(), Some(SameSite::Lax));
+    assert_eq!(cookie.max_age(), Some(Duration::days(1)));
+    assert!(res
+        .cookies()
+        .get(CookieScheme::default_cookie_name())
+        .is_none());
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.into_string().await.unwrap(), "user1");
+
+    // Tokens are signed with the configured secret
+    let token = client
+        .get("/token")
+        .dispatch()
+        .await
+        .into_string()
+        .await
+        .unwrap();
+
+    let mut validation = jsonwebtoken::Validation::default();
+    validation.set_issuer(&["https://example.com"]);
+    let claims = jsonwebtoken::decode::<rocket::serde::json::Value>(
+        &token,
+        &jsonwebtoken::DecodingKey::from_secret(b"jwt secret"),
+        &validation,
+    )
+    .unwrap()
+    .claims;
+    assert_eq!(
+        claims["exp"].as_i64().unwrap() - claims["iat"].as_i64().unwrap(),
+        3600
+    );
+
+    let client = Client::untracked(setup(SETTINGS, &mut Identity::config()))
+        .await
+        .expect("Failed to acquire Client");
+    let res = client
+        .get("/authenticated")
+        .header(Header::new("Authorization", format!("Bearer {}", token)))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+}
+
+#[rocket::async_test]
+async fn code_settings_take_precedence() {
+    let mut config = Identity::config();
+    config
+        .with_missing_auth_policy(MissingAuthPolicy::Fail)
+        .add_scheme(CookieScheme::new("code"));
+
+    let client = Client::tracked(setup(SETTINGS, &mut config))
+        .await
+        .expect("Failed to acquire Client");
+
+    assert_eq!(
+        scheme_names(&client),
+        ["Cookie(code)", "JwtBearer", "Basic(realm=example)"]
+    );
+
+    let res = client.get("/authenticated").dispatch().await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn schemes_are_recognized_by_type() {
+    let mut config = Identity::config();
+    config.add_scheme(LookalikeScheme);
+
+    let client = Client::tracked(setup(SETTINGS, &mut config))
+        .await
+        .expect("Failed to acquire Client");
+
+    assert_eq!(
+        scheme_names(&client),
+        [
+            "Basic(realm=lookalike)",
+            "JwtBearer",
+            "Cookie(session)",
+            "Basic(realm=example)"
+        ]
+    );
+}
+
+#[rocket::async_test]
+async fn invalid_settings_prevent_launch() {
+    for settings in [
+        "[identity]\nunknown = 1",
+        "[identity.jwt]\nissuer = \"https://example.com\"",
+        "[identity.cookie]\nsame_site = \"sometimes\"",
+        "[identity.argon2]\nmemory_cost = 0\niterations = 1\nparallelism = 1",
+    ] {
+        match Client::tracked(setup(settings, &mut Identity::config())).await {
+            Ok(_) => panic!("{} should be rejected", settings),
+            Err(e) => assert!(matches!(e.kind(), ErrorKind::FailedFairings(_))),
+        }
+    }
+}
@@ -0,0 +1,287 @@
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine};
+use hmac::{Hmac, Mac};
+use rocket::{
+    get,
+    http::{Header, Status},
+    local::asynchronous::{Client, LocalRequest},
+    post, routes,
+    time::OffsetDateTime,
+};
+use rocket_identity::{
+    schemes::signature::{HmacSignature, SignedBody},
+    stores::{memory::MemoryStore, StoredSigningKey},
+    Identity, Services, User,
+};
+use sha2::{Digest, Sha256};
+
+#[get("/authenticated")]
+fn handler(user: &User) -> String {
+    let mut roles = user.roles.iter().collect::<Vec<_>>();
+    roles.sort();
+
+    format!("{} {}", user.username, roles.join(","))
+}
+
+#[post("/webhook", data = "<body>")]
+fn webhook(body: SignedBody) -> String {
+    format!(
+        "{} {}",
+        body.user().username,
+        String::from_utf8(body.bytes().to_vec()).unwrap()
+    )
+}
+
+#[post("/unverified", data = "<body>")]
+fn unverified(user: &User, body: String) -> String {
+    format!("{} {}", user.username, body)
+}
+
+async fn setup(scheme: HmacSignature) -> (Client, StoredSigningKey) {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .add_scheme(scheme)
+        .build();
+
+    let rocket = rocket::build()
+        .mount("/", routes![handler, webhook, unverified])
+        .attach(Identity::fairing(config));
+
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("partner");
+    users.add_user(&user, None).await.unwrap();
+    let key = users
+        .create_signing_key(&user, "Partner webhooks", &["webhooks"])
+        .await
+        .unwrap();
+
+    (client, key)
+}
+
+/// Sign a request like a client would.
+fn sign<'c>(
+    req: LocalRequest<'c>,
+    key_id: &str,
+    secret: &str,
+    timestamp: i64,
+    nonce: &str,
+    headers: &[&str],
+) -> LocalRequest<'c> {
+    let mut lines = vec![
+        req.inner().method().as_str().to_owned(),
+        req.inner().uri().to_string(),
+        timestamp.to_string(),
+        nonce.to_owned(),
+    ];
+    for name in headers {
+        let value = req.inner().headers().get_one(name).unwrap();
+        lines.push(format!("{}:{}", name, value));
+    }
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(lines.join("\n").as_bytes());
+    let signature = BASE64.encode(mac.finalize().into_bytes());
+
+    let authorization = format!(
+        r#"HMAC-SHA256 keyId="{}", timestamp="{}", nonce="{}", headers="{}", signature="{}""#,
+        key_id,
+        timestamp,
+        nonce,
+        headers.join(" "),
+        signature
+    );
+
+    req.header(Header::new("Authorization", authorization))
+}
+
+fn now() -> i64 {
+    OffsetDateTime::now_utc().unix_timestamp()
+}
+
+fn content_digest(body: &str) -> Header<'static> {
+    Header::new(
+        "Content-Digest",
+        format!("sha-256=:{}:", BASE64.encode(Sha256::digest(body))),
+    )
+}
+
+#[rocket::async_test]
+async fn signed_requests_authenticate() {
+    let (client, key) = setup(HmacSignature::new()).await;
+
+    let res = sign(
+        client.get("/authenticated"),
+        &key.id,
+        &key.secret,
+        now(),
+        "n1",
+        &[],
+    )
+    .dispatch()
+    .await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(res.into_string().await.unwrap(), "partner webhooks");
+
+    // Nonces cannot be reused
+    let res = sign(
+        client.get("/authenticated"),
+        &key.id,
+        &key.secret,
+        now(),
+        "n1",
+        &[],
+    )
+    .dispatch()
+    .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+    assert_eq!(
+        res.headers().get_one("WWW-Authenticate"),
+        Some("HMAC-SHA256")
+    );
+
+    let wrong_secret = sign(
+        client.get("/authenticated"),
+        &key.id,
+        "wrong",
+        now(),
+        "n2",
+        &[],
+    );
+    assert_eq!(wrong_secret.dispatch().await.status(), Status::Unauthorized);
+
+    let expired = sign(
+        client.get("/authenticated"),
+        &key.id,
+        &key.secret,
+        now() - 600,
+        "n3",
+        &[],
+    );
+    assert_eq!(expired.dispatch().await.status(), Status::Unauthorized);
+
+    let unknown_key = sign(
+        client.get("/authenticated"),
+        "unknown",
+        &key.secret,
+        now(),
+        "n4",
+        &[],
+    );
+    assert_eq!(unknown_key.dispatch().await.status(), Status::Unauthorized);
+
+    let res = client
+        .get("/authenticated")
+        .header(Header::new(
+            "Authorization",
+            format!(r#"HMAC-SHA256 keyId="{}", nonce="n5""#, key.id),
+        ))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::BadRequest);
+
+    // Revoked keys are not accepted anymore
+    let users = client.rocket().user_repository().await;
+    let user = users.find_by_username("partner").await.unwrap().unwrap();
+    users.revoke_signing_key(&user, &key.id).await.unwrap();
+
+    let revoked = sign(
+        client.get("/authenticated"),
+        &key.id,
+        &key.secret,
+        now(),
+        "n6",
+        &[],
+    );
+    assert_eq!(revoked.dispatch().await.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn signed_bodies_are_verified() {
+    let (client, key) = setup(HmacSignature::new()).await;
+    let body = r#"{"event":"paid"}"#;
+
+    let req = client
+        .post("/webhook")
+        .header(content_digest(body))
+        .body(body);
+    let res = sign(req, &key.id, &key.secret, now(), "n1", &["content-digest"])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+    assert_eq!(
+        res.into_string().await.unwrap(),
+        format!("partner {}", body)
+    );
+
+    // The body has to match the signed digest
+    let req = client
+        .post("/webhook")
+        .header(content_digest(body))
+        .body(r#"{"event":"refunded"}"#);
+    let res = sign(req, &key.id, &key.secret, now(), "n2", &["content-digest"])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::BadRequest);
+
+    // The digest has to be signed
+    let req = client
+        .post("/webhook")
+        .header(content_digest(body))
+        .body(body);
+    let res = sign(req, &key.id, &key.secret, now(), "n3", &[])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::BadRequest);
+
+    // Bodies without a digest are rejected
+    let req = client.post("/webhook").body(body);
+    let res = sign(req, &key.id, &key.secret, now(), "n4", &[])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::BadRequest);
+
+    // Routes cannot accept a signed body without verifying it
+    let req = client
+        .post("/unverified")
+        .header(content_digest(body))
+        .body(body);
+    let res = sign(req, &key.id, &key.secret, now(), "n5", &["content-digest"])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
+
+#[rocket::async_test]
+async fn required_headers_have_to_be_signed() {
+    let (client, key) = setup(HmacSignature::new().with_required_headers(&["X-Request-Id"])).await;
+
+    let req = client
+        .get("/authenticated")
+        .header(Header::new("X-Request-Id", "42"));
+    let res = sign(req, &key.id, &key.secret, now(), "n1", &[])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::BadRequest);
+
+    let req = client
+        .get("/authenticated")
+        .header(Header::new("X-Request-Id", "42"));
+    let res = sign(req, &key.id, &key.secret, now(), "n2", &["x-request-id"])
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Ok);
+
+    // Signed headers cannot be changed
+    let req = client
+        .get("/authenticated")
+        .header(Header::new("X-Request-Id", "42"));
+    let req = sign(req, &key.id, &key.secret, now(), "n3", &["x-request-id"]);
+    let res = req
+        .header(Header::new("X-Request-Id", "43"))
+        .dispatch()
+        .await;
+    assert_eq!(res.status(), Status::Unauthorized);
+}
@@ -0,0 +1,120 @@
+use std::sync::{
+    atomic::{AtomicUsize, Ordering},
+    Arc,
+};
+
+use rocket::local::asynchronous::Client;
+use rocket_identity::{
+    config::LockoutOptions,
+    hashers::{identity::IdentityPasswordHasher, PasswordHash, PasswordHasher},
+    stores::memory::MemoryStore,
+    util::Result,
+    Identity, LoginError, Services, User,
+};
+
+/// Counts how often a password is hashed and verified.
+#[derive(Debug, Clone, Default)]
+struct CountingHasher {
+    hashes: Arc<AtomicUsize>,
+    verifications: Arc<AtomicUsize>,
+}
+
+impl PasswordHasher for CountingHasher {
+    fn hash_password(&self, user: &User, password: &str) -> Result<PasswordHash> {
+        self.hashes.fetch_add(1, Ordering::SeqCst);
+        IdentityPasswordHasher.hash_password(user, password)
+    }
+
+    fn verify_password(
+        &self,
+        user: &User,
+        password_hash: &PasswordHash,
+        password: &str,
+    ) -> Result<bool> {
+        self.verifications.fetch_add(1, Ordering::SeqCst);
+        IdentityPasswordHasher.verify_password(user, password_hash, password)
+    }
+}
+
+#[rocket::async_test]
+async fn authenticate_verifies_a_hash_in_every_branch() {
+    let hasher = CountingHasher::default();
+    let verifications = hasher.verifications.clone();
+
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_password_hasher(hasher)
+        .with_lockout_options(LockoutOptions {
+            max_failed_attempts: 1,
+            ..LockoutOptions::recommended()
+        })
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    users
+        .add_user(&User::with_username("user1"), Some("pass1"))
+        .await
+        .expect("Could not add user");
+    users
+        .add_user(&User::with_username("nopass"), None)
+        .await
+        .expect("Could not add user");
+
+    let expect_verification = |result: std::result::Result<User, LoginError>| {
+        assert_eq!(verifications.swap(0, Ordering::SeqCst), 1, "{:?}", result);
+        result
+    };
+
+    assert!(matches!(
+        expect_verification(users.authenticate("unknown", "pass1").await),
+        Err(LoginError::UserNotFound)
+    ));
+    assert!(matches!(
+        expect_verification(users.authenticate(" ", "pass1").await),
+        Err(LoginError::UserNotFound)
+    ));
+    assert!(matches!(
+        expect_verification(users.authenticate("nopass", "pass1").await),
+        Err(LoginError::MissingPassword)
+    ));
+    assert!(expect_verification(users.authenticate("user1", "pass1").await).is_ok());
+    assert!(matches!(
+        expect_verification(users.authenticate("user1", "wrong").await),
+        Err(LoginError::LockedOut { .. })
+    ));
+    assert!(matches!(
+        expect_verification(users.authenticate("user1", "pass1").await),
+        Err(LoginError::LockedOut { .. })
+    ));
+}
+
+#[rocket::async_test]
+async fn dummy_hash_is_computed_once() {
+    let hasher = CountingHasher::default();
+    let hashes = hasher.hashes.clone();
+
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_password_hasher(hasher)
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    for _ in 0..3 {
+        assert!(matches!(
+            users.authenticate("unknown", "pass1").await,
+            Err(LoginError::UserNotFound)
+        ));
+    }
+
+    assert_eq!(hashes.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,139 @@
+use rocket::{
+    local::asynchronous::Client,
+    time::{Duration, OffsetDateTime},
+};
+use rocket_identity::{
+    stores::memory::MemoryStore,
+    two_factor::{Totp, TotpError},
+    Identity, LoginError, Services, TwoFactorError, User,
+};
+
+#[rocket::async_test]
+async fn login_requires_second_factor_once_enabled() {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .with_totp(Totp::new("Test"))
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+
+    let user = User::with_username("user1");
+    users
+        .add_user(&user, Some("pass1"))
+        .await
+        .expect("Could not add user");
+
+    let totp = Totp::new("Test");
+    let secret = users
+        .create_totp_secret(&user)
+        .await
+        .expect("Could not create secret");
+
+    assert!(users
+        .totp_provisioning_uri(&user, &secret)
+        .starts_with("otpauth://totp/Test:user1?secret="));
+
+    // Not enabled until confirmed
+    assert!(users.authenticate("user1", "pass1").await.is_ok());
+
+    let now = OffsetDateTime::now_utc();
+    users
+        .enable_two_factor(&user, &totp.code_at(&secret, now))
+        .await
+        .expect("Could not enable two-factor authentication");
+
+    let Err(LoginError::TwoFactorRequired { user }) = users.authenticate("user1", "pass1").await
+    else {
+        panic!("Expected a second factor to be required");
+    };
+
+    // The code used to enable two-factor authentication cannot be replayed
+    assert!(matches!(
+        users
+            .verify_two_factor_code(&user, &totp.code_at(&secret, now))
+            .await,
+        Err(TwoFactorError::InvalidCode)
+    ));
+
+    let next = now + Duration::seconds(30);
+    users
+        .verify_two_factor_code(&user, &totp.code_at(&secret, next))
+        .await
+        .expect("Could not verify code");
+}
+
+#[rocket::async_test]
+async fn recovery_codes_are_single_use() {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .build();
+
+    let rocket = rocket::build().attach(Identity::fairing(config));
+    let client = Client::tracked(rocket)
+        .await
+        .expect("Failed to acquire Client");
+    let users = client.rocket().user_repository().await;
+    let totp = Totp::default();
+
+    let user = User::with_username("user1");
+    users
+        .add_user(&user, Some("pass1"))
+        .await
+        .expect("Could not add user");
+
+    let secret = users.create_totp_secret(&user).await.unwrap();
+    users
+        .enable_two_factor(&user, &totp.code_at(&secret, OffsetDateTime::now_utc()))
+        .await
+        .unwrap();
+
+    let codes = users
+        .generate_recovery_codes(&user, 3)
+        .await
+        .expect("Could not generate recovery codes");
+    assert_eq!(codes.len(), 3);
+    assert_eq!(users.count_recovery_codes(&user).await.unwrap(), 3);
+
+    assert!(matches!(
+        users.redeem_recovery_code(&user, "aaaaa-aaaaa").await,
+        Err(TwoFactorError::InvalidCode)
+    ));
+
+    // Case and separators are ignored
+    let code = codes[1].to_uppercase().replace('-', " ");
+    users
+        .redeem_recovery_code(&user, &code)
+        .await
+        .expect("Could not redeem recovery code");
+    assert_eq!(users.count_recovery_codes(&user).await.unwrap(), 2);
+
+    assert!(matches!(
+        users.redeem_recovery_code(&user, &codes[1]).await,
+        Err(TwoFactorError::InvalidCode)
+    ));
+
+    // Regenerating invalidates the previous codes
+    let new_codes = users.generate_recovery_codes(&user, 2).await.unwrap();
+    assert_eq!(users.count_recovery_codes(&user).await.unwrap(), 2);
+    assert!(matches!(
+        users.redeem_recovery_code(&user, &codes[0]).await,
+        Err(TwoFactorError::InvalidCode)
+    ));
+    assert!(users
+        .redeem_recovery_code(&user, &new_codes[0])
+        .await
+        .is_ok());
+}
+
+#[test]
+fn totp_digits_are_validated() {
+    assert_eq!(Totp::new("Test").with_digits(8).unwrap().digits(), 8);
+    assert!(matches!(
+        Totp::new("Test").with_digits(20),
+        Err(TotpError::InvalidDigits(20))
+    ));
+}
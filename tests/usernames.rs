@@ -0,0 +1,65 @@
+use rocket::local::asynchronous::Client;
+use rocket_identity::{stores::memory::MemoryStore, Identity, Services, User};
+
+async fn setup_client() -> Client {
+    let config = Identity::config()
+        .with_user_store(MemoryStore::new())
+        .build();
+
+    Client::tracked(rocket::build().attach(Identity::fairing(config)))
+        .await
+        .expect("Failed to acquire Client")
+}
+
+#[rocket::async_test]
+async fn usernames_are_normalized_on_every_path() {
+    let client = setup_client().await;
+    let users = client.rocket().user_repository().await;
+
+    users
+        .add_user(&User::with_username("Alice "), Some("password1"))
+        .await
+        .expect("Could not add user");
+
+    let user = users.find_by_username("alice").await.unwrap().unwrap();
+    assert_eq!(user.username, "alice");
+
+    let user = users.authenticate(" ALICE", "password1").await.unwrap();
+    assert_eq!(user.username, "alice");
+
+    assert!(users
+        .add_user(&User::with_username("alice"), Some("password2"))
+        .await
+        .is_err());
+}
+
+#[rocket::async_test]
+async fn stored_usernames_can_be_normalized() {
+    let client = setup_client().await;
+    let users = client.rocket().user_repository().await;
+
+    // Users stored before the username policy applied
+    {
+        let mut user_store = users.user_store.write().await;
+        for username in ["Bob", "Carol", "carol "] {
+            user_store
+                .add_user(&User::with_username(username), None)
+                .await
+                .expect("Could not add user");
+        }
+    }
+
+    // Users are only found by their normalized username
+    assert!(users.find_by_username("Bob").await.unwrap().is_none());
+
+    let mut conflicts = users.normalize_usernames().await.unwrap();
+    conflicts.sort();
+    assert!(conflicts == ["Carol"] || conflicts == ["carol "]);
+
+    let user = users.find_by_username("Bob").await.unwrap().unwrap();
+    assert_eq!(user.username, "bob");
+    assert!(users.find_by_username("carol").await.unwrap().is_some());
+
+    // Normalizing again only reports the conflict
+    assert_eq!(users.normalize_usernames().await.unwrap(), conflicts);
+}